@@ -406,6 +406,24 @@ protover_get_supported_protocols(void)
     "Relay=1-2";
 }
 
+/** Return the canonical string containing the list of protocols that we
+ * support, as protover_get_supported_protocols() does. If <b>out_len</b> is
+ * not NULL, set it to the length of that string, not including the
+ * terminating NUL.
+ *
+ * The returned string is static: the caller must not free it.
+ *
+ * C_RUST_COUPLED: src/rust/protover/ffi.rs `protover_get_protocol_list`
+ **/
+const char *
+protover_get_protocol_list(int *out_len)
+{
+  const char *protocols = protover_get_supported_protocols();
+  if (out_len)
+    *out_len = (int)strlen(protocols);
+  return protocols;
+}
+
 /** The protocols from protover_get_supported_protocols(), as parsed into a
  * list of proto_entry_t values. Access this via
  * get_supported_protocol_list. */
@@ -473,7 +491,7 @@ encode_protocol_list(const smartlist_t *sl)
 
 /* We treat any protocol list with more than this many subprotocols in it
  * as a DoS attempt. */
-/// C_RUST_COUPLED: src/rust/protover/protover.rs
+/// C_RUST_COUPLED: src/rust/protover/lib.rs
 ///                 `MAX_PROTOCOLS_TO_EXPAND`
 static const int MAX_PROTOCOLS_TO_EXPAND = (1<<16);
 
@@ -736,39 +754,38 @@ protover_compute_vote(const smartlist_t *list_of_proto_strings,
   return result;
 }
 
-/** Return true if every protocol version described in the string <b>s</b> is
- * one that we support, and false otherwise.  If <b>missing_out</b> is
- * provided, set it to the list of protocols we do not support.
+/** Helper for protover_all_supported() and
+ * protover_is_supported_here_with_detail(): return 1 if every protocol
+ * version in the parsed list <b>entries</b> is one that we support, and 0
+ * otherwise.  If <b>missing_out</b> is provided, and we return 0, set it to
+ * the list of protocols we do not support.
+ *
+ * If <b>reject_unknown</b> is true, return -1, and leave <b>missing_out</b>
+ * unchanged, if <b>entries</b> names a protocol we do not recognize.
  *
  * NOTE: This is quadratic, but we don't do it much: only a few times per
  * consensus. Checking signatures should be way more expensive than this
  * ever would be.
  **/
-int
-protover_all_supported(const char *s, char **missing_out)
+static int
+protocol_list_all_supported(const smartlist_t *entries, int reject_unknown,
+                            char **missing_out)
 {
   int all_supported = 1;
   smartlist_t *missing_some;
   smartlist_t *missing_completely;
   smartlist_t *missing_all;
 
-  if (!s) {
-    return 1;
-  }
-
-  smartlist_t *entries = parse_protocol_list(s);
-  if (BUG(entries == NULL)) {
-    log_warn(LD_NET, "Received an unparseable protocol list %s"
-             " from the consensus", escaped(s));
-    return 1;
-  }
-
   missing_some = smartlist_new();
   missing_completely = smartlist_new();
 
   SMARTLIST_FOREACH_BEGIN(entries, const proto_entry_t *, ent) {
     protocol_type_t tp;
     if (str_to_protocol_type(ent->name, &tp) < 0) {
+      if (reject_unknown) {
+        all_supported = -1;
+        break;
+      }
       if (smartlist_len(ent->ranges)) {
         goto unsupported;
       }
@@ -849,7 +866,7 @@ protover_all_supported(const char *s, char **missing_out)
   smartlist_add_all(missing_all, missing_some);
   smartlist_add_all(missing_all, missing_completely);
 
-  if (missing_out && !all_supported) {
+  if (missing_out && all_supported == 0) {
     tor_assert(smartlist_len(missing_all) != 0);
     *missing_out = encode_protocol_list(missing_all);
   }
@@ -858,12 +875,71 @@ protover_all_supported(const char *s, char **missing_out)
   smartlist_free(missing_completely);
   smartlist_free(missing_all);
 
+  return all_supported;
+}
+
+/** Return true if every protocol version described in the string <b>s</b> is
+ * one that we support, and false otherwise.  If <b>missing_out</b> is
+ * provided, set it to the list of protocols we do not support.
+ **/
+int
+protover_all_supported(const char *s, char **missing_out)
+{
+  int all_supported;
+
+  if (!s) {
+    return 1;
+  }
+
+  smartlist_t *entries = parse_protocol_list(s);
+  if (BUG(entries == NULL)) {
+    log_warn(LD_NET, "Received an unparseable protocol list %s"
+             " from the consensus", escaped(s));
+    return 1;
+  }
+
+  all_supported = protocol_list_all_supported(entries, 0, missing_out);
+
   SMARTLIST_FOREACH(entries, proto_entry_t *, ent, proto_entry_free(ent));
   smartlist_free(entries);
 
   return all_supported;
 }
 
+/** Return 1 if we support every protocol version in the string
+ * <b>required</b>, or if it is NULL or lists no protocols.  Otherwise, return
+ * 0, and if <b>missing_out</b> is provided, set it to a newly allocated list
+ * of the protocol versions we do not support.
+ *
+ * Return -1, and leave <b>missing_out</b> unchanged, if <b>required</b> is
+ * not a valid protocol list, or names a protocol we do not recognize.
+ *
+ * C_RUST_COUPLED: src/rust/protover/ffi.rs
+ *                 `protover_is_supported_here_with_detail`
+ **/
+int
+protover_is_supported_here_with_detail(const char *required,
+                                       char **missing_out)
+{
+  int result;
+
+  if (!required) {
+    return 1;
+  }
+
+  smartlist_t *entries = parse_protocol_list(required);
+  if (!entries) {
+    return -1;
+  }
+
+  result = protocol_list_all_supported(entries, 1, missing_out);
+
+  SMARTLIST_FOREACH(entries, proto_entry_t *, ent, proto_entry_free(ent));
+  smartlist_free(entries);
+
+  return result;
+}
+
 /** Helper: Given a list of proto_entry_t, return true iff
  * <b>pr</b>=<b>ver</b> is included in that list. */
 static int
@@ -51,8 +51,11 @@ typedef enum protocol_type_t {
 
 bool protover_contains_long_protocol_names(const char *s);
 int protover_all_supported(const char *s, char **missing);
+int protover_is_supported_here_with_detail(const char *required,
+                                           char **missing_out);
 int protover_is_supported_here(protocol_type_t pr, uint32_t ver);
 const char *protover_get_supported_protocols(void);
+const char *protover_get_protocol_list(int *out_len);
 
 char *protover_compute_vote(const struct smartlist_t *list_of_proto_strings,
                             int threshold);
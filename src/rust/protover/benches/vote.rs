@@ -0,0 +1,184 @@
+// Copyright (c) 2016-2019, The Tor Project, Inc. */
+// See LICENSE for licensing information */
+//! Benchmarks for protocol voting and parsing.
+//!
+//! These use a small timing harness rather than an external benchmarking
+//! crate like criterion.  Tor builds its Rust code offline, using only the
+//! crates vendored in the tor-rust-dependencies submodule, and criterion
+//! isn't vendored there.  (Its dependencies also need a newer Rust than our
+//! minimum version.)
+//!
+//! Like criterion, the harness warms up first, then reports the median of
+//! several samples, so one slow sample doesn't skew the comparison with the
+//! baseline loop.  Run them with:
+//!
+//! ```text
+//! cargo bench -p protover --features test_linking_hack
+//! ```
+
+extern crate protover;
+
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use protover::protoset::ProtoSet;
+use protover::protoset::Version;
+use protover::ProtoEntry;
+use protover::ProtoverVote;
+use protover::UnvalidatedProtoEntry;
+
+/// "proto" lines advertised by various tor releases, and a few from relays
+/// running unknown or future versions.
+const PROTO_LINES: &[&str] = &[
+    "Cons=1-2 Desc=1-2 DirCache=1-2 HSDir=1-2 HSIntro=3-4 HSRend=1-2 Link=1-5 \
+     LinkAuth=1,3 Microdesc=1-2 Relay=1-2",
+    "Cons=1-2 Desc=1-2 DirCache=1-2 HSDir=1-2 HSIntro=3-4 HSRend=1-2 Link=1-5 \
+     LinkAuth=1,3 Microdesc=1-2 Padding=2 Relay=1-2",
+    "Cons=1-2 Desc=1-2 DirCache=1-2 FlowCtrl=1 HSDir=1-2 HSIntro=3-5 HSRend=1-2 \
+     Link=1-5 LinkAuth=1,3 Microdesc=1-2 Padding=2 Relay=1-3",
+    "Cons=1 Desc=1 DirCache=1 HSDir=1 HSIntro=3 HSRend=1 Link=1-4 LinkAuth=1 \
+     Microdesc=1 Relay=1-2",
+    "Cons=1-2 Desc=1-2 DirCache=1-2 HSDir=1-2 HSIntro=3-4 HSRend=1-2 Link=1-5 \
+     LinkAuth=3 Microdesc=1-2 Padding=2 Relay=1-2 Wombat=1-9",
+];
+
+/// The number of timed samples taken by `bench()`.
+const SAMPLES: usize = 11;
+
+/// Run `f` repeatedly for `target`, and return the mean time per iteration,
+/// and the number of iterations.
+fn sample<F>(f: &mut F, target: Duration, checksum: &mut usize) -> (Duration, u32)
+where
+    F: FnMut() -> usize,
+{
+    let start = Instant::now();
+    let mut iterations: u32 = 0;
+
+    while start.elapsed() < target {
+        // Use the result, so the work can't be optimised away.
+        *checksum = checksum.wrapping_add(f());
+        iterations += 1;
+    }
+    (start.elapsed() / iterations, iterations)
+}
+
+/// Warm up by running `f` for a tenth of a second, then take `SAMPLES`
+/// samples of about 50 milliseconds each, and print the median time per
+/// iteration.
+fn bench<F: FnMut() -> usize>(name: &str, mut f: F) -> Duration {
+    let mut checksum: usize = 0;
+    let mut iterations: u32 = 0;
+
+    sample(&mut f, Duration::from_millis(100), &mut checksum);
+
+    let mut samples: Vec<Duration> = Vec::with_capacity(SAMPLES);
+    for _ in 0..SAMPLES {
+        let (per_iteration, count) = sample(&mut f, Duration::from_millis(50), &mut checksum);
+        samples.push(per_iteration);
+        iterations += count;
+    }
+    samples.sort();
+
+    let median = samples[SAMPLES / 2];
+    println!(
+        "{:<40} {:>12?}/iter (median of {} samples, range {:?} to {:?}, \
+         {} iterations, checksum {})",
+        name,
+        median,
+        SAMPLES,
+        samples[0],
+        samples[SAMPLES - 1],
+        iterations,
+        checksum
+    );
+    median
+}
+
+/// Convert `duration` to seconds.  (`Duration::as_secs_f64()` needs a newer
+/// Rust than our minimum version.)
+fn seconds(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) / 1e9
+}
+
+/// Make `count` votes, cycling through `PROTO_LINES`.
+fn votes(count: usize) -> Vec<UnvalidatedProtoEntry> {
+    PROTO_LINES
+        .iter()
+        .cycle()
+        .take(count)
+        .map(|line| line.parse().unwrap())
+        .collect()
+}
+
+/// A straightforward vote using nested loops, as a baseline for
+/// `ProtoverVote::compute()`.
+fn baseline_vote(proto_entries: &[UnvalidatedProtoEntry], threshold: usize) -> usize {
+    let mut counts: BTreeMap<String, BTreeMap<Version, usize>> = BTreeMap::new();
+
+    for entry in proto_entries {
+        for (protocol, versions) in entry.iter() {
+            let protocol_counts = counts
+                .entry(protocol.to_string())
+                .or_insert(BTreeMap::new());
+
+            for version in versions.to_vec() {
+                *protocol_counts.entry(version).or_insert(0) += 1;
+            }
+        }
+    }
+
+    let mut output: Vec<String> = Vec::new();
+
+    for (protocol, protocol_counts) in counts {
+        let voted: Vec<Version> = protocol_counts
+            .into_iter()
+            .filter(|&(_, count)| count >= threshold)
+            .map(|(version, _)| version)
+            .collect();
+
+        if !voted.is_empty() {
+            output.push(format!(
+                "{}={}",
+                protocol,
+                ProtoSet::from(voted).to_string()
+            ));
+        }
+    }
+    output.join(" ").len()
+}
+
+fn main() {
+    for &count in [3, 30, 100].iter() {
+        let proto_entries = votes(count);
+        let threshold = count / 2 + 1;
+
+        let compute = bench(&format!("ProtoverVote::compute ({} voters)", count), || {
+            ProtoverVote::compute(&proto_entries, &threshold)
+                .to_string()
+                .len()
+        });
+        let baseline = bench(&format!("baseline vote loop ({} voters)", count), || {
+            baseline_vote(&proto_entries, threshold)
+        });
+
+        println!(
+            "{:<40} {:>12.2}x\n",
+            "compute speedup over baseline",
+            seconds(baseline) / seconds(compute)
+        );
+    }
+
+    let relay: UnvalidatedProtoEntry = PROTO_LINES[4].parse().unwrap();
+
+    bench("UnvalidatedProtoEntry::all_supported", || {
+        relay
+            .all_supported()
+            .map_or(0, |unsupported| unsupported.protocol_count())
+    });
+    bench("ProtoEntry::from_str", || {
+        PROTO_LINES[2]
+            .parse::<ProtoEntry>()
+            .unwrap()
+            .protocol_count()
+    });
+}
@@ -7,18 +7,48 @@
 use std::fmt;
 use std::fmt::Display;
 
+/// The maximum number of bytes of an invalid token which are kept in a
+/// `ProtoverError::UnparseableVersionString`.
+///
+/// Invalid input can be arbitrarily long, so we don't copy all of it.
+pub const MAX_UNPARSEABLE_TOKEN_LENGTH: usize = 50;
+
 /// All errors which may occur during protover parsing routines.
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+///
+/// This type is `Clone`, but not `Copy`, because
+/// `UnparseableVersionString` owns a copy of the invalid token.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
 #[allow(missing_docs)] // See Display impl for error descriptions
 pub enum ProtoverError {
     Overlap,
     LowGreaterThanHigh,
     Unparseable,
     ExceedsMax,
+    // ProtoEntry::from_str() returns ExceedsMax when an entry has too many
+    // versions, because the whole entry is invalid. The bounded ProtoSet
+    // methods return this variant instead: their sets are valid, and can
+    // still be parsed and stored as ranges, they are just too large to
+    // expand. A separate variant lets callers tell those cases apart.
     ExceedsExpansionLimit,
     UnknownProtocol,
     ExceedsNameLimit,
     InvalidProtocol,
+    EmptyInput,
+    UnparseableVersionString(String),
+}
+
+impl ProtoverError {
+    /// Create a `ProtoverError::UnparseableVersionString` for `token`,
+    /// truncated to at most `MAX_UNPARSEABLE_TOKEN_LENGTH` bytes.
+    pub(crate) fn unparseable_version_string(token: &str) -> ProtoverError {
+        let mut end: usize = token.len().min(MAX_UNPARSEABLE_TOKEN_LENGTH);
+
+        // Don't split a multi-byte character.
+        while !token.is_char_boundary(end) {
+            end -= 1;
+        }
+        ProtoverError::UnparseableVersionString(token[..end].to_string())
+    }
 }
 
 /// Descriptive error messages for `ProtoverError` variants.
@@ -52,6 +82,10 @@ impl Display for ProtoverError {
             ProtoverError::InvalidProtocol => {
                 write!(f, "A protocol name includes invalid characters.")
             }
+            ProtoverError::EmptyInput => write!(f, "The protover string was empty."),
+            ProtoverError::UnparseableVersionString(ref token) => {
+                write!(f, "The protover version {:?} was unparseable.", token)
+            }
         }
     }
 }
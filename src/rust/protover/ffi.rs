@@ -36,6 +36,35 @@ fn translate_to_rust(c_proto: uint32_t) -> Result<Protocol, ProtoverError> {
     }
 }
 
+/// Conversion from a C smartlist of protocol list strings, such as the votes
+/// passed to `protover_compute_vote`, into `UnvalidatedProtoEntry`s.
+///
+/// This is an extension trait, rather than an inherent method on `Stringlist`,
+/// because the `smartlist` crate can't depend on `protover`: `protover`
+/// already depends on `smartlist`.
+trait ToUnvalidatedProtoEntries {
+    /// Parse each string in this list into an `UnvalidatedProtoEntry`,
+    /// skipping any which fail to parse.
+    fn to_unvalidated_proto_entries(&self) -> Vec<UnvalidatedProtoEntry>;
+}
+
+impl ToUnvalidatedProtoEntries for Stringlist {
+    fn to_unvalidated_proto_entries(&self) -> Vec<UnvalidatedProtoEntry> {
+        let data: Vec<String> = self.get_list();
+        let mut proto_entries: Vec<UnvalidatedProtoEntry> = Vec::new();
+
+        for datum in data {
+            // C_RUST_DIFFERS: The C logs a warning for each unparseable vote.
+            let entry: UnvalidatedProtoEntry = match datum.parse() {
+                Ok(n) => n,
+                Err(_) => continue,
+            };
+            proto_entries.push(entry);
+        }
+        proto_entries
+    }
+}
+
 /// Provide an interface for C to translate arguments and return types for
 /// protover::all_supported
 #[no_mangle]
@@ -178,6 +207,33 @@ pub extern "C" fn protover_get_supported_protocols() -> *const c_char {
     supported.as_ptr()
 }
 
+/// Provide an interface for C to translate arguments and return types for
+/// protover::get_supported_protocols, also returning the length of the
+/// protocol list.
+///
+/// If `out_len` is not NULL, it is set to the length of the returned string,
+/// not including the terminating NUL.
+///
+/// The returned string is static, so the caller does not own it, and must not
+/// free it.
+///
+/// C_RUST_COUPLED: protover.c `protover_get_protocol_list`
+//
+// This is only called from C, which must pass NULL or a valid pointer.
+// `out_len` is checked for NULL before it is dereferenced.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn protover_get_protocol_list(out_len: *mut c_int) -> *const c_char {
+    let supported: &'static CStr = get_supported_protocols_cstr();
+
+    if !out_len.is_null() {
+        // Dereference of raw pointer requires an unsafe block. The pointer is
+        // checked above to ensure it is not null.
+        unsafe { *out_len = supported.to_bytes().len() as c_int };
+    }
+    supported.as_ptr()
+}
+
 /// Provide an interface for C to translate arguments and return types for
 /// protover::compute_vote
 //
@@ -190,17 +246,9 @@ pub extern "C" fn protover_compute_vote(list: *const Stringlist, threshold: c_in
 
     // Dereference of raw pointer requires an unsafe block. The pointer is
     // checked above to ensure it is not null.
-    let data: Vec<String> = unsafe { (*list).get_list() };
+    let proto_entries: Vec<UnvalidatedProtoEntry> =
+        unsafe { (*list).to_unvalidated_proto_entries() };
     let hold: usize = threshold as usize;
-    let mut proto_entries: Vec<UnvalidatedProtoEntry> = Vec::new();
-
-    for datum in data {
-        let entry: UnvalidatedProtoEntry = match datum.parse() {
-            Ok(n) => n,
-            Err(_) => continue,
-        };
-        proto_entries.push(entry);
-    }
     let vote: UnvalidatedProtoEntry = ProtoverVote::compute(&proto_entries, &hold);
 
     allocate_and_copy_string(&vote.to_string())
@@ -220,6 +268,74 @@ pub extern "C" fn protover_is_supported_here(c_protocol: uint32_t, version: uint
     return if is_supported { 1 } else { 0 };
 }
 
+/// Provide an interface for C to translate arguments and return types for
+/// protover::ProtoEntry::check_required_protocols, checking the `required`
+/// protocols against the ones we support.
+///
+/// Returns 1 if we support every required protocol version, or if `required`
+/// is NULL or lists no protocols.  Otherwise, returns 0, and if `missing_out`
+/// is not NULL, sets it to a newly allocated protocol list of the versions we
+/// are missing.
+///
+/// Returns -1, and leaves `missing_out` unchanged, if `required` is not a
+/// valid protocol list, or names a protocol we do not recognise.
+///
+/// C_RUST_COUPLED: protover.c `protover_is_supported_here_with_detail`
+//
+// Like the other functions in this module, this is only called from C, which
+// must pass NULL or valid pointers.  Both pointers are checked for NULL before
+// they are dereferenced.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+#[no_mangle]
+pub extern "C" fn protover_is_supported_here_with_detail(
+    c_required: *const c_char,
+    missing_out: *mut *mut c_char,
+) -> c_int {
+    if c_required.is_null() {
+        return 1;
+    }
+
+    // Require an unsafe block to read the version from a C string. The pointer
+    // is checked above to ensure it is not null.
+    let c_str: &CStr = unsafe { CStr::from_ptr(c_required) };
+
+    let missing: ProtoEntry = match unsupported_here(c_str) {
+        Ok(n) => n,
+        Err(_) => return -1,
+    };
+
+    if missing.is_empty() {
+        return 1;
+    }
+    if !missing_out.is_null() {
+        let ptr = allocate_and_copy_string(&missing.to_string());
+        unsafe { *missing_out = ptr };
+    }
+    0
+}
+
+/// Get the protocol versions in the `required` protocol list which we don't
+/// support, for `protover_is_supported_here_with_detail`.
+///
+/// An empty `required` list doesn't require anything, so it is all supported.
+fn unsupported_here(required: &CStr) -> Result<ProtoEntry, ProtoverError> {
+    let required: ProtoEntry = match required.to_str() {
+        Ok(s) => match s.parse() {
+            Ok(n) => n,
+            Err(ProtoverError::EmptyInput) => return Ok(ProtoEntry::default()),
+            Err(e) => return Err(e),
+        },
+        Err(_) => return Err(ProtoverError::Unparseable),
+    };
+    let supported: ProtoEntry = ProtoEntry::supported()?;
+    let mut missing: ProtoEntry = ProtoEntry::default();
+
+    for (protocol, versions) in supported.check_required_protocols(&required) {
+        missing.insert(protocol, versions);
+    }
+    Ok(missing)
+}
+
 /// Provide an interface for C to translate arguments and return types for
 /// protover::compute_for_old_tor
 #[no_mangle]
@@ -245,3 +361,124 @@ pub extern "C" fn protover_compute_for_old_tor(version: *const c_char) -> *const
     supported = compute_for_old_tor_cstr(&version);
     supported.as_ptr()
 }
+
+#[cfg(test)]
+#[allow(deprecated)] // The tests build Stringlists like C does, using the fields.
+mod test {
+    use std::ffi::CString;
+
+    use super::*;
+
+    /// Parse `votes` via a `Stringlist`, as C would pass them to
+    /// `protover_compute_vote`, and then vote on them with `threshold`.
+    fn compute_vote_via_stringlist(votes: &[&str], threshold: usize) -> String {
+        let c_strings: Vec<CString> = votes.iter().map(|v| CString::new(*v).unwrap()).collect();
+        let pointers: Vec<*const c_char> = c_strings.iter().map(|s| s.as_ptr()).collect();
+        let list = Stringlist {
+            list: pointers.as_ptr(),
+            num_used: pointers.len() as c_int,
+            capacity: pointers.len() as c_int,
+        };
+        let proto_entries: Vec<UnvalidatedProtoEntry> = list.to_unvalidated_proto_entries();
+
+        ProtoverVote::compute(&proto_entries, &threshold).to_string()
+    }
+
+    #[test]
+    fn test_translate_to_rust_matches_protocol_variants() {
+        for (index, protocol) in Protocol::variants().iter().enumerate() {
+            assert_eq!(Ok(protocol.clone()), translate_to_rust(index as uint32_t));
+        }
+        let past_the_end = Protocol::variants().len() as uint32_t;
+
+        assert_eq!(
+            Err(ProtoverError::UnknownProtocol),
+            translate_to_rust(past_the_end)
+        );
+    }
+
+    #[test]
+    fn test_protover_get_protocol_list() {
+        let mut len: c_int = -1;
+        let ptr = protover_get_protocol_list(&mut len);
+        let protocols: &CStr = unsafe { CStr::from_ptr(ptr) };
+
+        assert_eq!(get_supported_protocols_cstr(), protocols);
+        assert_eq!(protocols.to_bytes().len() as c_int, len);
+
+        let ptr_without_len = protover_get_protocol_list(::std::ptr::null_mut());
+        assert_eq!(ptr, ptr_without_len);
+    }
+
+    #[test]
+    fn test_unsupported_here() {
+        fn missing(required: &[u8]) -> Result<String, ProtoverError> {
+            let c_required = CString::new(required).unwrap();
+
+            unsupported_here(&c_required).map(|entry| entry.to_string())
+        }
+
+        assert_eq!(Ok("".to_string()), missing(b""));
+        assert_eq!(Ok("".to_string()), missing(b"Cons=1-2 Link=1-5"));
+        assert_eq!(
+            Ok("Cons=3 Link=6-9".to_string()),
+            missing(b"Cons=1-3 Link=1-9")
+        );
+
+        assert_eq!(Err(ProtoverError::UnknownProtocol), missing(b"Fribble=1"));
+        assert_eq!(Err(ProtoverError::LowGreaterThanHigh), missing(b"Link=5-1"));
+        assert_eq!(Err(ProtoverError::Unparseable), missing(b"Link=\xff"));
+    }
+
+    // Mirrors test_protover_vote() in src/test/test_protover.c.
+    #[test]
+    fn test_stringlist_to_unvalidated_proto_entries_vote() {
+        assert_eq!("", compute_vote_via_stringlist(&[], 1));
+
+        let votes = &["Foo=1-10,500 Bar=1,3-7,8"];
+        assert_eq!(
+            "Bar=1,3-8 Foo=1-10,500",
+            compute_vote_via_stringlist(votes, 1)
+        );
+
+        let votes = &[
+            "Foo=1-10,500 Bar=1,3-7,8",
+            "Quux=123-456,78 Bar=2-6,8 Foo=9",
+        ];
+        assert_eq!(
+            "Bar=1-8 Foo=1-10,500 Quux=78,123-456",
+            compute_vote_via_stringlist(votes, 1)
+        );
+        assert_eq!("Bar=3-6,8 Foo=9", compute_vote_via_stringlist(votes, 2));
+        assert_eq!("", compute_vote_via_stringlist(votes, 3));
+
+        assert_eq!("", compute_vote_via_stringlist(&["Faux=10-5"], 1));
+        assert_eq!("", compute_vote_via_stringlist(&["Faux=-0"], 1));
+        assert_eq!(
+            "Sleen=1-500",
+            compute_vote_via_stringlist(&["Sleen=1-500"], 1)
+        );
+        assert_eq!(
+            "Sleen=4294967294",
+            compute_vote_via_stringlist(&["Sleen=4294967294"], 1)
+        );
+    }
+
+    #[test]
+    fn test_stringlist_to_unvalidated_proto_entries_skips_unparseable() {
+        let c_strings: Vec<CString> = ["Link=1-3", "Faux=10-5", "Cons=1 Desc"]
+            .iter()
+            .map(|v| CString::new(*v).unwrap())
+            .collect();
+        let pointers: Vec<*const c_char> = c_strings.iter().map(|s| s.as_ptr()).collect();
+        let list = Stringlist {
+            list: pointers.as_ptr(),
+            num_used: pointers.len() as c_int,
+            capacity: pointers.len() as c_int,
+        };
+        let proto_entries: Vec<UnvalidatedProtoEntry> = list.to_unvalidated_proto_entries();
+
+        assert_eq!(1, proto_entries.len());
+        assert_eq!("Link=1-3", proto_entries[0].to_string());
+    }
+}
@@ -34,7 +34,25 @@ extern crate tor_util;
 
 pub mod errors;
 pub mod ffi;
+#[macro_use]
 pub mod protoset;
 mod protover;
+#[cfg(test)]
+#[path = "tests/rng/mod.rs"]
+mod rng;
 
 pub use protover::*;
+
+/// The maximum number of subprotocol version numbers we will attempt to expand
+/// before concluding that someone is trying to DoS us.
+///
+/// Protocol lists are sent to us by other relays, and version ranges like
+/// `"Link=1-4294967294"` are cheap to send but very expensive to expand into
+/// individual versions.  Anything which would expand more than this many
+/// versions (for example, while counting votes) is treated as a DoS attempt.
+///
+/// This is shared by `protover.rs` and `protoset.rs`, so that the two
+/// modules cannot disagree about the limit.
+///
+/// C_RUST_COUPLED: protover.c `MAX_PROTOCOLS_TO_EXPAND`, which is `(1<<16)`
+pub(crate) const MAX_PROTOCOLS_TO_EXPAND: usize = 1 << 16;
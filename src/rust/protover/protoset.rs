@@ -5,12 +5,19 @@
 //! Sets for lazily storing ordered, non-overlapping ranges of integers.
 
 use std::cmp;
+use std::collections::BTreeSet;
+use std::convert::TryFrom;
+use std::fmt;
 use std::iter;
+use std::mem;
+use std::ops::Deref;
+use std::ops::RangeInclusive;
 use std::slice;
 use std::str::FromStr;
 use std::u32;
 
 use errors::ProtoverError;
+use MAX_PROTOCOLS_TO_EXPAND;
 
 /// A single version number.
 pub type Version = u32;
@@ -49,16 +56,65 @@ pub type Version = u32;
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
 pub struct ProtoSet {
     pub(crate) pairs: Vec<(Version, Version)>,
+    /// The total number of `Version`s in `pairs`, kept up to date by every
+    /// method which changes `pairs`, so that `len()` takes O(1) time.
+    pub(crate) count: usize,
 }
 
 impl Default for ProtoSet {
     fn default() -> Self {
         let pairs: Vec<(Version, Version)> = Vec::new();
 
-        ProtoSet { pairs }
+        ProtoSet { pairs, count: 0 }
     }
 }
 
+/// Return the index of the first pair in the sorted `pairs` for which `pred`
+/// is false, or `pairs.len()` if it is true for all of them.
+///
+/// `pred` must be true for a prefix of `pairs`, and false for the rest.
+///
+/// This is `slice::partition_point()`, which needs Rust 1.52.
+fn partition_point<P>(pairs: &[(Version, Version)], pred: P) -> usize
+where
+    P: Fn(&(Version, Version)) -> bool,
+{
+    // pred never says Equal, so the search always fails, at the boundary.
+    pairs
+        .binary_search_by(|pair| {
+            if pred(pair) {
+                cmp::Ordering::Less
+            } else {
+                cmp::Ordering::Greater
+            }
+        })
+        .unwrap_or_else(|index| index)
+}
+
+/// Sort some `(low, high)` pairs, and merge any which overlap or are adjacent.
+fn sort_and_merge(pairs: &mut Vec<(Version, Version)>) {
+    pairs.sort_unstable();
+
+    // dedup_by() passes each pair along with the last pair it kept, and
+    // drops the pair if we merge it into that one.
+    pairs.dedup_by(|&mut (low, high), last| {
+        if low <= last.1.saturating_add(1) {
+            last.1 = cmp::max(last.1, high);
+            true
+        } else {
+            false
+        }
+    });
+}
+
+/// Count the `Version`s in some `(low, high)` pairs, which must not overlap.
+fn count_versions(pairs: &[(Version, Version)]) -> usize {
+    pairs
+        .iter()
+        .map(|&(low, high)| (high as usize).saturating_sub(low as usize) + 1)
+        .sum()
+}
+
 impl<'a> ProtoSet {
     /// Create a new `ProtoSet` from a slice of `(low, high)` pairs.
     ///
@@ -66,57 +122,22 @@ impl<'a> ProtoSet {
     ///
     /// We do not assume the input pairs are deduplicated or ordered.
     pub fn from_slice(low_high_pairs: &'a [(Version, Version)]) -> Result<Self, ProtoverError> {
-        let mut pairs: Vec<(Version, Version)> = Vec::with_capacity(low_high_pairs.len());
-
-        for &(low, high) in low_high_pairs {
-            pairs.push((low, high));
-        }
-        // Sort the pairs without reallocation and remove all duplicate pairs.
-        pairs.sort_unstable();
-        pairs.dedup();
-
-        ProtoSet { pairs }.is_ok()
-    }
-}
-
-/// Expand this `ProtoSet` to a `Vec` of all its `Version`s.
-///
-/// # Examples
-///
-/// ```
-/// use std::str::FromStr;
-/// use protover::protoset::ProtoSet;
-/// use protover::protoset::Version;
-/// # use protover::errors::ProtoverError;
-///
-/// # fn do_test() -> Result<Vec<Version>, ProtoverError> {
-/// let protoset: ProtoSet = ProtoSet::from_str("3-5,21")?;
-/// let versions: Vec<Version> = protoset.into();
-///
-/// assert_eq!(&versions[..], &[3, 4, 5, 21]);
-/// #
-/// # Ok(versions)
-/// # }
-/// # fn main() { do_test(); }  // wrap the test so we can use the ? operator
-/// ```
-impl Into<Vec<Version>> for ProtoSet {
-    fn into(self) -> Vec<Version> {
-        let mut versions: Vec<Version> = Vec::new();
-
-        for &(low, high) in self.iter() {
-            versions.extend(low..high + 1);
-        }
-        versions
-    }
-}
-
-impl ProtoSet {
-    /// Get an iterator over the `(low, high)` `pairs` in this `ProtoSet`.
-    pub fn iter(&self) -> slice::Iter<(Version, Version)> {
-        self.pairs.iter()
+        ProtoSet::from_pairs(low_high_pairs.iter().cloned())
     }
 
-    /// Expand this `ProtoSet` into a `Vec` of all its `Version`s.
+    /// Create a new `ProtoSet` from a `Vec` of `(low, high)` pairs which are
+    /// already sorted in ascending order.
+    ///
+    /// Unlike `ProtoSet::from_slice()`, this does not sort or deduplicate the
+    /// pairs, so it does not need to copy them.
+    ///
+    /// # Errors
+    ///
+    /// * `ProtoverError::Overlap`: if the pairs are not sorted, or if any of
+    ///   them overlap,
+    /// * `ProtoverError::LowGreaterThanHigh`: if a `low` is higher than its
+    ///   `high`, and
+    /// * `ProtoverError::ExceedsMax`: if any `Version` is `u32::MAX`.
     ///
     /// # Examples
     ///
@@ -124,98 +145,147 @@ impl ProtoSet {
     /// # use protover::errors::ProtoverError;
     /// use protover::protoset::ProtoSet;
     ///
-    /// # fn do_test() -> Result<bool, ProtoverError> {
-    /// let protoset: ProtoSet = "3-5,9".parse()?;
-    ///
-    /// assert_eq!(protoset.expand(), vec![3, 4, 5, 9]);
-    ///
-    /// let protoset: ProtoSet = "1,3,5-7".parse()?;
+    /// # fn do_test() -> Result<ProtoSet, ProtoverError> {
+    /// let protoset: ProtoSet = ProtoSet::from_sorted(vec![(1, 3), (7, 9)])?;
     ///
-    /// assert_eq!(protoset.expand(), vec![1, 3, 5, 6, 7]);
-    /// #
-    /// # Ok(true)
+    /// assert_eq!(protoset.to_string(), "1-3,7-9".to_string());
+    /// assert_eq!(ProtoSet::from_sorted(vec![(7, 9), (1, 3)]), Err(ProtoverError::Overlap));
+    /// # Ok(protoset)
     /// # }
-    /// # fn main() { do_test(); }  // wrap the test so we can use the ? operator
+    /// # fn main() { do_test().unwrap(); }  // wrap the test so we can use the ? operator
     /// ```
-    pub fn expand(self) -> Vec<Version> {
-        self.into()
+    pub fn from_sorted(pairs: Vec<(Version, Version)>) -> Result<Self, ProtoverError> {
+        ProtoSet::from_sorted_unchecked(pairs).is_ok()
     }
 
-    pub fn len(&self) -> usize {
-        let mut length: usize = 0;
-
-        for &(low, high) in self.iter() {
-            length += (high as usize - low as usize) + 1;
-        }
+    /// Create a new `ProtoSet` from a `Vec` of `(low, high)` pairs, without
+    /// checking them at all.
+    ///
+    /// # Warning
+    ///
+    /// The caller must make sure that the pairs are sorted, non-overlapping,
+    /// and otherwise valid, as they would be for `ProtoSet::from_sorted()`.
+    /// Other `ProtoSet` methods may give wrong answers if they are not.
+    pub(crate) fn from_sorted_unchecked(pairs: Vec<(Version, Version)>) -> Self {
+        let count: usize = count_versions(&pairs);
 
-        length
+        ProtoSet { pairs, count }
     }
 
-    /// Check that this `ProtoSet` is well-formed.
+    /// Create a new `ProtoSet` from a `Vec` of `(low, high)` pairs which may
+    /// be in any order, and may overlap or be adjacent.
     ///
-    /// This is automatically called in `ProtoSet::from_str()`.
+    /// The pairs are sorted and merged before the `ProtoSet` is built, so it
+    /// uses as few ranges as possible, like `ProtoSet::normalize()`.
     ///
-    /// # Errors
+    /// # Warning
     ///
-    /// * `ProtoverError::LowGreaterThanHigh`: if its `pairs` were not
-    ///   well-formed, i.e. a `low` in a `(low, high)` was higher than the
-    ///   previous `high`,
-    /// * `ProtoverError::Overlap`: if one or more of the `pairs` are
-    ///   overlapping,
-    /// * `ProtoverError::ExceedsMax`: if the number of versions when expanded
-    ///   would exceed `MAX_PROTOCOLS_TO_EXPAND`, and
+    /// The caller must make sure that each `low` is less than or equal to
+    /// its `high`, and that no bound is `u32::MAX`.
+    pub(crate) fn from_unsorted(mut pairs: Vec<(Version, Version)>) -> Self {
+        sort_and_merge(&mut pairs);
+        ProtoSet::from_sorted_unchecked(pairs)
+    }
+
+    /// Create a new `ProtoSet` from a `Vec` of `(low, high)` pairs which may
+    /// be in any order, like `ProtoSet::from_unsorted()`, but check that the
+    /// pairs are valid.
     ///
-    /// # Returns
+    /// # Errors
     ///
-    /// A `Result` whose `Ok` is this `Protoset`, and whose `Err` is one of the
-    /// errors enumerated in the Errors section above.
-    fn is_ok(self) -> Result<ProtoSet, ProtoverError> {
-        let mut last_high: Version = 0;
-
-        for &(low, high) in self.iter() {
-            if low == u32::MAX || high == u32::MAX {
-                return Err(ProtoverError::ExceedsMax);
-            }
-            if low <= last_high {
-                return Err(ProtoverError::Overlap);
-            } else if low > high {
-                return Err(ProtoverError::LowGreaterThanHigh);
-            }
-            last_high = high;
+    /// * `ProtoverError::LowGreaterThanHigh`: if a `low` is greater than its
+    ///   `high`, and
+    /// * `ProtoverError::Overlap` or `ProtoverError::ExceedsMax`: if a bound
+    ///   is 0 or `u32::MAX`, like `ProtoSet::from_sorted()`.
+    fn try_from_unsorted(pairs: Vec<(Version, Version)>) -> Result<Self, ProtoverError> {
+        if pairs.iter().any(|&(low, high)| low > high) {
+            return Err(ProtoverError::LowGreaterThanHigh);
         }
-
-        Ok(self)
+        // Merging can't hide a 0 or u32::MAX bound, so is_ok() finds them.
+        ProtoSet::from_unsorted(pairs).is_ok()
     }
 
-    /// Determine if this `ProtoSet` contains no `Version`s.
+    /// Create a new `ProtoSet` from any iterable of `(low, high)` pairs.
     ///
-    /// # Returns
+    /// This is the same as `ProtoSet::from_slice()`, but does not require the
+    /// caller to collect the pairs first.
     ///
-    /// * `true` if this `ProtoSet`'s length is zero, and
-    /// * `false` otherwise.
+    /// # Inputs
+    ///
+    /// We do not assume the input pairs are deduplicated or ordered.
     ///
     /// # Examples
     ///
     /// ```
+    /// use std::iter;
     /// use protover::protoset::ProtoSet;
+    /// # use protover::errors::ProtoverError;
     ///
-    /// let protoset: ProtoSet = ProtoSet::default();
+    /// # fn do_test() -> Result<ProtoSet, ProtoverError> {
+    /// let pairs = iter::once((7, 9)).chain(vec![(1, 3), (7, 9)]);
+    /// let protoset: ProtoSet = ProtoSet::from_pairs(pairs)?;
     ///
-    /// assert!(protoset.is_empty());
+    /// assert_eq!(protoset.to_string(), "1-3,7-9".to_string());
+    /// # Ok(protoset)
+    /// # }
+    /// # fn main() { do_test(); }  // wrap the test so we can use the ? operator
     /// ```
-    pub fn is_empty(&self) -> bool {
-        self.pairs.len() == 0
+    pub fn from_pairs(
+        low_high_pairs: impl IntoIterator<Item = (Version, Version)>,
+    ) -> Result<Self, ProtoverError> {
+        let mut pairs: Vec<(Version, Version)> = low_high_pairs.into_iter().collect();
+
+        // Sort the pairs without reallocation and remove all duplicate pairs.
+        pairs.sort_unstable();
+        pairs.dedup();
+
+        ProtoSet::from_sorted(pairs)
     }
 
-    /// Determine if `version` is included within this `ProtoSet`.
+    /// Create a new `ProtoSet` from single `Version`s which are strictly
+    /// increasing, merging consecutive versions into ranges in a single pass.
+    ///
+    /// # Errors
+    ///
+    /// The same errors as `ProtoSet::from_sorted()`.  The caller must make
+    /// sure the versions are strictly increasing, or the result is
+    /// unspecified.
+    pub(crate) fn from_sorted_versions(
+        versions: impl IntoIterator<Item = Version>,
+    ) -> Result<Self, ProtoverError> {
+        let mut pairs: Vec<(Version, Version)> = Vec::new();
+
+        for version in versions {
+            if let Some(last) = pairs.last_mut() {
+                // The versions are strictly increasing, so this can't overflow.
+                if last.1 + 1 == version {
+                    last.1 = version;
+                    continue;
+                }
+            }
+            pairs.push((version, version));
+        }
+        ProtoSet::from_sorted(pairs)
+    }
+
+    /// Create a new `ProtoSet` from any iterable of single `Version`s.
+    ///
+    /// Unlike `ProtoSet::from(Vec<Version>)`, this does not require the
+    /// caller to collect the versions first, and it reports invalid versions,
+    /// rather than returning an empty `ProtoSet`.
     ///
     /// # Inputs
     ///
-    /// * `version`: a `Version`.
+    /// We do not assume the input versions are deduplicated or ordered.
     ///
     /// # Returns
     ///
-    /// `true` if the `version` is contained within this set; `false` otherwise.
+    /// A normalised `ProtoSet`.
+    ///
+    /// # Errors
+    ///
+    /// * `ProtoverError::Overlap`: if any version is 0, and
+    /// * `ProtoverError::ExceedsMax`: if any version is `u32::MAX`.
     ///
     /// # Examples
     ///
@@ -224,26 +294,40 @@ impl ProtoSet {
     /// use protover::protoset::ProtoSet;
     ///
     /// # fn do_test() -> Result<ProtoSet, ProtoverError> {
-    /// let protoset: ProtoSet = ProtoSet::from_slice(&[(0, 5), (7, 9), (13, 14)])?;
+    /// let protoset = ProtoSet::from_expansion((1u32..10).filter(|&v| v % 2 == 0))?;
+    /// assert_eq!(protoset.to_string(), "2,4,6,8");
     ///
-    /// assert!(protoset.contains(&5));
-    /// assert!(!protoset.contains(&10));
-    /// #
+    /// let protoset = ProtoSet::from_expansion(vec![5, 3, 4, 3, 9])?;
+    /// assert_eq!(protoset.to_string(), "3-5,9");
+    ///
+    /// // Version 0 is not valid.
+    /// assert_eq!(ProtoSet::from_expansion((0u32..10).filter(|&v| v % 2 == 0)),
+    ///            Err(ProtoverError::Overlap));
     /// # Ok(protoset)
     /// # }
-    /// # fn main() { do_test(); }  // wrap the test so we can use the ? operator
+    /// # fn main() { do_test().unwrap(); }  // wrap the test so we can use the ? operator
     /// ```
-    pub fn contains(&self, version: &Version) -> bool {
-        for &(low, high) in self.iter() {
-            if low <= *version && *version <= high {
-                return true;
-            }
-        }
-        false
+    pub fn from_expansion(
+        expanded: impl IntoIterator<Item = Version>,
+    ) -> Result<Self, ProtoverError> {
+        let pairs: Vec<(Version, Version)> = expanded.into_iter().map(|v| (v, v)).collect();
+
+        ProtoSet::try_from_unsorted(pairs)
     }
 
-    /// Returns all the `Version`s in `self` which are not also in the `other`
-    /// `ProtoSet`.
+    /// Create a new `ProtoSet` from sorted `(low, high)` pairs, like
+    /// `ProtoSet::from_sorted()`, but reject sets which would be too large to
+    /// expand.
+    ///
+    /// Use this for `ProtoSet`s which will be expanded later, so that the DoS
+    /// limit is enforced when they are stored, rather than when they are
+    /// used.
+    ///
+    /// # Errors
+    ///
+    /// * `ProtoverError::ExceedsExpansionLimit`: if the set would contain
+    ///   more than `MAX_PROTOCOLS_TO_EXPAND` versions.
+    /// * Any error returned by `ProtoSet::from_sorted()`.
     ///
     /// # Examples
     ///
@@ -251,114 +335,2101 @@ impl ProtoSet {
     /// # use protover::errors::ProtoverError;
     /// use protover::protoset::ProtoSet;
     ///
-    /// # fn do_test() -> Result<bool, ProtoverError> {
-    /// let protoset: ProtoSet = "1,3-6,10-12,15-16".parse()?;
-    /// let other: ProtoSet = "2,5-7,9-11,14-20".parse()?;
-    ///
-    /// let subset: ProtoSet = protoset.and_not_in(&other);
+    /// # fn do_test() -> Result<ProtoSet, ProtoverError> {
+    /// let protoset: ProtoSet = ProtoSet::from_sorted_bounded(vec![(1, 3), (7, 65535)])?;
     ///
-    /// assert_eq!(subset.expand(), vec![1, 3, 4, 12]);
-    /// #
-    /// # Ok(true)
+    /// assert_eq!(protoset.len(), 65532);
+    /// assert_eq!(ProtoSet::from_sorted_bounded(vec![(1, 65537)]),
+    ///            Err(ProtoverError::ExceedsExpansionLimit));
+    /// # Ok(protoset)
     /// # }
-    /// # fn main() { do_test(); }  // wrap the test so we can use the ? operator
+    /// # fn main() { do_test().unwrap(); }  // wrap the test so we can use the ? operator
     /// ```
-    pub fn and_not_in(&self, other: &Self) -> Self {
-        if self.is_empty() || other.is_empty() {
-            return self.clone();
-        }
-
-        let pairs = self.iter().flat_map(|&(lo, hi)| {
-            let the_end = (hi + 1, hi + 1); // special case to mark the end of the range.
-            let excluded_ranges = other
-                .iter()
-                .cloned() // have to be owned tuples, to match iter::once(the_end).
-                .skip_while(move|&(_, hi2)| hi2 < lo) // skip the non-overlapping ranges.
-                .take_while(move|&(lo2, _)| lo2 <= hi) // take all the overlapping ones.
-                .chain(iter::once(the_end));
-
-            let mut nextlo = lo;
-            excluded_ranges.filter_map(move |(excluded_lo, excluded_hi)| {
-                let pair = if nextlo < excluded_lo {
-                    Some((nextlo, excluded_lo - 1))
-                } else {
-                    None
-                };
-                nextlo = cmp::min(excluded_hi, u32::MAX - 1) + 1;
-                pair
-            })
-        });
-
-        let pairs = pairs.collect();
-        ProtoSet::is_ok(ProtoSet { pairs }).expect("should be already sorted")
+    pub fn from_sorted_bounded(pairs: Vec<(Version, Version)>) -> Result<Self, ProtoverError> {
+        ProtoSet::from_sorted(pairs)?.check_expansion_limit()
     }
-}
-
-impl FromStr for ProtoSet {
-    type Err = ProtoverError;
 
-    /// Parse the unique version numbers supported by a subprotocol from a string.
-    ///
-    /// # Inputs
-    ///
-    /// * `version_string`, a string comprised of "[0-9,-]"
-    ///
-    /// # Returns
-    ///
-    /// A `Result` whose `Ok` value is a `ProtoSet` holding all of the unique
-    /// version numbers.
+    /// Parse a `ProtoSet` like `ProtoSet::from_str()`, but reject sets which
+    /// would be too large to expand.
     ///
-    /// The returned `Result`'s `Err` value is an `ProtoverError` appropriate to
-    /// the error.
+    /// Plain `ProtoSet::from_str()` accepts huge ranges like
+    /// `"1-4294967294"`, because they are valid in protocol lists, and they
+    /// are cheap to store as ranges.
     ///
     /// # Errors
     ///
-    /// This function will error if:
-    ///
-    /// * the `version_string` is an equals (`"="`) sign,
-    /// * the expansion of a version range produces an error (see
-    ///   `expand_version_range`),
-    /// * any single version number is not parseable as an `u32` in radix 10, or
-    /// * there are greater than 2^16 version numbers to expand.
+    /// * `ProtoverError::ExceedsExpansionLimit`: if the set would contain
+    ///   more than `MAX_PROTOCOLS_TO_EXPAND` versions.
+    /// * Any error returned by `ProtoSet::from_str()`.
     ///
     /// # Examples
     ///
     /// ```
-    /// use std::str::FromStr;
-    ///
-    /// use protover::errors::ProtoverError;
+    /// # use protover::errors::ProtoverError;
     /// use protover::protoset::ProtoSet;
     ///
     /// # fn do_test() -> Result<ProtoSet, ProtoverError> {
-    /// let protoset: ProtoSet = ProtoSet::from_str("2-5,8")?;
+    /// let protoset: ProtoSet = ProtoSet::from_str_bounded("1-5,10")?;
     ///
-    /// assert!(protoset.contains(&5));
-    /// assert!(!protoset.contains(&10));
+    /// assert!("1-4294967294".parse::<ProtoSet>().is_ok());
+    /// assert_eq!(ProtoSet::from_str_bounded("1-4294967294"),
+    ///            Err(ProtoverError::ExceedsExpansionLimit));
+    /// # Ok(protoset)
+    /// # }
+    /// # fn main() { do_test().unwrap(); }  // wrap the test so we can use the ? operator
+    /// ```
+    pub fn from_str_bounded(version_string: &str) -> Result<Self, ProtoverError> {
+        version_string.parse::<ProtoSet>()?.check_expansion_limit()
+    }
+
+    /// Parse a `ProtoSet` like `ProtoSet::from_str()`, but explicitly reject
+    /// any leading or trailing whitespace before parsing.
     ///
-    /// // We can also equivalently call `ProtoSet::from_str` by doing (all
-    /// // implementations of `FromStr` can be called this way, this one isn't
-    /// // special):
-    /// let protoset: ProtoSet = "4-6,12".parse()?;
+    /// `ProtoSet::from_str()` does not trim its input, so padded strings
+    /// already fail to parse as version numbers. This function makes that
+    /// boundary part of the contract, rather than a side-effect of integer
+    /// parsing.
     ///
-    /// // Calling it (either way) can take really large ranges (up to `u32::MAX`):
-    /// let protoset: ProtoSet = "1-70000".parse()?;
-    /// let protoset: ProtoSet = "1-4294967296".parse()?;
+    /// # Errors
     ///
-    /// // There are lots of ways to get an `Err` from this function.  Here are
-    /// // a few:
-    /// assert_eq!(Err(ProtoverError::Unparseable), ProtoSet::from_str("="));
-    /// assert_eq!(Err(ProtoverError::Unparseable), ProtoSet::from_str("-"));
-    /// assert_eq!(Err(ProtoverError::Unparseable), ProtoSet::from_str("not_an_int"));
-    /// assert_eq!(Err(ProtoverError::Unparseable), ProtoSet::from_str("3-"));
-    /// assert_eq!(Err(ProtoverError::Unparseable), ProtoSet::from_str("1-,4"));
+    /// * `ProtoverError::Unparseable`: if `version_string` has leading or
+    ///   trailing whitespace.
+    /// * Any error returned by `ProtoSet::from_str()`.
     ///
-    /// // An empty string is, however, legal, and results in an
-    /// // empty `ProtoSet`:
-    /// assert_eq!(Ok(ProtoSet::default()), ProtoSet::from_str(""));
-    /// #
+    /// # Examples
+    ///
+    /// ```
+    /// # use protover::errors::ProtoverError;
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// # fn do_test() -> Result<ProtoSet, ProtoverError> {
+    /// let protoset: ProtoSet = ProtoSet::from_str_strict("1-3,5")?;
+    ///
+    /// assert_eq!(ProtoSet::from_str_strict(" 1-3"), Err(ProtoverError::Unparseable));
+    /// assert_eq!(ProtoSet::from_str_strict("1-3\n"), Err(ProtoverError::Unparseable));
     /// # Ok(protoset)
     /// # }
-    /// # fn main() { do_test(); }  // wrap the test so we can use the ? operator
+    /// # fn main() { do_test().unwrap(); }  // wrap the test so we can use the ? operator
+    /// ```
+    pub fn from_str_strict(version_string: &str) -> Result<Self, ProtoverError> {
+        if version_string != version_string.trim() {
+            return Err(ProtoverError::Unparseable);
+        }
+        version_string.parse()
+    }
+
+    /// Return this `ProtoSet`, or `ProtoverError::ExceedsExpansionLimit` if it
+    /// contains more than `MAX_PROTOCOLS_TO_EXPAND` versions.
+    fn check_expansion_limit(self) -> Result<Self, ProtoverError> {
+        if self.len() > MAX_PROTOCOLS_TO_EXPAND {
+            return Err(ProtoverError::ExceedsExpansionLimit);
+        }
+        Ok(self)
+    }
+}
+
+/// Expand this `ProtoSet` to a `Vec` of all its `Version`s.
+///
+/// # Examples
+///
+/// ```
+/// use std::str::FromStr;
+/// use protover::protoset::ProtoSet;
+/// use protover::protoset::Version;
+/// # use protover::errors::ProtoverError;
+///
+/// # fn do_test() -> Result<Vec<Version>, ProtoverError> {
+/// let protoset: ProtoSet = ProtoSet::from_str("3-5,21")?;
+/// let versions: Vec<Version> = protoset.into();
+///
+/// assert_eq!(&versions[..], &[3, 4, 5, 21]);
+/// #
+/// # Ok(versions)
+/// # }
+/// # fn main() { do_test(); }  // wrap the test so we can use the ? operator
+/// ```
+impl From<ProtoSet> for Vec<Version> {
+    fn from(protoset: ProtoSet) -> Vec<Version> {
+        let mut versions: Vec<Version> = Vec::new();
+
+        for &(low, high) in protoset.iter() {
+            versions.extend(low..high + 1);
+        }
+        versions
+    }
+}
+
+/// Expand a borrowed `ProtoSet` to a `Vec` of all its `Version`s, like
+/// `ProtoSet::to_vec()`.
+///
+/// # Examples
+///
+/// ```
+/// use protover::protoset::ProtoSet;
+/// use protover::protoset::Version;
+///
+/// let protoset: ProtoSet = "3-5,21".parse().unwrap();
+/// let versions: Vec<Version> = Vec::from(&protoset);
+///
+/// assert_eq!(versions, vec![3, 4, 5, 21]);
+/// assert_eq!(ProtoSet::from(versions), protoset);
+/// ```
+impl<'a> From<&'a ProtoSet> for Vec<Version> {
+    fn from(protoset: &'a ProtoSet) -> Vec<Version> {
+        protoset.to_vec()
+    }
+}
+
+/// Unwrap a `ProtoSet` into its sorted, non-overlapping `(low, high)` pairs.
+///
+/// # Examples
+///
+/// ```
+/// use protover::protoset::ProtoSet;
+/// use protover::protoset::Version;
+///
+/// let protoset: ProtoSet = "3-5,21".parse().unwrap();
+/// let pairs: Vec<(Version, Version)> = protoset.into();
+///
+/// assert_eq!(pairs, vec![(3, 5), (21, 21)]);
+/// ```
+impl From<ProtoSet> for Vec<(Version, Version)> {
+    fn from(protoset: ProtoSet) -> Vec<(Version, Version)> {
+        protoset.pairs
+    }
+}
+
+impl ProtoSet {
+    /// Get an iterator over the `(low, high)` `pairs` in this `ProtoSet`.
+    pub fn iter(&self) -> slice::Iter<(Version, Version)> {
+        self.pairs.iter()
+    }
+
+    /// Iterate over the `(low, high)` inclusive ranges in this `ProtoSet`, in
+    /// ascending order.
+    ///
+    /// This is the same as `iter()`, but yields owned tuples rather than
+    /// references.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protover::errors::ProtoverError;
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// # fn do_test() -> Result<bool, ProtoverError> {
+    /// let protoset: ProtoSet = "1-3,7".parse()?;
+    ///
+    /// for (low, high) in protoset.iter_ranges() {
+    ///     assert!(low <= high);
+    /// }
+    ///
+    /// let widths: Vec<u32> = protoset.iter_ranges().map(|(low, high)| high - low + 1).collect();
+    ///
+    /// assert_eq!(widths, vec![3, 1]);
+    /// #
+    /// # Ok(true)
+    /// # }
+    /// # fn main() { do_test().unwrap(); }  // wrap the test so we can use the ? operator
+    /// ```
+    pub fn iter_ranges<'a>(&'a self) -> impl Iterator<Item = (Version, Version)> + 'a {
+        self.pairs.iter().cloned()
+    }
+
+    /// Copy the ranges in this `ProtoSet` into a `Vec` of Rust
+    /// `RangeInclusive`s, in ascending order.
+    ///
+    /// This is the reverse of `TryFrom<Vec<RangeInclusive<Version>>>`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::convert::TryFrom;
+    /// # use protover::errors::ProtoverError;
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// # fn do_test() -> Result<bool, ProtoverError> {
+    /// let protoset: ProtoSet = "1-3,5,8-20".parse()?;
+    ///
+    /// assert_eq!(protoset.to_ranges(), vec![1..=3, 5..=5, 8..=20]);
+    /// assert_eq!(ProtoSet::try_from(protoset.to_ranges()), Ok(protoset));
+    /// #
+    /// # Ok(true)
+    /// # }
+    /// # fn main() { do_test().unwrap(); }  // wrap the test so we can use the ? operator
+    /// ```
+    pub fn to_ranges(&self) -> Vec<RangeInclusive<Version>> {
+        self.pairs.iter().map(|&(low, high)| low..=high).collect()
+    }
+
+    /// Expand this `ProtoSet` into a `Vec` of all its `Version`s.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protover::errors::ProtoverError;
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// # fn do_test() -> Result<bool, ProtoverError> {
+    /// let protoset: ProtoSet = "3-5,9".parse()?;
+    ///
+    /// assert_eq!(protoset.expand(), vec![3, 4, 5, 9]);
+    ///
+    /// let protoset: ProtoSet = "1,3,5-7".parse()?;
+    ///
+    /// assert_eq!(protoset.expand(), vec![1, 3, 5, 6, 7]);
+    /// #
+    /// # Ok(true)
+    /// # }
+    /// # fn main() { do_test(); }  // wrap the test so we can use the ? operator
+    /// ```
+    pub fn expand(self) -> Vec<Version> {
+        self.into()
+    }
+
+    /// Expand this `ProtoSet` into a `Vec` of all its `Version`s, without
+    /// consuming it.
+    ///
+    /// Like `expand()`, this does not check how many versions there are, so
+    /// it should not be used on untrusted input.  See `expand_bounded()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protover::errors::ProtoverError;
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// # fn do_test() -> Result<bool, ProtoverError> {
+    /// let protoset: ProtoSet = "3-5,9".parse()?;
+    ///
+    /// assert_eq!(protoset.to_vec(), vec![3, 4, 5, 9]);
+    /// assert_eq!(protoset.to_string(), "3-5,9");
+    /// #
+    /// # Ok(true)
+    /// # }
+    /// # fn main() { do_test(); }  // wrap the test so we can use the ? operator
+    /// ```
+    pub fn to_vec(&self) -> Vec<Version> {
+        let mut versions: Vec<Version> = Vec::with_capacity(self.len());
+
+        for &(low, high) in self.iter() {
+            versions.extend(low..high + 1);
+        }
+        versions
+    }
+
+    /// Expand this `ProtoSet` into a `Vec` of all its `Version`s, in
+    /// ascending order.
+    ///
+    /// This is identical to `to_vec()`, since a `ProtoSet` always stores its
+    /// ranges in ascending order, but it makes that guarantee explicit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protover::errors::ProtoverError;
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// # fn do_test() -> Result<bool, ProtoverError> {
+    /// let protoset: ProtoSet = ProtoSet::from_slice(&[(9, 9), (3, 5)])?;
+    ///
+    /// assert_eq!(protoset.to_sorted_vec(), vec![3, 4, 5, 9]);
+    /// #
+    /// # Ok(true)
+    /// # }
+    /// # fn main() { do_test(); }  // wrap the test so we can use the ? operator
+    /// ```
+    pub fn to_sorted_vec(&self) -> Vec<Version> {
+        self.to_vec()
+    }
+
+    /// Iterate over all the `Version`s in this `ProtoSet`, from highest to
+    /// lowest.
+    ///
+    /// This walks the ranges backwards, and yields the versions lazily, so it
+    /// does not allocate, even for very large sets.  It yields the same
+    /// sequence as `expand().into_iter().rev()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protover::errors::ProtoverError;
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// # fn do_test() -> Result<bool, ProtoverError> {
+    /// let protoset: ProtoSet = "1,3,5-7".parse()?;
+    ///
+    /// assert_eq!(protoset.expand_reverse().collect::<Vec<_>>(), vec![7, 6, 5, 3, 1]);
+    ///
+    /// // Find the highest version which is a multiple of 3.
+    /// assert_eq!(protoset.expand_reverse().find(|v| v % 3 == 0), Some(6));
+    /// #
+    /// # Ok(true)
+    /// # }
+    /// # fn main() { do_test(); }  // wrap the test so we can use the ? operator
+    /// ```
+    pub fn expand_reverse(&self) -> impl DoubleEndedIterator<Item = Version> + '_ {
+        self.pairs
+            .iter()
+            .rev()
+            .flat_map(|&(low, high)| (low..=high).rev())
+    }
+
+    /// Iterate over the `Version`s from `low` to `high` (inclusive) which are
+    /// in this `ProtoSet`, in ascending order.
+    ///
+    /// The ranges are clamped to the window before they are expanded, so
+    /// this takes O(ranges + output) time, rather than expanding the whole
+    /// set and filtering it.
+    ///
+    /// If `low` is greater than `high`, the range is empty, so nothing is
+    /// yielded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protover::errors::ProtoverError;
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// # fn do_test() -> Result<bool, ProtoverError> {
+    /// let protoset: ProtoSet = "1-3,5,8-4294967294".parse()?;
+    ///
+    /// assert_eq!(protoset.expand_in_range(2, 9).collect::<Vec<_>>(), vec![2, 3, 5, 8, 9]);
+    /// #
+    /// # Ok(true)
+    /// # }
+    /// # fn main() { do_test(); }  // wrap the test so we can use the ? operator
+    /// ```
+    pub fn expand_in_range(
+        &self,
+        low: Version,
+        high: Version,
+    ) -> impl Iterator<Item = Version> + '_ {
+        // The first range which ends at or above low.
+        let start: usize = partition_point(&self.pairs, |&(_, h)| h < low);
+
+        self.pairs[start..]
+            .iter()
+            .take_while(move |&&(range_low, _)| range_low <= high)
+            .flat_map(move |&(range_low, range_high)| {
+                // Empty if low > high.
+                cmp::max(range_low, low)..=cmp::min(range_high, high)
+            })
+    }
+
+    /// Expand this `ProtoSet` into a `Vec` of all its `Version`s, as long as
+    /// there are no more than `limit` of them.
+    ///
+    /// Unlike `expand()`, this checks the number of versions before
+    /// allocating, so it is safe to call on untrusted input.
+    ///
+    /// # Errors
+    ///
+    /// * `ProtoverError::ExceedsExpansionLimit`: if this `ProtoSet` contains
+    ///   more than `limit` versions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protover::errors::ProtoverError;
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// # fn do_test() -> Result<bool, ProtoverError> {
+    /// let protoset: ProtoSet = "3-5,9".parse()?;
+    ///
+    /// assert_eq!(protoset.expand_bounded(4), Ok(vec![3, 4, 5, 9]));
+    /// assert_eq!(protoset.expand_bounded(3), Err(ProtoverError::ExceedsExpansionLimit));
+    /// #
+    /// # Ok(true)
+    /// # }
+    /// # fn main() { do_test(); }  // wrap the test so we can use the ? operator
+    /// ```
+    pub fn expand_bounded(&self, limit: usize) -> Result<Vec<Version>, ProtoverError> {
+        if self.len() > limit {
+            return Err(ProtoverError::ExceedsExpansionLimit);
+        }
+        Ok(self.to_vec())
+    }
+
+    /// Remove all the `Version`s from this `ProtoSet`, and return a lazy
+    /// iterator over them, in ascending order.
+    ///
+    /// This `ProtoSet` is empty as soon as `drain()` returns, even if the
+    /// iterator is not fully consumed.  Unlike `expand()`, the versions are
+    /// never collected into a `Vec`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protover::errors::ProtoverError;
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// # fn do_test() -> Result<bool, ProtoverError> {
+    /// let mut protoset: ProtoSet = "3-5,9".parse()?;
+    ///
+    /// assert_eq!(protoset.drain().sum::<u32>(), 21);
+    /// assert!(protoset.is_empty());
+    /// #
+    /// # Ok(true)
+    /// # }
+    /// # fn main() { do_test(); }  // wrap the test so we can use the ? operator
+    /// ```
+    pub fn drain(&mut self) -> impl Iterator<Item = Version> {
+        self.count = 0;
+        let pairs: Vec<(Version, Version)> = mem::replace(&mut self.pairs, Vec::new());
+
+        self.debug_verify_invariants();
+        pairs.into_iter().flat_map(|(low, high)| low..high + 1)
+    }
+
+    /// Get the `n`th smallest `Version` in this `ProtoSet`, counting from zero.
+    ///
+    /// This walks the `(low, high)` pairs rather than expanding them, so it
+    /// takes O(number of ranges) time.
+    ///
+    /// `ProtoSet` does not implement `Index<usize>`, because the versions
+    /// inside each range are not stored anywhere that a reference could
+    /// point to.
+    ///
+    /// # Returns
+    ///
+    /// The `Version`, or `None` if `n` is not less than `self.len()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protover::errors::ProtoverError;
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// # fn do_test() -> Result<bool, ProtoverError> {
+    /// let protoset: ProtoSet = "3-5,9".parse()?;
+    ///
+    /// assert_eq!(protoset.get(0), Some(3));
+    /// assert_eq!(protoset.get(2), Some(5));
+    /// assert_eq!(protoset.get(3), Some(9));
+    /// assert_eq!(protoset.get(4), None);
+    /// #
+    /// # Ok(true)
+    /// # }
+    /// # fn main() { do_test(); }  // wrap the test so we can use the ? operator
+    /// ```
+    pub fn get(&self, n: usize) -> Option<Version> {
+        let mut remaining: usize = n;
+
+        for &(low, high) in self.iter() {
+            let width: usize = (high - low) as usize + 1;
+
+            if remaining < width {
+                return Some(low + remaining as Version);
+            }
+            remaining -= width;
+        }
+        None
+    }
+
+    /// Get the number of `Version`s in this `ProtoSet`.
+    ///
+    /// This is cached, so it takes O(1) time.
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Get the number of `(low, high)` ranges stored in this `ProtoSet`.
+    ///
+    /// Adjacent ranges are counted separately, unless the set has been
+    /// normalized.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protover::errors::ProtoverError;
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// # fn do_test() -> Result<bool, ProtoverError> {
+    /// let mut protoset: ProtoSet = "1-3,4,9".parse()?;
+    ///
+    /// assert_eq!(protoset.count_pairs(), 3);
+    /// protoset.normalize();
+    /// assert_eq!(protoset.count_pairs(), 2);
+    /// #
+    /// # Ok(true)
+    /// # }
+    /// # fn main() { do_test().unwrap(); }  // wrap the test so we can use the ? operator
+    /// ```
+    pub fn count_pairs(&self) -> usize {
+        self.pairs.len()
+    }
+
+    /// Get the `n`th `(low, high)` range in this `ProtoSet`, counting from 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protover::errors::ProtoverError;
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// # fn do_test() -> Result<bool, ProtoverError> {
+    /// let protoset: ProtoSet = "1-3,5,9-10".parse()?;
+    ///
+    /// assert_eq!(protoset.nth_range(1), Some((5, 5)));
+    /// assert_eq!(protoset.nth_range(3), None);
+    /// #
+    /// # Ok(true)
+    /// # }
+    /// # fn main() { do_test().unwrap(); }  // wrap the test so we can use the ? operator
+    /// ```
+    pub fn nth_range(&self, n: usize) -> Option<(Version, Version)> {
+        self.pairs.get(n).cloned()
+    }
+
+    /// Get the lowest `(low, high)` range in this `ProtoSet`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protover::errors::ProtoverError;
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// # fn do_test() -> Result<bool, ProtoverError> {
+    /// let protoset: ProtoSet = "1-3,5,9-10".parse()?;
+    ///
+    /// assert_eq!(protoset.first_range(), Some((1, 3)));
+    /// assert_eq!(ProtoSet::default().first_range(), None);
+    /// #
+    /// # Ok(true)
+    /// # }
+    /// # fn main() { do_test().unwrap(); }  // wrap the test so we can use the ? operator
+    /// ```
+    pub fn first_range(&self) -> Option<(Version, Version)> {
+        self.pairs.first().cloned()
+    }
+
+    /// Get the highest `(low, high)` range in this `ProtoSet`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protover::errors::ProtoverError;
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// # fn do_test() -> Result<bool, ProtoverError> {
+    /// let protoset: ProtoSet = "1-3,5,9-10".parse()?;
+    ///
+    /// assert_eq!(protoset.last_range(), Some((9, 10)));
+    /// assert_eq!(ProtoSet::default().last_range(), None);
+    /// #
+    /// # Ok(true)
+    /// # }
+    /// # fn main() { do_test().unwrap(); }  // wrap the test so we can use the ? operator
+    /// ```
+    pub fn last_range(&self) -> Option<(Version, Version)> {
+        self.pairs.last().cloned()
+    }
+
+    /// Check that the cached `len()` matches the number of `Version`s in
+    /// `pairs`, counted from scratch.
+    #[cfg(test)]
+    pub(crate) fn assert_count_is_consistent(&self) {
+        assert_eq!(count_versions(&self.pairs), self.count, "{:?}", self.pairs);
+    }
+
+    /// Check the internal invariants of this `ProtoSet`: its pairs are
+    /// sorted and don't overlap, no `low` is greater than its `high`, and no
+    /// bound is 0 or `u32::MAX`.
+    ///
+    /// `is_ok()` uses this to validate parsed sets, so parsing and the
+    /// debug checks after mutations enforce the same rules.
+    ///
+    /// # Errors
+    ///
+    /// The first problem found is reported, checking each pair in order:
+    ///
+    /// * `ProtoverError::ExceedsMax`: if either bound is `u32::MAX`,
+    /// * `ProtoverError::Overlap`: if the pairs are not sorted, or overlap,
+    ///   or a `low` is 0, and
+    /// * `ProtoverError::LowGreaterThanHigh`: if a `low` is greater than its
+    ///   `high`.
+    pub(crate) fn verify_invariants(&self) -> Result<(), ProtoverError> {
+        // Version 0 is not valid, so it is treated like an overlap.
+        let mut last_high: Version = 0;
+
+        for &(low, high) in self.pairs.iter() {
+            if low == u32::MAX || high == u32::MAX {
+                return Err(ProtoverError::ExceedsMax);
+            }
+            if low <= last_high {
+                return Err(ProtoverError::Overlap);
+            } else if low > high {
+                return Err(ProtoverError::LowGreaterThanHigh);
+            }
+            last_high = high;
+        }
+        Ok(())
+    }
+
+    /// Panic with a description of the problem if this `ProtoSet` breaks
+    /// any of its invariants, or if its cached `len()` is wrong.
+    #[cfg(test)]
+    pub(crate) fn check(&self) {
+        if let Err(error) = self.verify_invariants() {
+            panic!("invalid ProtoSet {:?}: {}", self.pairs, error);
+        }
+        self.assert_count_is_consistent();
+    }
+
+    /// In debug builds, panic if a mutation has left this `ProtoSet` invalid.
+    fn debug_verify_invariants(&self) {
+        if cfg!(debug_assertions) {
+            if let Err(error) = self.verify_invariants() {
+                panic!("mutation broke ProtoSet {:?}: {}", self.pairs, error);
+            }
+        }
+    }
+
+    /// Check that this `ProtoSet` is well-formed.
+    ///
+    /// This is automatically called in `ProtoSet::from_str()`.
+    ///
+    /// # Errors
+    ///
+    /// The same errors as `verify_invariants()`, which does the checking.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` whose `Ok` is this `Protoset`, and whose `Err` is one of the
+    /// errors enumerated in the Errors section above.
+    fn is_ok(self) -> Result<ProtoSet, ProtoverError> {
+        self.verify_invariants().map(|()| self)
+    }
+
+    /// Merge any overlapping or adjacent `(low, high)` pairs in this
+    /// `ProtoSet`, so that it is stored using as few ranges as possible.
+    ///
+    /// Parsing does not do this automatically, so `"1-3,4-6"` is stored as
+    /// two ranges until it is normalized.  Callers which build a `ProtoSet`
+    /// up piece by piece can use this to compact it afterwards.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protover::errors::ProtoverError;
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// # fn do_test() -> Result<bool, ProtoverError> {
+    /// let mut protoset: ProtoSet = "1-3,4-6,9".parse()?;
+    ///
+    /// assert_eq!(protoset.to_string(), "1-3,4-6,9");
+    ///
+    /// protoset.normalize();
+    ///
+    /// assert_eq!(protoset.to_string(), "1-6,9");
+    /// #
+    /// # Ok(true)
+    /// # }
+    /// # fn main() { do_test(); }  // wrap the test so we can use the ? operator
+    /// ```
+    pub fn normalize(&mut self) {
+        if self.pairs.len() < 2 {
+            return;
+        }
+        sort_and_merge(&mut self.pairs);
+
+        // Merging overlapping pairs can change the count.
+        self.count = count_versions(&self.pairs);
+        self.debug_verify_invariants();
+    }
+
+    /// Determine if this `ProtoSet` contains no `Version`s.
+    ///
+    /// # Returns
+    ///
+    /// * `true` if this `ProtoSet`'s length is zero, and
+    /// * `false` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// let protoset: ProtoSet = ProtoSet::default();
+    ///
+    /// assert!(protoset.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.pairs.len() == 0
+    }
+
+    /// Determine if `version` is included within this `ProtoSet`.
+    ///
+    /// # Inputs
+    ///
+    /// * `version`: a `Version`.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the `version` is contained within this set; `false` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protover::errors::ProtoverError;
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// # fn do_test() -> Result<ProtoSet, ProtoverError> {
+    /// let protoset: ProtoSet = ProtoSet::from_slice(&[(0, 5), (7, 9), (13, 14)])?;
+    ///
+    /// assert!(protoset.contains(&5));
+    /// assert!(!protoset.contains(&10));
+    /// #
+    /// # Ok(protoset)
+    /// # }
+    /// # fn main() { do_test(); }  // wrap the test so we can use the ? operator
+    /// ```
+    pub fn contains(&self, version: &Version) -> bool {
+        for &(low, high) in self.iter() {
+            if low <= *version && *version <= high {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Determine if every `Version` in this `ProtoSet` is also in `other`.
+    ///
+    /// This walks both sets of ranges once, without allocating, so it takes
+    /// O(n + m) time in the number of ranges.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protover::errors::ProtoverError;
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// # fn do_test() -> Result<bool, ProtoverError> {
+    /// let protoset: ProtoSet = "2-3,7".parse()?;
+    /// let other: ProtoSet = "1-4,6-9".parse()?;
+    ///
+    /// assert!(protoset.is_subset_of(&other));
+    /// assert!(!other.is_subset_of(&protoset));
+    /// #
+    /// # Ok(true)
+    /// # }
+    /// # fn main() { do_test(); }  // wrap the test so we can use the ? operator
+    /// ```
+    pub fn is_subset_of(&self, other: &Self) -> bool {
+        let mut others = other.iter().peekable();
+
+        for &(low, high) in self.iter() {
+            // The lowest version in this range which isn't known to be in other.
+            let mut needed = low;
+
+            loop {
+                let (other_low, other_high) = match others.peek() {
+                    Some(&&pair) => pair,
+                    None => return false,
+                };
+
+                if other_high < needed {
+                    others.next();
+                } else if other_low > needed {
+                    return false;
+                } else if other_high >= high {
+                    // This other range might also cover our next range.
+                    break;
+                } else {
+                    // other_high < high, so this can't overflow.
+                    needed = other_high + 1;
+                    others.next();
+                }
+            }
+        }
+        true
+    }
+
+    /// Determine if every `Version` in `other` is also in this `ProtoSet`.
+    ///
+    /// This is the same as `other.is_subset_of(self)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protover::errors::ProtoverError;
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// # fn do_test() -> Result<bool, ProtoverError> {
+    /// let protoset: ProtoSet = "1-4,6-9".parse()?;
+    /// let other: ProtoSet = "2-3,7".parse()?;
+    ///
+    /// assert!(protoset.is_superset_of(&other));
+    /// assert!(!other.is_superset_of(&protoset));
+    /// #
+    /// # Ok(true)
+    /// # }
+    /// # fn main() { do_test(); }  // wrap the test so we can use the ? operator
+    /// ```
+    pub fn is_superset_of(&self, other: &Self) -> bool {
+        other.is_subset_of(self)
+    }
+
+    /// Add `version` to this `ProtoSet`, merging it into any ranges which it
+    /// touches.
+    ///
+    /// Inserting a `version` which is already in the set does nothing.
+    ///
+    /// # Errors
+    ///
+    /// * `ProtoverError::Overlap`: if `version` is 0, which `from_str()` also
+    ///   rejects, and
+    /// * `ProtoverError::ExceedsMax`: if `version` is `u32::MAX`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protover::errors::ProtoverError;
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// # fn do_test() -> Result<ProtoSet, ProtoverError> {
+    /// let mut protoset: ProtoSet = "1-3,5".parse()?;
+    ///
+    /// protoset.insert(4)?;
+    /// protoset.insert(9)?;
+    ///
+    /// assert_eq!(protoset.to_string(), "1-5,9");
+    /// #
+    /// # Ok(protoset)
+    /// # }
+    /// # fn main() { do_test(); }  // wrap the test so we can use the ? operator
+    /// ```
+    pub fn insert(&mut self, version: Version) -> Result<(), ProtoverError> {
+        if version == 0 {
+            return Err(ProtoverError::Overlap);
+        }
+        if version == u32::MAX {
+            return Err(ProtoverError::ExceedsMax);
+        }
+        if self.contains(&version) {
+            return Ok(());
+        }
+
+        let index = self
+            .pairs
+            .iter()
+            .position(|&(low, _)| low > version)
+            .unwrap_or(self.pairs.len());
+
+        self.pairs.insert(index, (version, version));
+        self.count += 1;
+
+        // Merge with the following range, then the preceding one.
+        if index + 1 < self.pairs.len() && self.pairs[index + 1].0 == version + 1 {
+            self.pairs[index].1 = self.pairs[index + 1].1;
+            self.pairs.remove(index + 1);
+        }
+        if index > 0 && self.pairs[index - 1].1 + 1 == version {
+            self.pairs[index - 1].1 = self.pairs[index].1;
+            self.pairs.remove(index);
+        }
+        self.debug_verify_invariants();
+        Ok(())
+    }
+
+    /// Add every `Version` from `low` to `high` (inclusive) to this
+    /// `ProtoSet`, merging them with any ranges which they overlap or touch.
+    ///
+    /// This is the in-place version of `ProtoSet::union()`.
+    ///
+    /// # Errors
+    ///
+    /// * `ProtoverError::LowGreaterThanHigh`: if `low` is greater than `high`,
+    /// * `ProtoverError::Overlap`: if `low` is 0, which `from_str()` also
+    ///   rejects, and
+    /// * `ProtoverError::ExceedsMax`: if `high` is `u32::MAX`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protover::errors::ProtoverError;
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// # fn do_test() -> Result<ProtoSet, ProtoverError> {
+    /// let mut protoset: ProtoSet = "1-3,5,8-9".parse()?;
+    ///
+    /// protoset.extend_range(4, 7)?;
+    /// protoset.extend_range(20, 30)?;
+    ///
+    /// assert_eq!(protoset.to_string(), "1-9,20-30");
+    /// #
+    /// # Ok(protoset)
+    /// # }
+    /// # fn main() { do_test().unwrap(); }  // wrap the test so we can use the ? operator
+    /// ```
+    pub fn extend_range(&mut self, low: Version, high: Version) -> Result<(), ProtoverError> {
+        if low > high {
+            return Err(ProtoverError::LowGreaterThanHigh);
+        }
+        if low == 0 {
+            return Err(ProtoverError::Overlap);
+        }
+        if high == u32::MAX {
+            return Err(ProtoverError::ExceedsMax);
+        }
+
+        // Valid versions are less than u32::MAX, so these can't overflow.
+        // The first range which ends at or after low - 1, and the first range
+        // which starts after high + 1.
+        let start: usize = partition_point(&self.pairs, |&(_, h)| h + 1 < low);
+        let end: usize = partition_point(&self.pairs, |&(l, _)| l <= high + 1);
+
+        let mut merged: (Version, Version) = (low, high);
+
+        if start < end {
+            merged.0 = cmp::min(low, self.pairs[start].0);
+            merged.1 = cmp::max(high, self.pairs[end - 1].1);
+        }
+        self.count -= count_versions(&self.pairs[start..end]);
+        self.count += count_versions(&[merged]);
+        self.pairs.splice(start..end, iter::once(merged));
+        self.debug_verify_invariants();
+
+        Ok(())
+    }
+
+    /// Add `version` to this `ProtoSet`, merging it with any ranges which it
+    /// touches.
+    ///
+    /// This is the same as `ProtoSet::insert()`, and
+    /// `extend_range(version, version)`.
+    ///
+    /// # Errors
+    ///
+    /// * `ProtoverError::Overlap`: if `version` is 0, and
+    /// * `ProtoverError::ExceedsMax`: if `version` is `u32::MAX`.
+    pub fn extend_version(&mut self, version: Version) -> Result<(), ProtoverError> {
+        self.extend_range(version, version)
+    }
+
+    /// Remove `version` from this `ProtoSet`, splitting the range which
+    /// contains it if necessary.
+    ///
+    /// # Returns
+    ///
+    /// `true` if `version` was in this set; `false` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protover::errors::ProtoverError;
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// # fn do_test() -> Result<ProtoSet, ProtoverError> {
+    /// let mut protoset: ProtoSet = "1-5,9".parse()?;
+    ///
+    /// assert!(protoset.remove(3));
+    /// assert!(protoset.remove(9));
+    /// assert!(!protoset.remove(7));
+    ///
+    /// assert_eq!(protoset.to_string(), "1-2,4-5");
+    /// #
+    /// # Ok(protoset)
+    /// # }
+    /// # fn main() { do_test(); }  // wrap the test so we can use the ? operator
+    /// ```
+    pub fn remove(&mut self, version: Version) -> bool {
+        let index = match self
+            .pairs
+            .iter()
+            .position(|&(low, high)| low <= version && version <= high)
+        {
+            Some(index) => index,
+            None => return false,
+        };
+        let (low, high) = self.pairs[index];
+
+        if low == high {
+            self.pairs.remove(index);
+        } else if version == low {
+            self.pairs[index].0 = low + 1;
+        } else if version == high {
+            self.pairs[index].1 = high - 1;
+        } else {
+            self.pairs[index].1 = version - 1;
+            self.pairs.insert(index + 1, (version + 1, high));
+        }
+        self.count -= 1;
+        self.debug_verify_invariants();
+        true
+    }
+
+    /// Find the smallest `Version` greater than `version` which is not in
+    /// this `ProtoSet`.
+    ///
+    /// # Returns
+    ///
+    /// The missing `Version`, or `None` if every valid `Version` above
+    /// `version` is in this set.  (`u32::MAX` is never a valid `Version`.)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protover::errors::ProtoverError;
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// # fn do_test() -> Result<bool, ProtoverError> {
+    /// let protoset: ProtoSet = "1-3,5-7".parse()?;
+    ///
+    /// assert_eq!(protoset.first_missing_after(1), Some(4));
+    /// assert_eq!(protoset.first_missing_after(4), Some(8));
+    /// assert_eq!(protoset.first_missing_after(9), Some(10));
+    /// #
+    /// # Ok(true)
+    /// # }
+    /// # fn main() { do_test().unwrap(); }  // wrap the test so we can use the ? operator
+    /// ```
+    pub fn first_missing_after(&self, version: Version) -> Option<Version> {
+        let mut candidate: Version = version.checked_add(1)?;
+
+        for &(low, high) in self.iter() {
+            if low > candidate {
+                break;
+            }
+            if high >= candidate {
+                // high is at most u32::MAX - 1, so this can't overflow.
+                candidate = high + 1;
+            }
+        }
+
+        if candidate == u32::MAX {
+            None
+        } else {
+            Some(candidate)
+        }
+    }
+
+    /// Determine if every `Version` from `lo` to `hi` (inclusive) is in this
+    /// `ProtoSet`.
+    ///
+    /// If `lo` is greater than `hi`, the range is empty, so it is always
+    /// covered.
+    ///
+    /// This uses a binary search to find the first pair which could contain
+    /// `lo`, then only walks forward over adjacent pairs, like `"1-2,3-4"`,
+    /// which haven't been merged by `normalize()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protover::errors::ProtoverError;
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// # fn do_test() -> Result<bool, ProtoverError> {
+    /// let protoset: ProtoSet = "1-3,4-5,8-20".parse()?;
+    ///
+    /// assert!(protoset.covers_range(2, 5));
+    /// assert!(!protoset.covers_range(5, 8));
+    /// #
+    /// # Ok(true)
+    /// # }
+    /// # fn main() { do_test().unwrap(); }  // wrap the test so we can use the ? operator
+    /// ```
+    pub fn covers_range(&self, lo: Version, hi: Version) -> bool {
+        if lo > hi {
+            return true;
+        }
+        // The first range which ends at or above lo.
+        let start: usize = partition_point(&self.pairs, |&(_, high)| high < lo);
+        let mut next_uncovered: Version = lo;
+
+        for &(low, high) in &self.pairs[start..] {
+            if low > next_uncovered {
+                return false;
+            }
+            if high >= hi {
+                return true;
+            }
+            next_uncovered = high + 1;
+        }
+        false
+    }
+
+    /// Count the `Version`s from `low` to `high` (inclusive) which are in this
+    /// `ProtoSet`.
+    ///
+    /// If `low` is greater than `high`, the range is empty, so the count is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protover::errors::ProtoverError;
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// # fn do_test() -> Result<bool, ProtoverError> {
+    /// let protoset: ProtoSet = "1-3,5,8-20".parse()?;
+    ///
+    /// assert_eq!(protoset.count_versions_in_range(2, 9), 5);
+    /// #
+    /// # Ok(true)
+    /// # }
+    /// # fn main() { do_test().unwrap(); }  // wrap the test so we can use the ? operator
+    /// ```
+    pub fn count_versions_in_range(&self, low: Version, high: Version) -> usize {
+        if low > high {
+            return 0;
+        }
+        // The first range which ends at or above low.
+        let start: usize = partition_point(&self.pairs, |&(_, h)| h < low);
+        let mut count: usize = 0;
+
+        for &(range_low, range_high) in &self.pairs[start..] {
+            if range_low > high {
+                break;
+            }
+            let overlap_low: Version = cmp::max(range_low, low);
+            let overlap_high: Version = cmp::min(range_high, high);
+
+            count += (overlap_high - overlap_low) as usize + 1;
+        }
+        count
+    }
+
+    /// Get the `Version`s from `low` to `high` (inclusive) which are in this
+    /// `ProtoSet`.
+    ///
+    /// Ranges which overlap the window are clamped to it, and ranges outside
+    /// the window are dropped, so this takes O(n) time in the number of
+    /// ranges, without expanding any versions.
+    ///
+    /// If `low` is greater than `high`, the range is empty, so the result is
+    /// an empty `ProtoSet`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protover::errors::ProtoverError;
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// # fn do_test() -> Result<ProtoSet, ProtoverError> {
+    /// let protoset: ProtoSet = "1-3,5,8-20".parse()?;
+    /// let window: ProtoSet = protoset.overlapping_range(2, 9);
+    ///
+    /// assert_eq!(window.to_string(), "2-3,5,8-9");
+    /// assert_eq!(window.len(), protoset.count_versions_in_range(2, 9));
+    /// #
+    /// # Ok(window)
+    /// # }
+    /// # fn main() { do_test().unwrap(); }  // wrap the test so we can use the ? operator
+    /// ```
+    pub fn overlapping_range(&self, low: Version, high: Version) -> Self {
+        if low > high {
+            return ProtoSet::default();
+        }
+        // The first range which ends at or above low.
+        let start: usize = partition_point(&self.pairs, |&(_, h)| h < low);
+        let mut pairs: Vec<(Version, Version)> = Vec::new();
+
+        for &(range_low, range_high) in &self.pairs[start..] {
+            if range_low > high {
+                break;
+            }
+            pairs.push((cmp::max(range_low, low), cmp::min(range_high, high)));
+        }
+
+        // Clamping sorted, disjoint ranges keeps them sorted and disjoint.
+        ProtoSet::from_sorted_unchecked(pairs)
+    }
+
+    /// Count the `Version`s from `low` to `high` (inclusive) which are *not*
+    /// in this `ProtoSet`.
+    ///
+    /// If `low` is greater than `high`, the range is empty, so the count is 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protover::errors::ProtoverError;
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// # fn do_test() -> Result<bool, ProtoverError> {
+    /// let protoset: ProtoSet = "1-3,5,8-20".parse()?;
+    ///
+    /// // 4, 6, and 7 are missing.
+    /// assert_eq!(protoset.count_missing_in_range(2, 9), 3);
+    /// #
+    /// # Ok(true)
+    /// # }
+    /// # fn main() { do_test().unwrap(); }  // wrap the test so we can use the ? operator
+    /// ```
+    pub fn count_missing_in_range(&self, low: Version, high: Version) -> usize {
+        if low > high {
+            return 0;
+        }
+        (high - low) as usize + 1 - self.count_versions_in_range(low, high)
+    }
+
+    /// Find the largest `Version` in this `ProtoSet` which is less than
+    /// `version`.
+    ///
+    /// This uses a binary search, so it takes O(log n) time in the number of
+    /// ranges.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protover::errors::ProtoverError;
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// # fn do_test() -> Result<bool, ProtoverError> {
+    /// let protoset: ProtoSet = "1-3,7-9".parse()?;
+    ///
+    /// assert_eq!(protoset.version_before(8), Some(7));
+    /// assert_eq!(protoset.version_before(5), Some(3));
+    /// assert_eq!(protoset.version_before(1), None);
+    /// #
+    /// # Ok(true)
+    /// # }
+    /// # fn main() { do_test().unwrap(); }  // wrap the test so we can use the ? operator
+    /// ```
+    pub fn version_before(&self, version: Version) -> Option<Version> {
+        let target: Version = version.checked_sub(1)?;
+        // The number of ranges which start at or below target.
+        let index: usize = partition_point(&self.pairs, |&(low, _)| low <= target);
+        let &(_, high) = self.pairs.get(index.checked_sub(1)?)?;
+
+        Some(cmp::min(high, target))
+    }
+
+    /// Find the smallest `Version` in this `ProtoSet` which is greater than
+    /// `version`.
+    ///
+    /// This uses a binary search, so it takes O(log n) time in the number of
+    /// ranges.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protover::errors::ProtoverError;
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// # fn do_test() -> Result<bool, ProtoverError> {
+    /// let protoset: ProtoSet = "1-3,7-9".parse()?;
+    ///
+    /// assert_eq!(protoset.version_after(2), Some(3));
+    /// assert_eq!(protoset.version_after(3), Some(7));
+    /// assert_eq!(protoset.version_after(9), None);
+    /// #
+    /// # Ok(true)
+    /// # }
+    /// # fn main() { do_test().unwrap(); }  // wrap the test so we can use the ? operator
+    /// ```
+    pub fn version_after(&self, version: Version) -> Option<Version> {
+        let target: Version = version.checked_add(1)?;
+        // The first range which ends at or above target.
+        let index: usize = partition_point(&self.pairs, |&(_, high)| high < target);
+        let &(low, _) = self.pairs.get(index)?;
+
+        Some(cmp::max(low, target))
+    }
+
+    /// Find the longest contiguous range of `Version`s in this `ProtoSet`.
+    ///
+    /// Ranges are compared as they are stored, so call `normalize()` first if
+    /// adjacent ranges should be treated as one.
+    ///
+    /// # Returns
+    ///
+    /// The `(low, high)` pair with the most `Version`s, or `None` if this set
+    /// is empty.  If several ranges are equally long, the one with the lowest
+    /// start is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protover::errors::ProtoverError;
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// # fn do_test() -> Result<bool, ProtoverError> {
+    /// let protoset: ProtoSet = "1-2,5-9,12".parse()?;
+    ///
+    /// assert_eq!(protoset.max_contiguous_range(), Some((5, 9)));
+    /// assert_eq!(ProtoSet::default().max_contiguous_range(), None);
+    /// #
+    /// # Ok(true)
+    /// # }
+    /// # fn main() { do_test().unwrap(); }  // wrap the test so we can use the ? operator
+    /// ```
+    pub fn max_contiguous_range(&self) -> Option<(Version, Version)> {
+        let mut longest: Option<(Version, Version)> = None;
+
+        for &(low, high) in self.iter() {
+            match longest {
+                // Only replace on a strictly longer range, so ties go to the
+                // lowest start.
+                Some((longest_low, longest_high)) if high - low <= longest_high - longest_low => {}
+                _ => longest = Some((low, high)),
+            }
+        }
+        longest
+    }
+
+    /// Walk the ranges of `self` and `other` together, and determine whether
+    /// any of them overlap, and whether any of them touch (one ends at `N`
+    /// and the other starts at `N + 1`).
+    fn overlaps_and_touches(&self, other: &Self) -> (bool, bool) {
+        let mut ours = self.iter().peekable();
+        let mut theirs = other.iter().peekable();
+        let mut overlaps = false;
+        let mut touches = false;
+
+        while let (Some(&&(low, high)), Some(&&(other_low, other_high))) =
+            (ours.peek(), theirs.peek())
+        {
+            if low <= other_high && other_low <= high {
+                overlaps = true;
+            }
+            // Valid versions are less than u32::MAX, so these can't overflow.
+            if high + 1 == other_low || other_high + 1 == low {
+                touches = true;
+            }
+            if high < other_high {
+                ours.next();
+            } else {
+                theirs.next();
+            }
+        }
+        (overlaps, touches)
+    }
+
+    /// Determine if this `ProtoSet` and `other` touch without overlapping:
+    /// some range in one set ends at `N`, and a range in the other starts at
+    /// `N + 1`, but no `Version` is in both.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protover::errors::ProtoverError;
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// # fn do_test() -> Result<bool, ProtoverError> {
+    /// let protoset: ProtoSet = "1-3".parse()?;
+    ///
+    /// assert!(protoset.adjacent(&"4-6".parse()?));
+    /// assert!(!protoset.adjacent(&"5-6".parse()?));
+    /// assert!(!protoset.adjacent(&"3-6".parse()?));
+    /// #
+    /// # Ok(true)
+    /// # }
+    /// # fn main() { do_test().unwrap(); }  // wrap the test so we can use the ? operator
+    /// ```
+    pub fn adjacent(&self, other: &Self) -> bool {
+        let (overlaps, touches) = self.overlaps_and_touches(other);
+
+        touches && !overlaps
+    }
+
+    /// Merge `a` and `b` into a single `ProtoSet`, if they are adjacent or
+    /// overlapping.
+    ///
+    /// # Returns
+    ///
+    /// `Ok` with the union of `a` and `b`, or `Err` with `a` and `b`
+    /// unchanged if there is a gap between every pair of their ranges.  Empty
+    /// sets are never merged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protover::errors::ProtoverError;
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// # fn do_test() -> Result<bool, ProtoverError> {
+    /// let a: ProtoSet = "1-3".parse()?;
+    /// let b: ProtoSet = "4-6".parse()?;
+    /// let c: ProtoSet = "9".parse()?;
+    ///
+    /// let merged = ProtoSet::merge_if_adjacent(a, b).unwrap();
+    ///
+    /// assert_eq!(merged.to_string(), "1-6");
+    /// assert!(ProtoSet::merge_if_adjacent(merged, c).is_err());
+    /// #
+    /// # Ok(true)
+    /// # }
+    /// # fn main() { do_test().unwrap(); }  // wrap the test so we can use the ? operator
+    /// ```
+    pub fn merge_if_adjacent(a: ProtoSet, b: ProtoSet) -> Result<ProtoSet, (ProtoSet, ProtoSet)> {
+        let (overlaps, touches) = a.overlaps_and_touches(&b);
+
+        if overlaps || touches {
+            Ok(a.union(&b))
+        } else {
+            Err((a, b))
+        }
+    }
+
+    /// Returns all the `Version`s which are in either `self` or the `other`
+    /// `ProtoSet`, using as few ranges as possible.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protover::errors::ProtoverError;
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// # fn do_test() -> Result<bool, ProtoverError> {
+    /// let protoset: ProtoSet = "1-3,10".parse()?;
+    /// let other: ProtoSet = "2-5,8".parse()?;
+    ///
+    /// assert_eq!(protoset.union(&other).to_string(), "1-5,8,10");
+    /// #
+    /// # Ok(true)
+    /// # }
+    /// # fn main() { do_test(); }  // wrap the test so we can use the ? operator
+    /// ```
+    pub fn union(&self, other: &Self) -> Self {
+        let mut pairs: Vec<(Version, Version)> =
+            Vec::with_capacity(self.pairs.len() + other.pairs.len());
+
+        pairs.extend(self.iter());
+        pairs.extend(other.iter());
+
+        ProtoSet::from_unsorted(pairs)
+    }
+
+    /// Returns the union of `self` and `other`, like `ProtoSet::union()`, but
+    /// only if it is small enough to expand.
+    ///
+    /// # Errors
+    ///
+    /// * `ProtoverError::ExceedsExpansionLimit`: if the union would contain
+    ///   more than `MAX_PROTOCOLS_TO_EXPAND` versions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protover::errors::ProtoverError;
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// # fn do_test() -> Result<ProtoSet, ProtoverError> {
+    /// let protoset: ProtoSet = "1-3,10".parse()?;
+    ///
+    /// assert_eq!(protoset.try_merge(&"2-5".parse()?)?.to_string(), "1-5,10");
+    /// assert_eq!(protoset.try_merge(&"11-70000".parse()?),
+    ///            Err(ProtoverError::ExceedsExpansionLimit));
+    /// # Ok(protoset)
+    /// # }
+    /// # fn main() { do_test().unwrap(); }  // wrap the test so we can use the ? operator
+    /// ```
+    pub fn try_merge(&self, other: &Self) -> Result<Self, ProtoverError> {
+        self.union(other).check_expansion_limit()
+    }
+
+    /// Returns all the `Version`s in `self` which are not also in the `other`
+    /// `ProtoSet`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protover::errors::ProtoverError;
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// # fn do_test() -> Result<bool, ProtoverError> {
+    /// let protoset: ProtoSet = "1,3-6,10-12,15-16".parse()?;
+    /// let other: ProtoSet = "2,5-7,9-11,14-20".parse()?;
+    ///
+    /// let subset: ProtoSet = protoset.and_not_in(&other);
+    ///
+    /// assert_eq!(subset.expand(), vec![1, 3, 4, 12]);
+    /// #
+    /// # Ok(true)
+    /// # }
+    /// # fn main() { do_test(); }  // wrap the test so we can use the ? operator
+    /// ```
+    pub fn and_not_in(&self, other: &Self) -> Self {
+        if self.is_empty() || other.is_empty() {
+            return self.clone();
+        }
+
+        let pairs = self.iter().flat_map(|&(lo, hi)| {
+            let the_end = (hi + 1, hi + 1); // special case to mark the end of the range.
+            let excluded_ranges = other
+                .iter()
+                .cloned() // have to be owned tuples, to match iter::once(the_end).
+                .skip_while(move|&(_, hi2)| hi2 < lo) // skip the non-overlapping ranges.
+                .take_while(move|&(lo2, _)| lo2 <= hi) // take all the overlapping ones.
+                .chain(iter::once(the_end));
+
+            let mut nextlo = lo;
+            excluded_ranges.filter_map(move |(excluded_lo, excluded_hi)| {
+                let pair = if nextlo < excluded_lo {
+                    Some((nextlo, excluded_lo - 1))
+                } else {
+                    None
+                };
+                nextlo = cmp::min(excluded_hi, u32::MAX - 1) + 1;
+                pair
+            })
+        });
+
+        // The pairs are sorted subranges of our own valid pairs.
+        ProtoSet::from_sorted_unchecked(pairs.collect())
+    }
+
+    /// Keep only the `Version`s in this `ProtoSet` which are also in `mask`,
+    /// returning them in a new `ProtoSet`.
+    ///
+    /// This is the intersection of the two sets.  It walks both lists of
+    /// ranges once, so it takes O(n + m) time, without expanding any ranges.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protover::errors::ProtoverError;
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// # fn do_test() -> Result<bool, ProtoverError> {
+    /// let protoset: ProtoSet = "1,3-6,10-12,15-16".parse()?;
+    /// let mask: ProtoSet = "2,5-7,9-11,14-20".parse()?;
+    ///
+    /// let retained: ProtoSet = protoset.retain_range_set(&mask);
+    ///
+    /// assert_eq!(retained.to_string(), "5-6,10-11,15-16");
+    /// #
+    /// # Ok(true)
+    /// # }
+    /// # fn main() { do_test(); }  // wrap the test so we can use the ? operator
+    /// ```
+    pub fn retain_range_set(&self, mask: &Self) -> Self {
+        let mut pairs: Vec<(Version, Version)> = Vec::new();
+        let mut ours = self.pairs.iter().peekable();
+        let mut theirs = mask.pairs.iter().peekable();
+
+        while let (Some(&&(lo, hi)), Some(&&(mask_lo, mask_hi))) = (ours.peek(), theirs.peek()) {
+            let low: Version = cmp::max(lo, mask_lo);
+            let high: Version = cmp::min(hi, mask_hi);
+
+            if low <= high {
+                pairs.push((low, high));
+            }
+            // Advance whichever range ends first: it can't overlap anything else.
+            if hi < mask_hi {
+                ours.next();
+            } else {
+                theirs.next();
+            }
+        }
+
+        // The pairs are sorted subranges of our own valid pairs.
+        ProtoSet::from_sorted_unchecked(pairs)
+    }
+
+    /// Keep only the `Version`s in this `ProtoSet` which are also in `mask`,
+    /// like `retain_range_set()`, but modify this `ProtoSet` in place.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protover::errors::ProtoverError;
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// # fn do_test() -> Result<bool, ProtoverError> {
+    /// let mut protoset: ProtoSet = "1-10".parse()?;
+    ///
+    /// protoset.retain_range_set_assign(&"3-4,8-20".parse()?);
+    ///
+    /// assert_eq!(protoset.to_string(), "3-4,8-10");
+    /// #
+    /// # Ok(true)
+    /// # }
+    /// # fn main() { do_test(); }  // wrap the test so we can use the ? operator
+    /// ```
+    pub fn retain_range_set_assign(&mut self, mask: &Self) {
+        *self = self.retain_range_set(mask);
+        self.debug_verify_invariants();
+    }
+
+    /// Split this `ProtoSet` at `version`, without expanding any ranges.
+    ///
+    /// This is the efficient form of `partition()`, for predicates like
+    /// `|v| v < version`.  It takes O(log n) time to find the split point in
+    /// the n ranges, plus the time to copy them.
+    ///
+    /// # Returns
+    ///
+    /// A tuple of two `ProtoSet`s: the `Version`s less than `version`, and
+    /// the `Version`s greater than or equal to `version`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protover::errors::ProtoverError;
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// # fn do_test() -> Result<bool, ProtoverError> {
+    /// let protoset: ProtoSet = "1-3,5-9".parse()?;
+    /// let (below, above) = protoset.split_at(7);
+    ///
+    /// assert_eq!(below.to_string(), "1-3,5-6");
+    /// assert_eq!(above.to_string(), "7-9");
+    /// #
+    /// # Ok(true)
+    /// # }
+    /// # fn main() { do_test().unwrap(); }  // wrap the test so we can use the ? operator
+    /// ```
+    pub fn split_at(&self, version: Version) -> (Self, Self) {
+        // The index of the first range which has any versions >= version.
+        let split: usize = partition_point(&self.pairs, |&(_, high)| high < version);
+        let mut below: Vec<(Version, Version)> = self.pairs[..split].to_vec();
+        let mut above: Vec<(Version, Version)> = Vec::with_capacity(self.pairs.len() - split);
+
+        for &(low, high) in &self.pairs[split..] {
+            if low < version {
+                below.push((low, version - 1));
+                above.push((version, high));
+            } else {
+                above.push((low, high));
+            }
+        }
+
+        // Both halves are sorted subranges of our own valid pairs.
+        (
+            ProtoSet::from_sorted_unchecked(below),
+            ProtoSet::from_sorted_unchecked(above),
+        )
+    }
+
+    /// Split this `ProtoSet` into the `Version`s which match the predicate
+    /// `f`, and those which don't.
+    ///
+    /// The predicate is called once for every `Version` in this set, but no
+    /// list of versions is allocated: consecutive versions with the same
+    /// result are collected into ranges.  When the predicate splits the set
+    /// at a single `Version`, use `split_at()` instead, which doesn't call
+    /// a predicate at all, and works on sets of any size.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` whose `Ok` is a tuple of two disjoint `ProtoSet`s: the
+    /// `Version`s for which `f` returned `true`, and the `Version`s for which
+    /// it returned `false`.  Their union is equal to this set.
+    ///
+    /// # Errors
+    ///
+    /// * `ProtoverError::ExceedsExpansionLimit`: if this set contains more
+    ///   than `MAX_PROTOCOLS_TO_EXPAND` versions, because calling `f` for
+    ///   each of them could take a very long time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protover::errors::ProtoverError;
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// # fn do_test() -> Result<bool, ProtoverError> {
+    /// let protoset: ProtoSet = "1-6,9".parse()?;
+    /// let (odd, even) = protoset.partition(|v| v % 2 == 1)?;
+    ///
+    /// assert_eq!(odd.to_string(), "1,3,5,9");
+    /// assert_eq!(even.to_string(), "2,4,6");
+    /// #
+    /// # Ok(true)
+    /// # }
+    /// # fn main() { do_test().unwrap(); }  // wrap the test so we can use the ? operator
+    /// ```
+    pub fn partition(&self, f: impl Fn(Version) -> bool) -> Result<(Self, Self), ProtoverError> {
+        if self.len() > MAX_PROTOCOLS_TO_EXPAND {
+            return Err(ProtoverError::ExceedsExpansionLimit);
+        }
+
+        let mut matching: Vec<(Version, Version)> = Vec::new();
+        let mut non_matching: Vec<(Version, Version)> = Vec::new();
+
+        for &(low, high) in self.iter() {
+            let mut run_low: Version = low;
+            let mut run_matches: bool = f(low);
+
+            // high is at most u32::MAX - 1, so low + 1 can't overflow.
+            for version in low + 1..=high {
+                let matches: bool = f(version);
+
+                if matches != run_matches {
+                    if run_matches {
+                        matching.push((run_low, version - 1));
+                    } else {
+                        non_matching.push((run_low, version - 1));
+                    }
+                    run_low = version;
+                    run_matches = matches;
+                }
+            }
+            if run_matches {
+                matching.push((run_low, high));
+            } else {
+                non_matching.push((run_low, high));
+            }
+        }
+
+        // Both halves are sorted subranges of our own valid pairs.
+        Ok((
+            ProtoSet::from_sorted_unchecked(matching),
+            ProtoSet::from_sorted_unchecked(non_matching),
+        ))
+    }
+
+    /// Add `offset` to every `Version` in this `ProtoSet`, returning the
+    /// shifted set.
+    ///
+    /// # Errors
+    ///
+    /// * `ProtoverError::ExceedsMax`: if any shifted `Version` would be less
+    ///   than 1, or greater than or equal to `u32::MAX`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protover::errors::ProtoverError;
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// # fn do_test() -> Result<ProtoSet, ProtoverError> {
+    /// let protoset: ProtoSet = "1-3,7".parse()?;
+    ///
+    /// assert_eq!(protoset.shift(10)?.to_string(), "11-13,17");
+    /// assert_eq!(protoset.shift(-2), Err(ProtoverError::ExceedsMax));
+    /// # Ok(protoset)
+    /// # }
+    /// # fn main() { do_test().unwrap(); }  // wrap the test so we can use the ? operator
+    /// ```
+    pub fn shift(&self, offset: i64) -> Result<Self, ProtoverError> {
+        let shift_version = |version: Version| -> Result<Version, ProtoverError> {
+            match (version as i64).checked_add(offset) {
+                Some(shifted) if shifted >= 1 && shifted < u32::MAX as i64 => {
+                    Ok(shifted as Version)
+                }
+                _ => Err(ProtoverError::ExceedsMax),
+            }
+        };
+        let mut pairs: Vec<(Version, Version)> = Vec::with_capacity(self.pairs.len());
+
+        for &(low, high) in self.iter() {
+            pairs.push((shift_version(low)?, shift_version(high)?));
+        }
+        // Shifting every version by the same amount keeps the pairs sorted.
+        Ok(ProtoSet::from_sorted_unchecked(pairs))
+    }
+
+    /// Pack this `ProtoSet` into a 64-bit bitmask, where bit `n` is set if
+    /// `Version` `n` is in the set.
+    ///
+    /// This is a compact representation of common, small sets, suitable for
+    /// use as a cache key.
+    ///
+    /// # Returns
+    ///
+    /// The bitmask, or `None` if any `Version` is greater than 63.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protover::errors::ProtoverError;
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// # fn do_test() -> Result<bool, ProtoverError> {
+    /// let protoset: ProtoSet = "1-3,5".parse()?;
+    ///
+    /// assert_eq!(protoset.pack(), Some(0b101110));
+    /// assert_eq!(ProtoSet::unpack(0b101110), protoset);
+    /// assert_eq!("1-64".parse::<ProtoSet>()?.pack(), None);
+    /// #
+    /// # Ok(true)
+    /// # }
+    /// # fn main() { do_test().unwrap(); }  // wrap the test so we can use the ? operator
+    /// ```
+    pub fn pack(&self) -> Option<u64> {
+        let mut bits: u64 = 0;
+
+        for &(low, high) in self.iter() {
+            if high > 63 {
+                return None;
+            }
+            for version in low..high + 1 {
+                bits |= 1 << version;
+            }
+        }
+        Some(bits)
+    }
+
+    /// Unpack a `ProtoSet` from a 64-bit bitmask produced by
+    /// `ProtoSet::pack()`.
+    ///
+    /// Version 0 is not a valid `Version` in a `ProtoSet`, so bit 0 is
+    /// ignored.
+    pub fn unpack(bits: u64) -> Self {
+        let mut pairs: Vec<(Version, Version)> = Vec::new();
+
+        for version in (1..64).filter(|v| bits & (1u64 << v) != 0) {
+            if let Some(last) = pairs.last_mut() {
+                if last.1 + 1 == version {
+                    last.1 = version;
+                    continue;
+                }
+            }
+            pairs.push((version, version));
+        }
+        // The versions are all valid, and were added in ascending order.
+        ProtoSet::from_sorted_unchecked(pairs)
+    }
+
+    /// Encode this `ProtoSet` in a compact binary format.
+    ///
+    /// The encoding is a 4-byte count of ranges, followed by the `low` and
+    /// `high` `Version` of each range, as 4-byte integers.  All integers are
+    /// in network (big-endian) byte order, like other Tor wire formats.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protover::errors::ProtoverError;
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// # fn do_test() -> Result<bool, ProtoverError> {
+    /// let protoset: ProtoSet = "1-3,7".parse()?;
+    /// let encoded: Vec<u8> = protoset.encode();
+    ///
+    /// assert_eq!(encoded, vec![0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 3, 0, 0, 0, 7, 0, 0, 0, 7]);
+    /// assert_eq!(ProtoSet::decode(&encoded)?, protoset);
+    /// #
+    /// # Ok(true)
+    /// # }
+    /// # fn main() { do_test(); }  // wrap the test so we can use the ? operator
+    /// ```
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::with_capacity(4 + self.pairs.len() * 8);
+
+        // A valid ProtoSet has fewer than u32::MAX / 2 ranges.
+        write_u32(&mut bytes, self.pairs.len() as u32);
+
+        for &(low, high) in self.iter() {
+            write_u32(&mut bytes, low);
+            write_u32(&mut bytes, high);
+        }
+        bytes
+    }
+
+    /// Decode a `ProtoSet` from the binary format produced by
+    /// `ProtoSet::encode()`.
+    ///
+    /// # Errors
+    ///
+    /// * `ProtoverError::Unparseable`: if `bytes` is truncated, or has
+    ///   trailing data.
+    /// * Any error returned when validating a `ProtoSet`: the ranges must be
+    ///   sorted, must not overlap, and must not include `u32::MAX`.
+    pub fn decode(bytes: &[u8]) -> Result<Self, ProtoverError> {
+        if bytes.len() < 4 {
+            return Err(ProtoverError::Unparseable);
+        }
+        let (count, body) = bytes.split_at(4);
+        let count = read_u32(count) as usize;
+
+        if body.len() % 8 != 0 || body.len() / 8 != count {
+            return Err(ProtoverError::Unparseable);
+        }
+
+        let pairs: Vec<(Version, Version)> = body
+            .chunks(8)
+            .map(|pair| (read_u32(&pair[..4]), read_u32(&pair[4..])))
+            .collect();
+
+        ProtoSet::from_sorted(pairs)
+    }
+
+    /// Get a wrapper which displays this `ProtoSet` as a flat list of
+    /// comma-separated `Version`s, like `"3,4,5,8"`.
+    ///
+    /// Every `Version` is written out, so avoid this for large sets.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protover::errors::ProtoverError;
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// # fn do_test() -> Result<bool, ProtoverError> {
+    /// let protoset: ProtoSet = "3-5,8".parse()?;
+    ///
+    /// assert_eq!(format!("{}", protoset.display_flat()), "3,4,5,8");
+    /// assert_eq!(format!("{}", protoset.display_ranges()), "3-5,8");
+    /// #
+    /// # Ok(true)
+    /// # }
+    /// # fn main() { do_test().unwrap(); }  // wrap the test so we can use the ? operator
+    /// ```
+    pub fn display_flat(&self) -> ProtoSetDisplay<'_> {
+        ProtoSetDisplay {
+            protoset: self,
+            flat: true,
+        }
+    }
+
+    /// Get a wrapper which displays this `ProtoSet` in range notation, like
+    /// `"3-5,8"`.
+    ///
+    /// This is the same format as `to_string()`.
+    pub fn display_ranges(&self) -> ProtoSetDisplay<'_> {
+        ProtoSetDisplay {
+            protoset: self,
+            flat: false,
+        }
+    }
+
+    /// Encode this `ProtoSet` as a lowercase hex string, for use in places
+    /// like HTTP headers and short log lines.
+    ///
+    /// This is the binary format from `ProtoSet::encode()`, with each byte
+    /// written as two hex digits.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use protover::errors::ProtoverError;
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// # fn do_test() -> Result<bool, ProtoverError> {
+    /// let protoset: ProtoSet = "1-3,254".parse()?;
+    /// let hex: String = protoset.to_hex();
+    ///
+    /// assert_eq!(hex, "000000020000000100000003000000fe000000fe");
+    /// assert_eq!(ProtoSet::from_hex(&hex)?, protoset);
+    /// #
+    /// # Ok(true)
+    /// # }
+    /// # fn main() { do_test().unwrap(); }  // wrap the test so we can use the ? operator
+    /// ```
+    pub fn to_hex(&self) -> String {
+        self.encode()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    /// Decode a `ProtoSet` from the hex format produced by
+    /// `ProtoSet::to_hex()`.  Upper case hex digits are also accepted.
+    ///
+    /// # Errors
+    ///
+    /// * `ProtoverError::Unparseable`: if `hex` has an odd number of
+    ///   characters, or any character is not a hex digit.
+    /// * Any error returned by `ProtoSet::decode()`.
+    pub fn from_hex(hex: &str) -> Result<Self, ProtoverError> {
+        if hex.len() % 2 != 0 {
+            return Err(ProtoverError::Unparseable);
+        }
+
+        let mut bytes: Vec<u8> = Vec::with_capacity(hex.len() / 2);
+
+        for digits in hex.as_bytes().chunks(2) {
+            bytes.push(hex_digit(digits[0])? << 4 | hex_digit(digits[1])?);
+        }
+        ProtoSet::decode(&bytes)
+    }
+}
+
+/// Append `value` to `bytes` as a big-endian `u32`.
+///
+/// (`u32::to_be_bytes()` needs a newer Rust than our minimum version.)
+fn write_u32(bytes: &mut Vec<u8>, value: u32) {
+    bytes.push((value >> 24) as u8);
+    bytes.push((value >> 16) as u8);
+    bytes.push((value >> 8) as u8);
+    bytes.push(value as u8);
+}
+
+/// Read a big-endian `u32` from a slice of exactly 4 bytes.
+fn read_u32(bytes: &[u8]) -> u32 {
+    assert_eq!(bytes.len(), 4);
+
+    bytes
+        .iter()
+        .fold(0, |value, &byte| value << 8 | u32::from(byte))
+}
+
+/// Get the value of a single ASCII hex digit.
+fn hex_digit(digit: u8) -> Result<u8, ProtoverError> {
+    match (digit as char).to_digit(16) {
+        Some(value) => Ok(value as u8),
+        None => Err(ProtoverError::Unparseable),
+    }
+}
+
+impl FromStr for ProtoSet {
+    type Err = ProtoverError;
+
+    /// Parse the unique version numbers supported by a subprotocol from a string.
+    ///
+    /// # Inputs
+    ///
+    /// * `version_string`, a string comprised of "[0-9,-]"
+    ///
+    /// # Returns
+    ///
+    /// A `Result` whose `Ok` value is a `ProtoSet` holding all of the unique
+    /// version numbers.
+    ///
+    /// The returned `Result`'s `Err` value is an `ProtoverError` appropriate to
+    /// the error.
+    ///
+    /// # Errors
+    ///
+    /// This function will error if:
+    ///
+    /// * the `version_string` is an equals (`"="`) sign,
+    /// * the expansion of a version range produces an error (see
+    ///   `expand_version_range`),
+    /// * any single version number is not parseable as an `u32` in radix 10, or
+    /// * there are greater than 2^16 version numbers to expand.
+    ///
+    /// Pieces of `version_string` which don't parse are reported as a
+    /// `ProtoverError::UnparseableVersionString` containing the piece,
+    /// truncated to `MAX_UNPARSEABLE_TOKEN_LENGTH` bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::str::FromStr;
+    ///
+    /// use protover::errors::ProtoverError;
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// # fn do_test() -> Result<ProtoSet, ProtoverError> {
+    /// let protoset: ProtoSet = ProtoSet::from_str("2-5,8")?;
+    ///
+    /// assert!(protoset.contains(&5));
+    /// assert!(!protoset.contains(&10));
+    ///
+    /// // We can also equivalently call `ProtoSet::from_str` by doing (all
+    /// // implementations of `FromStr` can be called this way, this one isn't
+    /// // special):
+    /// let protoset: ProtoSet = "4-6,12".parse()?;
+    ///
+    /// // Calling it (either way) can take really large ranges (up to `u32::MAX`):
+    /// let protoset: ProtoSet = "1-70000".parse()?;
+    /// let protoset: ProtoSet = "1-4294967296".parse()?;
+    ///
+    /// // There are lots of ways to get an `Err` from this function.  Here are
+    /// // a few:
+    /// // The error contains the piece of the string which didn't parse:
+    /// let unparseable = |s: &str| Err(ProtoverError::UnparseableVersionString(s.to_string()));
+    ///
+    /// assert_eq!(unparseable("="), ProtoSet::from_str("="));
+    /// assert_eq!(unparseable("-"), ProtoSet::from_str("-"));
+    /// assert_eq!(unparseable("not_an_int"), ProtoSet::from_str("not_an_int"));
+    /// assert_eq!(unparseable("3-"), ProtoSet::from_str("3-"));
+    /// assert_eq!(unparseable("1-"), ProtoSet::from_str("1-,4"));
+    ///
+    /// // An empty string is, however, legal, and results in an
+    /// // empty `ProtoSet`:
+    /// assert_eq!(Ok(ProtoSet::default()), ProtoSet::from_str(""));
+    /// #
+    /// # Ok(protoset)
+    /// # }
+    /// # fn main() { do_test(); }  // wrap the test so we can use the ? operator
     /// ```
     fn from_str(version_string: &str) -> Result<Self, Self::Err> {
         // If we were passed in an empty string, then return an empty ProtoSet.
@@ -366,310 +2437,1832 @@ impl FromStr for ProtoSet {
             return Ok(Self::default());
         }
 
-        let mut pairs: Vec<(Version, Version)> = Vec::new();
-        let pieces: ::std::str::Split<char> = version_string.split(',');
+        let mut pairs: Vec<(Version, Version)> = Vec::new();
+        let pieces: ::std::str::Split<char> = version_string.split(',');
+
+        for p in pieces {
+            if p.contains('-') {
+                let mut pair = p.splitn(2, '-');
+
+                let unparseable = || ProtoverError::unparseable_version_string(p);
+
+                let low = pair.next().ok_or_else(unparseable)?;
+                let high = pair.next().ok_or_else(unparseable)?;
+
+                let lo: Version = low.parse().map_err(|_| unparseable())?;
+                let hi: Version = high.parse().map_err(|_| unparseable())?;
+
+                pairs.push((lo, hi));
+            } else {
+                let v: u32 = p
+                    .parse()
+                    .map_err(|_| ProtoverError::unparseable_version_string(p))?;
+
+                pairs.push((v, v));
+            }
+        }
+
+        ProtoSet::from_slice(&pairs[..])
+    }
+}
+
+impl ToString for ProtoSet {
+    /// Contracts a `ProtoSet` of versions into a string.
+    ///
+    /// # Returns
+    ///
+    /// A `String` representation of this `ProtoSet` in ascending order.
+    fn to_string(&self) -> String {
+        let mut final_output: Vec<String> = Vec::new();
+
+        for &(lo, hi) in self.iter() {
+            if lo != hi {
+                debug_assert!(lo < hi);
+                final_output.push(format!("{}-{}", lo, hi));
+            } else {
+                final_output.push(format!("{}", lo));
+            }
+        }
+        final_output.join(",")
+    }
+}
+
+/// Displays a `ProtoSet` in a particular style, without changing how
+/// `ProtoSet::to_string()` behaves.
+///
+/// Use `ProtoSet::display_flat()` or `ProtoSet::display_ranges()` to get one.
+#[derive(Clone, Copy, Debug)]
+pub struct ProtoSetDisplay<'a> {
+    protoset: &'a ProtoSet,
+    /// Write every `Version`, rather than `low-high` ranges.
+    flat: bool,
+}
+
+impl<'a> fmt::Display for ProtoSetDisplay<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut separator: &str = "";
+
+        for &(low, high) in self.protoset.iter() {
+            if self.flat {
+                for version in low..=high {
+                    write!(f, "{}{}", separator, version)?;
+                    separator = ",";
+                }
+            } else if low != high {
+                write!(f, "{}{}-{}", separator, low, high)?;
+            } else {
+                write!(f, "{}{}", separator, low)?;
+            }
+            separator = ",";
+        }
+        Ok(())
+    }
+}
+
+/// Checks to see if there is a continuous range of integers, starting at the
+/// first in the list. Returns the last integer in the range if a range exists.
+///
+/// # Inputs
+///
+/// `list`, an ordered  vector of `u32` integers of "[0-9,-]" representing the
+/// supported versions for a single protocol.
+///
+/// # Returns
+///
+/// A `bool` indicating whether the list contains a range, starting at the first
+/// in the list, a`Version` of the last integer in the range, and a `usize` of
+/// the index of that version.
+///
+/// For example, if given vec![1, 2, 3, 5], find_range will return true,
+/// as there is a continuous range, and 3, which is the last number in the
+/// continuous range, and 2 which is the index of 3.
+fn find_range(list: &Vec<Version>) -> (bool, Version, usize) {
+    if list.len() == 0 {
+        return (false, 0, 0);
+    }
+
+    let mut index: usize = 0;
+    let mut iterable = list.iter().peekable();
+    let mut range_end = match iterable.next() {
+        Some(n) => *n,
+        None => return (false, 0, 0),
+    };
+
+    let mut has_range = false;
+
+    while iterable.peek().is_some() {
+        let n = *iterable.next().unwrap();
+        if n != range_end + 1 {
+            break;
+        }
+
+        has_range = true;
+        range_end = n;
+        index += 1;
+    }
+
+    (has_range, range_end, index)
+}
+
+impl From<Vec<Version>> for ProtoSet {
+    fn from(mut v: Vec<Version>) -> ProtoSet {
+        let mut version_pairs: Vec<(Version, Version)> = Vec::new();
+
+        v.sort_unstable();
+        v.dedup();
+
+        'vector: while !v.is_empty() {
+            let (has_range, end, index): (bool, Version, usize) = find_range(&v);
+
+            if has_range {
+                let first: Version = match v.first() {
+                    Some(x) => *x,
+                    None => continue,
+                };
+                let last: Version = match v.get(index) {
+                    Some(x) => *x,
+                    None => continue,
+                };
+                debug_assert!(last == end, format!("last = {}, end = {}", last, end));
+
+                version_pairs.push((first, last));
+                v = v.split_off(index + 1);
+
+                if v.len() == 0 {
+                    break 'vector;
+                }
+            } else {
+                let last: Version = match v.get(index) {
+                    Some(x) => *x,
+                    None => continue,
+                };
+                version_pairs.push((last, last));
+                v.remove(index);
+            }
+        }
+        ProtoSet::from_slice(&version_pairs[..]).unwrap_or(ProtoSet::default())
+    }
+}
+
+/// Contract a `BTreeSet` of versions into a `ProtoSet`.
+///
+/// A `BTreeSet` iterates in ascending order without duplicates, so the ranges
+/// can be built in a single pass, without sorting.  Like `From<Vec<Version>>`,
+/// this returns an empty `ProtoSet` if the versions are not valid.
+impl From<BTreeSet<Version>> for ProtoSet {
+    fn from(versions: BTreeSet<Version>) -> ProtoSet {
+        ProtoSet::from_sorted_versions(versions).unwrap_or_default()
+    }
+}
+
+/// Convert a `Vec` of Rust `RangeInclusive`s into a `ProtoSet`.
+///
+/// The ranges may be in any order, and overlapping or adjacent ranges are
+/// merged.  Empty ranges (where the start is greater than the end) are
+/// ignored.
+///
+/// # Errors
+///
+/// * `ProtoverError::Overlap`: if any range contains version 0, and
+/// * `ProtoverError::ExceedsMax`: if any range contains `u32::MAX`.
+impl TryFrom<Vec<RangeInclusive<Version>>> for ProtoSet {
+    type Error = ProtoverError;
+
+    fn try_from(ranges: Vec<RangeInclusive<Version>>) -> Result<Self, Self::Error> {
+        let pairs: Vec<(Version, Version)> = ranges
+            .into_iter()
+            .map(|range| range.into_inner())
+            .filter(|&(low, high)| low <= high)
+            .collect();
+
+        ProtoSet::try_from_unsorted(pairs)
+    }
+}
+
+/// Expand a `ProtoSet` into a `BTreeSet` of all its versions.
+impl From<ProtoSet> for BTreeSet<Version> {
+    fn from(protoset: ProtoSet) -> BTreeSet<Version> {
+        protoset.expand().into_iter().collect()
+    }
+}
+
+/// Borrow the `(low, high)` pairs in this `ProtoSet`.
+///
+/// The pairs are guaranteed to be sorted, and not to overlap, but adjacent
+/// pairs are only merged if the set has been normalized.
+///
+/// # Examples
+///
+/// ```
+/// use protover::protoset::ProtoSet;
+/// use protover::protoset::Version;
+///
+/// fn widest<T: AsRef<[(Version, Version)]>>(pairs: T) -> Version {
+///     pairs.as_ref().iter().map(|&(low, high)| high - low + 1).max().unwrap_or(0)
+/// }
+///
+/// let protoset: ProtoSet = "1-3,7-20".parse().unwrap();
+///
+/// assert_eq!(widest(&protoset), 14);
+/// ```
+impl AsRef<[(Version, Version)]> for ProtoSet {
+    fn as_ref(&self) -> &[(Version, Version)] {
+        self.pairs.as_slice()
+    }
+}
+
+/// Dereference a `ProtoSet` to its sorted, non-overlapping `(low, high)`
+/// pairs, like `AsRef`.
+///
+/// Beware that `ProtoSet`'s own methods take priority over slice methods
+/// with the same name.  In particular, `len()` counts `Version`s, not pairs,
+/// and `contains()` and `get()` take `Version`s.
+impl Deref for ProtoSet {
+    type Target = [(Version, Version)];
+
+    fn deref(&self) -> &[(Version, Version)] {
+        self.pairs.as_slice()
+    }
+}
+
+/// Assert that two `ProtoSet`s contain the same `Version`s.
+///
+/// This compares the normalized `(low, high)` pairs, so it doesn't depend on
+/// how either set happens to be split into ranges, or on the string format.
+#[cfg(test)]
+macro_rules! assert_protoset_eq {
+    ($left:expr, $right:expr) => {{
+        let (left, right) = (&$left, &$right);
+        let mut left_normalized: $crate::protoset::ProtoSet = ::std::clone::Clone::clone(left);
+        let mut right_normalized: $crate::protoset::ProtoSet = ::std::clone::Clone::clone(right);
+
+        left_normalized.normalize();
+        right_normalized.normalize();
+        assert_eq!(
+            left_normalized.pairs, right_normalized.pairs,
+            "ProtoSets differ: {:?} != {:?}",
+            left, right
+        );
+    }};
+}
+
+/// Assert that a `ProtoSet` contains exactly the listed `Version`s, in
+/// ascending order.
+#[cfg(test)]
+macro_rules! assert_protoset_contains_exactly {
+    ($protoset:expr, [$($version:expr),* $(,)*]) => {{
+        let expected: Vec<$crate::protoset::Version> = vec![$($version),*];
+
+        assert_eq!(expected, $crate::protoset::ProtoSet::to_vec(&$protoset));
+    }};
+}
+
+#[cfg(test)]
+mod test {
+    use std::i64;
+    use std::u64;
+    use std::usize;
+
+    use super::*;
+    use errors::MAX_UNPARSEABLE_TOKEN_LENGTH;
+    use rng::TestRng;
+
+    #[test]
+    fn test_find_range() {
+        assert_eq!((false, 0, 0), find_range(&vec![]));
+        assert_eq!((false, 1, 0), find_range(&vec![1]));
+        assert_eq!((true, 2, 1), find_range(&vec![1, 2]));
+        assert_eq!((true, 3, 2), find_range(&vec![1, 2, 3]));
+        assert_eq!((true, 3, 2), find_range(&vec![1, 2, 3, 5]));
+    }
+
+    macro_rules! assert_contains_each {
+        ($protoset:expr, $versions:expr) => {
+            for version in $versions {
+                assert!($protoset.contains(version));
+            }
+        };
+    }
+
+    macro_rules! test_protoset_contains_versions {
+        ($list:expr, $str:expr) => {
+            let versions: &[Version] = $list;
+            let protoset: Result<ProtoSet, ProtoverError> = ProtoSet::from_str($str);
+
+            assert!(protoset.is_ok());
+            let p = protoset.unwrap();
+            assert_contains_each!(p, versions);
+        };
+    }
+
+    #[test]
+    fn test_versions_from_str() {
+        test_protoset_contains_versions!(&[], "");
+        test_protoset_contains_versions!(&[1], "1");
+        test_protoset_contains_versions!(&[1, 2], "1,2");
+        test_protoset_contains_versions!(&[1, 2, 3], "1-3");
+        test_protoset_contains_versions!(&[1, 2, 5], "1-2,5");
+        test_protoset_contains_versions!(&[1, 3, 4, 5], "1,3-5");
+        test_protoset_contains_versions!(&[42, 55, 56, 57, 58], "42,55-58");
+    }
+
+    fn unparseable(token: &str) -> Result<ProtoSet, ProtoverError> {
+        Err(ProtoverError::UnparseableVersionString(token.to_string()))
+    }
+
+    #[test]
+    fn test_versions_from_str_ab() {
+        assert_eq!(unparseable("a"), ProtoSet::from_str("a,b"));
+    }
+
+    #[test]
+    fn test_versions_from_str_negative_1() {
+        assert_eq!(unparseable("-1"), ProtoSet::from_str("-1"));
+    }
+
+    #[test]
+    fn test_versions_from_str_commas() {
+        assert_eq!(unparseable(""), ProtoSet::from_str(","));
+        assert_eq!(unparseable(""), ProtoSet::from_str("1,,2"));
+        assert_eq!(unparseable(""), ProtoSet::from_str("1,2,"));
+    }
+
+    #[test]
+    fn test_versions_from_str_hyphens() {
+        assert_eq!(unparseable("--1"), ProtoSet::from_str("--1"));
+        assert_eq!(unparseable("-1-2"), ProtoSet::from_str("-1-2"));
+        assert_eq!(unparseable("1--2"), ProtoSet::from_str("1--2"));
+    }
+
+    #[test]
+    fn test_versions_from_str_triple() {
+        assert_eq!(unparseable("1-2-3"), ProtoSet::from_str("1-2-3"));
+    }
+
+    #[test]
+    fn test_versions_from_str_1exclam() {
+        assert_eq!(unparseable("!"), ProtoSet::from_str("1,!"));
+    }
+
+    #[test]
+    fn test_versions_from_str_percent_equal() {
+        assert_eq!(unparseable("%="), ProtoSet::from_str("%="));
+    }
+
+    #[test]
+    fn test_versions_from_str_truncates_long_tokens() {
+        let long: String = "9".repeat(1000);
+        let token: &str = &long[..MAX_UNPARSEABLE_TOKEN_LENGTH];
+
+        assert_eq!(
+            unparseable(token),
+            ProtoSet::from_str(&format!("1,{}", long))
+        );
+        let range: String = format!("1-{}", long);
+        assert_eq!(
+            unparseable(&range[..MAX_UNPARSEABLE_TOKEN_LENGTH]),
+            ProtoSet::from_str(&range)
+        );
+
+        // Truncation doesn't split multi-byte characters.
+        let wide: String = "\u{e9}".repeat(100);
+        let error = ProtoSet::from_str(&wide);
+
+        assert_eq!(unparseable(&wide[..MAX_UNPARSEABLE_TOKEN_LENGTH]), error);
+        assert_eq!(unparseable(&"\u{e9}".repeat(25)), error);
+
+        let odd: String = format!("x{}", wide);
+        assert_eq!(
+            unparseable(&odd[..MAX_UNPARSEABLE_TOKEN_LENGTH - 1]),
+            ProtoSet::from_str(&odd)
+        );
+    }
+
+    #[test]
+    fn test_versions_from_str_whitespace() {
+        assert_eq!(unparseable("2\n"), ProtoSet::from_str("1,2\n"));
+        assert_eq!(unparseable("1\r"), ProtoSet::from_str("1\r,2"));
+        assert_eq!(unparseable("\t2"), ProtoSet::from_str("1,\t2"));
+    }
+
+    #[test]
+    fn test_versions_from_str_overlap() {
+        assert_eq!(Err(ProtoverError::Overlap), ProtoSet::from_str("1-3,2-4"));
+    }
+
+    #[test]
+    fn test_versions_from_slice_overlap() {
+        assert_eq!(
+            Err(ProtoverError::Overlap),
+            ProtoSet::from_slice(&[(1, 3), (2, 4)])
+        );
+    }
+
+    #[test]
+    fn test_versions_from_str_max() {
+        assert_eq!(
+            Err(ProtoverError::ExceedsMax),
+            ProtoSet::from_str("4294967295")
+        );
+    }
+
+    #[test]
+    fn test_versions_from_slice_max() {
+        assert_eq!(
+            Err(ProtoverError::ExceedsMax),
+            ProtoSet::from_slice(&[(4294967295, 4294967295)])
+        );
+    }
+
+    #[test]
+    fn test_protoset_contains() {
+        let protoset: ProtoSet = ProtoSet::from_slice(&[(1, 5), (7, 9), (13, 14)]).unwrap();
+
+        for x in 1..6 {
+            assert!(protoset.contains(&x), format!("should contain {}", x));
+        }
+        for x in 7..10 {
+            assert!(protoset.contains(&x), format!("should contain {}", x));
+        }
+        for x in 13..15 {
+            assert!(protoset.contains(&x), format!("should contain {}", x));
+        }
+
+        for x in [6, 10, 11, 12, 15, 42, 43, 44, 45, 1234584].iter() {
+            assert!(!protoset.contains(&x), format!("should not contain {}", x));
+        }
+    }
+
+    #[test]
+    fn test_protoset_contains_1_3() {
+        let protoset: ProtoSet = ProtoSet::from_slice(&[(1, 3)]).unwrap();
+
+        for x in 1..4 {
+            assert!(protoset.contains(&x), format!("should contain {}", x));
+        }
+    }
+
+    macro_rules! assert_protoset_from_vec_contains_all {
+        ($($x:expr),*) => (
+            let vec: Vec<Version> = vec!($($x),*);
+            let protoset: ProtoSet = vec.clone().into();
+
+            for x in vec.iter() {
+                assert!(protoset.contains(&x));
+            }
+        )
+    }
+
+    #[test]
+    fn test_protoset_from_vec_123() {
+        assert_protoset_from_vec_contains_all!(1, 2, 3);
+    }
+
+    #[test]
+    fn test_protoset_from_vec_1_315() {
+        assert_protoset_from_vec_contains_all!(1, 2, 3, 15);
+    }
+
+    #[test]
+    fn test_protoset_from_vec_unordered() {
+        let v: Vec<Version> = vec![2, 3, 8, 4, 3, 9, 7, 2];
+        let ps: ProtoSet = v.into();
+
+        assert_eq!(ps.to_string(), "2-4,7-9");
+    }
+
+    #[test]
+    fn test_protoset_expand_bounded() {
+        let ps: ProtoSet = "1-3,7".parse().unwrap();
+
+        assert_eq!(Ok(vec![1, 2, 3, 7]), ps.expand_bounded(4));
+        assert_eq!(Ok(vec![1, 2, 3, 7]), ps.expand_bounded(100));
+        assert_eq!(
+            Err(ProtoverError::ExceedsExpansionLimit),
+            ps.expand_bounded(3)
+        );
+        assert_eq!(Ok(vec![]), ProtoSet::default().expand_bounded(0));
+    }
+
+    #[test]
+    fn test_protoset_expand_bounded_does_not_dos() {
+        let ps: ProtoSet = "1-4294967294".parse().unwrap();
+
+        assert_eq!(
+            Err(ProtoverError::ExceedsExpansionLimit),
+            ps.expand_bounded(1 << 16)
+        );
+    }
+
+    #[test]
+    fn test_protoset_get() {
+        let ps: ProtoSet = "1-3,7,10-12".parse().unwrap();
+        let expanded: Vec<Version> = ps.clone().expand();
+
+        assert_eq!(Some(1), ps.get(0));
+        assert_eq!(Some(12), ps.get(ps.len() - 1));
+        assert_eq!(None, ps.get(ps.len()));
+        assert_eq!(None, ps.get(usize::MAX));
+
+        for (n, version) in expanded.iter().enumerate() {
+            assert_eq!(Some(*version), ps.get(n));
+        }
+    }
+
+    #[test]
+    fn test_protoset_get_empty() {
+        assert_eq!(None, ProtoSet::default().get(0));
+    }
+
+    #[test]
+    fn test_protoset_normalize_no_merges() {
+        let mut ps: ProtoSet = "1-3,5,7-9".parse().unwrap();
+        let before: ProtoSet = ps.clone();
+
+        ps.normalize();
+        assert_eq!(before, ps);
+
+        let mut empty: ProtoSet = ProtoSet::default();
+
+        empty.normalize();
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn test_protoset_normalize_one_merge() {
+        let mut ps: ProtoSet = "1-3,4-6".parse().unwrap();
+
+        ps.normalize();
+        assert_eq!(vec![(1, 6)], ps.pairs);
+    }
+
+    #[test]
+    fn test_protoset_normalize_multiple_merges() {
+        let mut ps: ProtoSet = ProtoSet::from_sorted_unchecked(vec![
+            (10, 12),
+            (1, 2),
+            (3, 3),
+            (4, 8),
+            (5, 6),
+            (12, 20),
+            (30, 30),
+        ]);
+
+        ps.normalize();
+        assert_eq!(vec![(1, 8), (10, 20), (30, 30)], ps.pairs);
+        assert_eq!(20, ps.len());
+        assert_eq!("1-8,10-20,30", ps.to_string());
+    }
+
+    #[test]
+    fn test_protoset_union() {
+        let a: ProtoSet = "1-3,7,20-30".parse().unwrap();
+        let b: ProtoSet = "4-5,8-9,25-40".parse().unwrap();
+
+        assert_eq!("1-5,7-9,20-40", a.union(&b).to_string());
+        assert_eq!(a.union(&b), b.union(&a));
+        assert_eq!(a, a.union(&ProtoSet::default()));
+        assert_eq!(a, ProtoSet::default().union(&a));
+    }
+
+    #[test]
+    fn test_protoset_version_before_and_after_inside_range() {
+        let ps: ProtoSet = "1-3,7-9,20".parse().unwrap();
+
+        assert_eq!(Some(7), ps.version_before(8));
+        assert_eq!(Some(9), ps.version_after(8));
+    }
+
+    #[test]
+    fn test_protoset_version_before_and_after_range_boundaries() {
+        let ps: ProtoSet = "1-3,7-9,20".parse().unwrap();
+
+        assert_eq!(Some(3), ps.version_before(7));
+        assert_eq!(Some(8), ps.version_after(7));
+        assert_eq!(Some(8), ps.version_before(9));
+        assert_eq!(Some(20), ps.version_after(9));
+        assert_eq!(Some(9), ps.version_before(20));
+        assert_eq!(None, ps.version_after(20));
+    }
+
+    #[test]
+    fn test_protoset_version_before_and_after_between_ranges() {
+        let ps: ProtoSet = "1-3,7-9,20".parse().unwrap();
+
+        assert_eq!(Some(3), ps.version_before(5));
+        assert_eq!(Some(7), ps.version_after(5));
+        assert_eq!(Some(9), ps.version_before(15));
+        assert_eq!(Some(20), ps.version_after(15));
+    }
+
+    #[test]
+    fn test_protoset_version_before_and_after_outside_ranges() {
+        let ps: ProtoSet = "1-3,7-9,4294967294".parse().unwrap();
+
+        assert_eq!(None, ps.version_before(0));
+        assert_eq!(None, ps.version_before(1));
+        assert_eq!(Some(1), ps.version_after(0));
+        assert_eq!(Some(4294967294), ps.version_before(u32::MAX));
+        assert_eq!(None, ps.version_after(4294967294));
+        assert_eq!(None, ps.version_after(u32::MAX));
+        assert_eq!(None, ProtoSet::default().version_before(5));
+        assert_eq!(None, ProtoSet::default().version_after(5));
+    }
+
+    #[test]
+    fn test_protoset_max_contiguous_range() {
+        let ps: ProtoSet = "1,3-4,10-1000,1002,1004-1005".parse().unwrap();
+        assert_eq!(Some((10, 1000)), ps.max_contiguous_range());
+
+        let ps: ProtoSet = "7-9".parse().unwrap();
+        assert_eq!(Some((7, 9)), ps.max_contiguous_range());
+
+        assert_eq!(None, ProtoSet::default().max_contiguous_range());
+    }
+
+    #[test]
+    fn test_protoset_max_contiguous_range_ties() {
+        let ps: ProtoSet = "1,3-5,7,9-11".parse().unwrap();
+        assert_eq!(Some((3, 5)), ps.max_contiguous_range());
+
+        let ps: ProtoSet = "1,3,5".parse().unwrap();
+        assert_eq!(Some((1, 1)), ps.max_contiguous_range());
+    }
+
+    #[test]
+    fn test_protoset_split_at() {
+        let ps: ProtoSet = "1-3,5-9,4294967294".parse().unwrap();
+
+        let (below, above) = ps.split_at(5);
+        assert_eq!("1-3", below.to_string());
+        assert_eq!("5-9,4294967294", above.to_string());
+        assert_eq!(3, below.len());
+        assert_eq!(6, above.len());
+
+        let (below, above) = ps.split_at(4);
+        assert_eq!("1-3", below.to_string());
+        assert_eq!("5-9,4294967294", above.to_string());
+
+        let (below, above) = ps.split_at(u32::MAX);
+        assert_eq!(ps, below);
+        assert!(above.is_empty());
+
+        let (below, above) = ps.split_at(0);
+        assert!(below.is_empty());
+        assert_eq!(ps, above);
+    }
+
+    #[test]
+    fn test_protoset_partition_splits_evenly() {
+        let ps: ProtoSet = "1-4,10-13".parse().unwrap();
+        let (low, high) = ps.partition(|v| v < 10).unwrap();
+
+        assert_eq!("1-4", low.to_string());
+        assert_eq!("10-13", high.to_string());
+        assert_eq!(ps.split_at(10), (low, high));
+
+        let (odd, even) = ps.partition(|v| v % 2 == 1).unwrap();
+        assert_eq!("1,3,11,13", odd.to_string());
+        assert_eq!("2,4,10,12", even.to_string());
+        assert_eq!(4, odd.len());
+        assert_eq!(ps, odd.union(&even));
+    }
+
+    #[test]
+    fn test_protoset_partition_matches_nothing_or_everything() {
+        let ps: ProtoSet = "1-3,5,4294967294".parse().unwrap();
+
+        let (matching, non_matching) = ps.partition(|_| false).unwrap();
+        assert!(matching.is_empty());
+        assert_eq!(ps, non_matching);
+
+        let (matching, non_matching) = ps.partition(|_| true).unwrap();
+        assert_eq!(ps, matching);
+        assert!(non_matching.is_empty());
+
+        let (matching, non_matching) = ProtoSet::default().partition(|_| true).unwrap();
+        assert!(matching.is_empty() && non_matching.is_empty());
+    }
+
+    #[test]
+    fn test_protoset_partition_exceeds_expansion_limit() {
+        let ps: ProtoSet = "1-4294967294".parse().unwrap();
+        assert_eq!(
+            Err(ProtoverError::ExceedsExpansionLimit),
+            ps.partition(|_| true)
+        );
+
+        let ps: ProtoSet =
+            ProtoSet::from_slice(&[(1, MAX_PROTOCOLS_TO_EXPAND as Version)]).unwrap();
+        let (matching, non_matching) = ps.partition(|v| v > 1).unwrap();
+        assert_eq!(1, non_matching.len());
+        assert_eq!(MAX_PROTOCOLS_TO_EXPAND - 1, matching.len());
+    }
+
+    #[test]
+    fn test_protoset_count_pairs() {
+        assert_eq!(0, ProtoSet::default().count_pairs());
+        assert_eq!(1, "1-10".parse::<ProtoSet>().unwrap().count_pairs());
+        assert_eq!(3, "1,3,5".parse::<ProtoSet>().unwrap().count_pairs());
+    }
+
+    #[test]
+    fn test_protoset_cached_count_is_consistent() {
+        let mut ps: ProtoSet = "1-3,4,9,20-4294967294".parse().unwrap();
+        ps.assert_count_is_consistent();
+
+        ps.insert(10).unwrap();
+        ps.assert_count_is_consistent();
+        ps.insert(5).unwrap();
+        ps.assert_count_is_consistent();
+        ps.remove(2);
+        ps.assert_count_is_consistent();
+        ps.remove(2);
+        ps.assert_count_is_consistent();
+        ps.normalize();
+        ps.assert_count_is_consistent();
+
+        let other: ProtoSet = "2,7-30".parse().unwrap();
+        ps.union(&other).assert_count_is_consistent();
+        ps.and_not_in(&other).assert_count_is_consistent();
+        other.and_not_in(&ps).assert_count_is_consistent();
+        other.shift(-1).unwrap().assert_count_is_consistent();
+
+        let (a, b) = ps.split_at(25);
+        a.assert_count_is_consistent();
+        b.assert_count_is_consistent();
+        let (a, b) = other.partition(|v| v % 3 == 0).unwrap();
+        a.assert_count_is_consistent();
+        b.assert_count_is_consistent();
+
+        ProtoSet::unpack(0xf0f0).assert_count_is_consistent();
+        ProtoSet::decode(&ps.encode())
+            .unwrap()
+            .assert_count_is_consistent();
+
+        let _ = ps.drain();
+        ps.assert_count_is_consistent();
+    }
+
+    #[test]
+    fn test_protoset_hex_round_trip() {
+        for versions in &["", "1", "1-3,5,10-4294967294", "1-63"] {
+            let ps: ProtoSet = versions.parse().unwrap();
+            let hex = ps.to_hex();
+
+            assert_eq!(ps.encode().len() * 2, hex.len());
+            assert!(hex
+                .chars()
+                .all(|c| c.is_ascii_digit() || c.is_ascii_lowercase()));
+            assert_eq!(Ok(ps), ProtoSet::from_hex(&hex));
+        }
+    }
+
+    #[test]
+    fn test_protoset_from_hex_upper_case() {
+        let ps: ProtoSet = "10-4294967294".parse().unwrap();
 
-        for p in pieces {
-            if p.contains('-') {
-                let mut pair = p.splitn(2, '-');
+        assert_eq!(
+            Ok(ps.clone()),
+            ProtoSet::from_hex(&ps.to_hex().to_uppercase())
+        );
+    }
+
+    #[test]
+    fn test_protoset_from_hex_invalid() {
+        let unparseable = Err(ProtoverError::Unparseable);
 
-                let low = pair.next().ok_or(ProtoverError::Unparseable)?;
-                let high = pair.next().ok_or(ProtoverError::Unparseable)?;
+        assert_eq!(unparseable, ProtoSet::from_hex(""));
+        assert_eq!(unparseable, ProtoSet::from_hex("0000000"));
+        assert_eq!(unparseable, ProtoSet::from_hex("0000000g"));
+        assert_eq!(unparseable, ProtoSet::from_hex("+0000000"));
+        assert_eq!(unparseable, ProtoSet::from_hex("000000é"));
+        assert_eq!(unparseable, ProtoSet::from_hex("0000000100000001"));
+        assert_eq!(Ok(ProtoSet::default()), ProtoSet::from_hex("00000000"));
+    }
 
-                let lo: Version = low.parse().or(Err(ProtoverError::Unparseable))?;
-                let hi: Version = high.parse().or(Err(ProtoverError::Unparseable))?;
+    #[test]
+    fn test_protoset_from_str_bounded() {
+        let too_big = Err(ProtoverError::ExceedsExpansionLimit);
 
-                pairs.push((lo, hi));
-            } else {
-                let v: u32 = p.parse().or(Err(ProtoverError::Unparseable))?;
+        assert_eq!(65536, ProtoSet::from_str_bounded("1-65536").unwrap().len());
+        assert_eq!(too_big, ProtoSet::from_str_bounded("1-65537"));
+        assert_eq!(too_big, ProtoSet::from_str_bounded("1-3,5-65538"));
+        assert_eq!(Ok(ProtoSet::default()), ProtoSet::from_str_bounded(""));
+        assert_eq!(too_big, ProtoSet::from_str_bounded("2-65538"));
+        assert_eq!(
+            Err(ProtoverError::Overlap),
+            ProtoSet::from_str_bounded("0-65535")
+        );
+        assert_eq!(unparseable("1-a"), ProtoSet::from_str_bounded("1-a"));
+    }
 
-                pairs.push((v, v));
-            }
+    #[test]
+    fn test_protoset_from_str_bounded_rejects_large_ranges() {
+        // These can still be parsed, and stored as ranges.
+        for versions in &["1-4294967294", "1-65537", "1-65536,70000"] {
+            let ps: ProtoSet = versions.parse().unwrap();
+
+            assert!(ps.len() > MAX_PROTOCOLS_TO_EXPAND);
+            assert_eq!(
+                Err(ProtoverError::ExceedsExpansionLimit),
+                ProtoSet::from_str_bounded(versions)
+            );
+            assert_eq!(
+                Err(ProtoverError::ExceedsExpansionLimit),
+                ProtoSet::from_sorted_bounded(ps.pairs.clone())
+            );
         }
+    }
 
-        ProtoSet::from_slice(&pairs[..])
+    #[test]
+    fn test_protoset_from_sorted_bounded() {
+        assert_eq!(
+            Ok(vec![(1, 2), (4, 65537)]),
+            ProtoSet::from_sorted_bounded(vec![(1, 2), (4, 65537)]).map(|ps| ps.pairs)
+        );
+        assert_eq!(
+            Err(ProtoverError::Overlap),
+            ProtoSet::from_sorted_bounded(vec![(4, 5), (1, 2)])
+        );
     }
-}
 
-impl ToString for ProtoSet {
-    /// Contracts a `ProtoSet` of versions into a string.
-    ///
-    /// # Returns
-    ///
-    /// A `String` representation of this `ProtoSet` in ascending order.
-    fn to_string(&self) -> String {
-        let mut final_output: Vec<String> = Vec::new();
+    #[test]
+    fn test_protoset_as_ref_and_deref() {
+        let ps: ProtoSet = "1-3,5,10-12".parse().unwrap();
+        let pairs: &[(Version, Version)] = ps.as_ref();
 
-        for &(lo, hi) in self.iter() {
-            if lo != hi {
-                debug_assert!(lo < hi);
-                final_output.push(format!("{}-{}", lo, hi));
-            } else {
-                final_output.push(format!("{}", lo));
+        assert_eq!(&[(1, 3), (5, 5), (10, 12)], pairs);
+        assert_eq!(pairs, &*ps);
+        assert_eq!(Some(&(10, 12)), ps.last());
+        assert_eq!(7, ps.len());
+        assert!(ps.contains(&11));
+
+        let empty = ProtoSet::default();
+        assert!(empty.as_ref().is_empty());
+        assert_eq!(None, empty.first());
+    }
+
+    #[test]
+    fn test_protoset_extend_range_already_contained() {
+        let mut ps: ProtoSet = "1-5,9-12".parse().unwrap();
+        let original = ps.clone();
+
+        ps.extend_range(2, 4).unwrap();
+        ps.extend_range(9, 12).unwrap();
+        ps.extend_version(10).unwrap();
+        assert_eq!(original, ps);
+    }
+
+    #[test]
+    fn test_protoset_extend_range_bridges_ranges() {
+        let mut ps: ProtoSet = "1-3,5-6,8-9,20".parse().unwrap();
+
+        ps.extend_range(4, 7).unwrap();
+        assert_eq!("1-9,20", ps.to_string());
+        assert_eq!(10, ps.len());
+        ps.assert_count_is_consistent();
+
+        ps.extend_range(15, 25).unwrap();
+        assert_eq!("1-9,15-25", ps.to_string());
+        ps.assert_count_is_consistent();
+
+        ps.extend_version(10).unwrap();
+        ps.extend_version(14).unwrap();
+        assert_eq!("1-10,14-25", ps.to_string());
+        ps.assert_count_is_consistent();
+    }
+
+    #[test]
+    fn test_protoset_extend_range_at_the_ends() {
+        let mut ps = ProtoSet::default();
+
+        ps.extend_range(4294967290, 4294967294).unwrap();
+        ps.extend_range(1, 2).unwrap();
+        ps.extend_version(3).unwrap();
+        assert_eq!("1-3,4294967290-4294967294", ps.to_string());
+        ps.assert_count_is_consistent();
+    }
+
+    #[test]
+    fn test_protoset_extend_range_invalid() {
+        let mut ps: ProtoSet = "1-3".parse().unwrap();
+
+        assert_eq!(
+            Err(ProtoverError::LowGreaterThanHigh),
+            ps.extend_range(5, 4)
+        );
+        assert_eq!(Err(ProtoverError::ExceedsMax), ps.extend_range(5, u32::MAX));
+        assert_eq!(Err(ProtoverError::ExceedsMax), ps.extend_version(u32::MAX));
+        assert_eq!(Err(ProtoverError::Overlap), ps.extend_range(0, 5));
+        assert_eq!(Err(ProtoverError::Overlap), ps.extend_version(0));
+        assert_eq!("1-3", ps.to_string());
+    }
+
+    #[test]
+    fn test_protoset_display_flat() {
+        let ps: ProtoSet = "1-3,4,9,11-12".parse().unwrap();
+
+        assert_eq!("1,2,3,4,9,11,12", ps.display_flat().to_string());
+        assert_eq!("", ProtoSet::default().display_flat().to_string());
+        assert_eq!(
+            "4294967293,4294967294",
+            ProtoSet::from_slice(&[(4294967293, 4294967294)])
+                .unwrap()
+                .display_flat()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_protoset_display_ranges_matches_to_string() {
+        for versions in &["", "1", "1-3,4,9,11-12", "5-4294967294"] {
+            let ps: ProtoSet = versions.parse().unwrap();
+
+            assert_eq!(*versions, ps.display_ranges().to_string());
+            assert_eq!(ps.to_string(), format!("{}", ps.display_ranges()));
+        }
+    }
+
+    #[test]
+    fn test_protoset_count_missing_in_range_full_coverage() {
+        let ps: ProtoSet = "1-3,4-10,20".parse().unwrap();
+
+        assert_eq!(0, ps.count_missing_in_range(1, 10));
+        assert_eq!(0, ps.count_missing_in_range(3, 5));
+        assert_eq!(0, ps.count_missing_in_range(20, 20));
+        assert_eq!(10, ps.count_versions_in_range(1, 10));
+    }
+
+    #[test]
+    fn test_protoset_count_missing_in_range_no_coverage() {
+        let ps: ProtoSet = "1-3,20".parse().unwrap();
+
+        assert_eq!(16, ps.count_missing_in_range(4, 19));
+        assert_eq!(10, ps.count_missing_in_range(21, 30));
+        assert_eq!(5, ProtoSet::default().count_missing_in_range(1, 5));
+        assert_eq!(0, ps.count_versions_in_range(4, 19));
+    }
+
+    #[test]
+    fn test_protoset_count_missing_in_range_partial_coverage() {
+        let ps: ProtoSet = "1-3,5,8-20,4294967294".parse().unwrap();
+
+        assert_eq!(3, ps.count_missing_in_range(2, 9));
+        assert_eq!(5, ps.count_versions_in_range(2, 9));
+        assert_eq!(1, ps.count_missing_in_range(0, 1));
+        assert_eq!(
+            4294967296 - ps.len(),
+            ps.count_missing_in_range(0, u32::MAX)
+        );
+        assert_eq!(0, ps.count_missing_in_range(9, 2));
+        assert_eq!(0, ps.count_versions_in_range(9, 2));
+        // An empty range inside a single pair.
+        assert_eq!(0, ps.count_missing_in_range(12, 10));
+        assert_eq!(0, ps.count_versions_in_range(12, 10));
+    }
+
+    #[test]
+    fn test_protoset_try_merge_within_limit() {
+        let a: ProtoSet = "1-32768".parse().unwrap();
+        let b: ProtoSet = "32769-65536".parse().unwrap();
+
+        let merged = a.try_merge(&b).unwrap();
+        assert_eq!("1-65536", merged.to_string());
+        assert_eq!(MAX_PROTOCOLS_TO_EXPAND, merged.len());
+
+        // Overlapping versions are only counted once.
+        let c: ProtoSet = "1-65536".parse().unwrap();
+        assert_eq!(Ok(c.clone()), c.try_merge(&a));
+    }
+
+    #[test]
+    fn test_protoset_try_merge_exceeds_limit() {
+        let a: ProtoSet = "1-32768".parse().unwrap();
+        let b: ProtoSet = "32769-65537".parse().unwrap();
+        let huge: ProtoSet = "1-4294967294".parse().unwrap();
+
+        assert_eq!(Err(ProtoverError::ExceedsExpansionLimit), a.try_merge(&b));
+        assert_eq!(Err(ProtoverError::ExceedsExpansionLimit), b.try_merge(&a));
+        assert_eq!(
+            Err(ProtoverError::ExceedsExpansionLimit),
+            ProtoSet::default().try_merge(&huge)
+        );
+    }
+
+    #[test]
+    fn test_protoset_from_str_strict() {
+        for s in &["", "1", "1-3", "1-3,5,7-9"] {
+            assert_eq!(ProtoSet::from_str(s), ProtoSet::from_str_strict(s));
+            assert!(ProtoSet::from_str_strict(s).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_protoset_from_str_strict_rejects_padding() {
+        for s in &[" 1-3", "1-3 ", " 1-3 ", "\t1", "1,2\n", " "] {
+            assert_eq!(
+                Err(ProtoverError::Unparseable),
+                ProtoSet::from_str_strict(s)
+            );
+            // Plain from_str() doesn't trim either, so it also rejects these.
+            assert!(ProtoSet::from_str(s).is_err());
+        }
+    }
+
+    #[test]
+    fn test_protoset_from_expansion_matches_from_vec() {
+        let inputs: &[&[Version]] = &[&[], &[1], &[3, 1, 2], &[9, 9, 1, 5, 4, 6]];
+
+        for versions in inputs {
+            assert_eq!(
+                Ok(ProtoSet::from(versions.to_vec())),
+                ProtoSet::from_expansion(versions.iter().cloned())
+            );
+        }
+    }
+
+    #[test]
+    fn test_protoset_from_expansion_invalid() {
+        assert_eq!(
+            Err(ProtoverError::Overlap),
+            ProtoSet::from_expansion(vec![0, 1])
+        );
+        assert_eq!(
+            Err(ProtoverError::Overlap),
+            ProtoSet::from_expansion(vec![3, 0])
+        );
+        assert_eq!(
+            Err(ProtoverError::ExceedsMax),
+            ProtoSet::from_expansion(vec![1, u32::MAX])
+        );
+    }
+
+    #[test]
+    fn test_protoset_overlapping_range_inside_set() {
+        let ps: ProtoSet = "1-10,20".parse().unwrap();
+
+        assert_eq!("3-5", ps.overlapping_range(3, 5).to_string());
+        assert_eq!("1-10", ps.overlapping_range(1, 10).to_string());
+        assert_eq!("20", ps.overlapping_range(20, 20).to_string());
+        assert_eq!(ps, ps.overlapping_range(0, u32::MAX));
+    }
+
+    #[test]
+    fn test_protoset_overlapping_range_outside_set() {
+        let ps: ProtoSet = "5-10,20".parse().unwrap();
+
+        assert!(ps.overlapping_range(1, 4).is_empty());
+        assert!(ps.overlapping_range(11, 19).is_empty());
+        assert!(ps.overlapping_range(21, u32::MAX).is_empty());
+        assert!(ps.overlapping_range(8, 6).is_empty());
+        assert!(ProtoSet::default().overlapping_range(1, 5).is_empty());
+    }
+
+    #[test]
+    fn test_protoset_overlapping_range_straddles_pairs() {
+        let ps: ProtoSet = "1-3,5,8-20,30-40".parse().unwrap();
+        let window = ps.overlapping_range(2, 35);
+
+        assert_eq!("2-3,5,8-20,30-35", window.to_string());
+        assert_eq!(ps.count_versions_in_range(2, 35), window.len());
+        window.assert_count_is_consistent();
+
+        assert_eq!("5,8-9", ps.overlapping_range(4, 9).to_string());
+    }
+
+    #[test]
+    fn test_protoset_retain_range_set() {
+        let ps: ProtoSet = "1-3,5,8-20,30-40".parse().unwrap();
+        let mask: ProtoSet = "2-8,10,12-31,40-50".parse().unwrap();
+        let retained = ps.retain_range_set(&mask);
+
+        assert_protoset_eq!(
+            "2-3,5,8,10,12-20,30-31,40".parse::<ProtoSet>().unwrap(),
+            retained
+        );
+        assert_protoset_eq!(retained, mask.retain_range_set(&ps));
+        retained.assert_count_is_consistent();
+
+        assert_eq!(ps, ps.retain_range_set(&ps));
+        assert!(ps.retain_range_set(&ProtoSet::default()).is_empty());
+        assert!(ProtoSet::default().retain_range_set(&ps).is_empty());
+        assert!(ps
+            .retain_range_set(&"21-29,41-50".parse().unwrap())
+            .is_empty());
+    }
+
+    #[test]
+    fn test_protoset_retain_range_set_matches_and_not_in() {
+        let ps: ProtoSet = "1,3-6,10-12,15-16".parse().unwrap();
+        let mask: ProtoSet = "2,5-7,9-11,14-20".parse().unwrap();
+
+        assert_eq!(
+            ps.and_not_in(&ps.and_not_in(&mask)),
+            ps.retain_range_set(&mask)
+        );
+    }
+
+    #[test]
+    fn test_protoset_retain_range_set_assign() {
+        let mut ps: ProtoSet = "1-3,5,8-20".parse().unwrap();
+
+        ps.retain_range_set_assign(&"3-9".parse().unwrap());
+        assert_eq!("3,5,8-9", ps.to_string());
+        ps.assert_count_is_consistent();
+
+        ps.retain_range_set_assign(&ProtoSet::default());
+        assert!(ps.is_empty());
+    }
+
+    #[test]
+    fn test_protoset_verify_invariants_valid() {
+        for s in &["", "1", "1-3,4-6,9", "1-4294967294"] {
+            let ps: ProtoSet = s.parse().unwrap();
+
+            assert_eq!(Ok(()), ps.verify_invariants());
+            ps.check();
+        }
+    }
+
+    #[test]
+    fn test_protoset_verify_invariants_invalid() {
+        let unsorted = ProtoSet::from_sorted_unchecked(vec![(7, 9), (1, 3)]);
+        let overlapping = ProtoSet::from_sorted_unchecked(vec![(1, 5), (5, 9)]);
+        let backwards = ProtoSet::from_sorted_unchecked(vec![(1, 2), (9, 5)]);
+        let too_high = ProtoSet::from_sorted_unchecked(vec![(1, u32::MAX)]);
+        let zero = ProtoSet::from_sorted_unchecked(vec![(0, 3)]);
+
+        assert_eq!(Err(ProtoverError::Overlap), unsorted.verify_invariants());
+        assert_eq!(Err(ProtoverError::Overlap), overlapping.verify_invariants());
+        assert_eq!(
+            Err(ProtoverError::LowGreaterThanHigh),
+            backwards.verify_invariants()
+        );
+        assert_eq!(Err(ProtoverError::ExceedsMax), too_high.verify_invariants());
+        assert_eq!(Err(ProtoverError::Overlap), zero.verify_invariants());
+    }
+
+    #[test]
+    fn test_protoset_verify_invariants_matches_is_ok() {
+        let pairs = vec![(1, 5), (3, 2)];
+
+        assert_eq!(
+            Err(ProtoverError::Overlap),
+            ProtoSet::from_sorted_unchecked(pairs.clone()).verify_invariants()
+        );
+        assert_eq!(
+            Err(ProtoverError::Overlap),
+            ProtoSet::from_sorted_unchecked(pairs).is_ok().map(|_| ())
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid ProtoSet")]
+    fn test_protoset_check_panics_on_overlap() {
+        ProtoSet::from_sorted_unchecked(vec![(1, 5), (3, 9)]).check();
+    }
+
+    #[test]
+    fn test_protoset_mutations_keep_invariants() {
+        let mut ps: ProtoSet = "1-3,8-20".parse().unwrap();
+
+        ps.insert(5).unwrap();
+        ps.check();
+        ps.extend_range(4, 7).unwrap();
+        ps.check();
+        assert!(ps.remove(10));
+        ps.check();
+        ps.retain_range_set_assign(&"2-15".parse().unwrap());
+        ps.check();
+        assert_eq!("2-9,11-15", ps.to_string());
+        assert_eq!(13, ps.drain().count());
+        ps.check();
+    }
+
+    #[test]
+    fn test_protoset_expand_reverse_matches_expand_rev() {
+        for s in &[
+            "",
+            "1",
+            "1-3",
+            "1,3,5-7",
+            "2-4,10,20-25,4294967290-4294967294",
+        ] {
+            let ps: ProtoSet = s.parse().unwrap();
+            let reversed: Vec<Version> = ps.expand_reverse().collect();
+
+            assert_eq!(
+                ps.clone().expand().into_iter().rev().collect::<Vec<_>>(),
+                reversed
+            );
+            assert_eq!(ps.len(), reversed.len());
+            // Reversing it again gives ascending order.
+            assert_eq!(ps.to_vec(), ps.expand_reverse().rev().collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn test_protoset_expand_reverse_is_lazy() {
+        let ps: ProtoSet = "1-4294967294".parse().unwrap();
+        let highest: Vec<Version> = ps.expand_reverse().take(3).collect();
+
+        assert_eq!(vec![4294967294, 4294967293, 4294967292], highest);
+    }
+
+    #[test]
+    fn test_protoset_expand_in_range_matches_filtered_expand() {
+        let sets = &["", "1", "1-3", "1,3,5-7", "2-4,10,20-25,30-40"];
+        let windows = &[
+            (0, 0),
+            (1, 1),
+            (2, 6),
+            (4, 22),
+            (11, 19),
+            (0, 100),
+            (24, 35),
+            (9, 2),
+        ];
+
+        for s in sets {
+            let ps: ProtoSet = s.parse().unwrap();
+
+            for &(low, high) in windows {
+                let expected: Vec<Version> = ps
+                    .clone()
+                    .expand()
+                    .into_iter()
+                    .filter(|&v| v >= low && v <= high)
+                    .collect();
+
+                assert_eq!(expected, ps.expand_in_range(low, high).collect::<Vec<_>>());
+                assert_eq!(expected.len(), ps.count_versions_in_range(low, high));
             }
         }
-        final_output.join(",")
     }
-}
 
-/// Checks to see if there is a continuous range of integers, starting at the
-/// first in the list. Returns the last integer in the range if a range exists.
-///
-/// # Inputs
-///
-/// `list`, an ordered  vector of `u32` integers of "[0-9,-]" representing the
-/// supported versions for a single protocol.
-///
-/// # Returns
-///
-/// A `bool` indicating whether the list contains a range, starting at the first
-/// in the list, a`Version` of the last integer in the range, and a `usize` of
-/// the index of that version.
-///
-/// For example, if given vec![1, 2, 3, 5], find_range will return true,
-/// as there is a continuous range, and 3, which is the last number in the
-/// continuous range, and 2 which is the index of 3.
-fn find_range(list: &Vec<Version>) -> (bool, Version, usize) {
-    if list.len() == 0 {
-        return (false, 0, 0);
+    #[test]
+    fn test_protoset_expand_in_range_huge_set() {
+        let ps: ProtoSet = "1-4294967294".parse().unwrap();
+        let window: Vec<Version> = ps.expand_in_range(4294967290, u32::MAX).collect();
+
+        assert_eq!(
+            vec![4294967290, 4294967291, 4294967292, 4294967293, 4294967294],
+            window
+        );
+        assert_eq!(0, ps.expand_in_range(u32::MAX, u32::MAX).count());
     }
 
-    let mut index: usize = 0;
-    let mut iterable = list.iter().peekable();
-    let mut range_end = match iterable.next() {
-        Some(n) => *n,
-        None => return (false, 0, 0),
-    };
+    #[test]
+    fn test_protoset_ranges_round_trip() {
+        for s in &[
+            "",
+            "1",
+            "1-3",
+            "1,3,5-7",
+            "2-4,10,20-25,4294967290-4294967294",
+        ] {
+            let ps: ProtoSet = s.parse().unwrap();
+            let ranges: Vec<RangeInclusive<Version>> = ps.to_ranges();
 
-    let mut has_range = false;
+            assert_eq!(ps.count_pairs(), ranges.len());
+            assert_eq!(Ok(ps), ProtoSet::try_from(ranges));
+        }
+    }
+
+    #[test]
+    fn test_protoset_from_ranges_merges_overlaps() {
+        let ps = ProtoSet::try_from(vec![8..=12, 1..=3, 2..=5, 10..=20, 6..=6, 30..=30]).unwrap();
+
+        assert_eq!("1-6,8-20,30", ps.to_string());
+        assert_eq!(vec![1..=6, 8..=20, 30..=30], ps.to_ranges());
+        ps.check();
+    }
+
+    #[test]
+    fn test_protoset_from_ranges_invalid() {
+        // Empty ranges are skipped.
+        assert_eq!(
+            "1-3",
+            ProtoSet::try_from(vec![5..=4, 1..=3]).unwrap().to_string()
+        );
+        assert_eq!(
+            Ok(ProtoSet::default()),
+            ProtoSet::try_from(Vec::<RangeInclusive<Version>>::new())
+        );
+
+        // Version 0 and u32::MAX are invalid, like in ProtoSet::from_slice().
+        assert_eq!(Err(ProtoverError::Overlap), ProtoSet::try_from(vec![0..=3]));
+        assert_eq!(
+            Err(ProtoverError::Overlap),
+            ProtoSet::try_from(vec![5..=9, 0..=0])
+        );
+        assert_eq!(
+            Err(ProtoverError::ExceedsMax),
+            ProtoSet::try_from(vec![1..=3, 7..=u32::MAX])
+        );
+    }
 
-    while iterable.peek().is_some() {
-        let n = *iterable.next().unwrap();
-        if n != range_end + 1 {
-            break;
-        }
+    #[test]
+    fn test_assert_protoset_eq_ignores_range_splits() {
+        let split: ProtoSet = "1-3,4-6,9".parse().unwrap();
+        let joined: ProtoSet = "1-6,9".parse().unwrap();
 
-        has_range = true;
-        range_end = n;
-        index += 1;
+        assert_ne!(split, joined);
+        assert_protoset_eq!(split, joined);
+        assert_protoset_eq!(&split, &joined);
+        assert_protoset_eq!(ProtoSet::default(), "".parse::<ProtoSet>().unwrap());
     }
 
-    (has_range, range_end, index)
-}
+    #[test]
+    #[should_panic(expected = "ProtoSets differ")]
+    fn test_assert_protoset_eq_fails_on_different_versions() {
+        assert_protoset_eq!(
+            "1-6".parse::<ProtoSet>().unwrap(),
+            "1-5".parse::<ProtoSet>().unwrap()
+        );
+    }
 
-impl From<Vec<Version>> for ProtoSet {
-    fn from(mut v: Vec<Version>) -> ProtoSet {
-        let mut version_pairs: Vec<(Version, Version)> = Vec::new();
+    #[test]
+    fn test_assert_protoset_contains_exactly() {
+        let ps: ProtoSet = "1-3,4,9".parse().unwrap();
 
-        v.sort_unstable();
-        v.dedup();
+        assert_protoset_contains_exactly!(ps, [1, 2, 3, 4, 9]);
+        assert_protoset_contains_exactly!(ProtoSet::default(), []);
+        assert_protoset_contains_exactly!(ps.overlapping_range(3, 8), [3, 4,]);
+    }
 
-        'vector: while !v.is_empty() {
-            let (has_range, end, index): (bool, Version, usize) = find_range(&v);
+    #[test]
+    fn test_protoset_covers_range() {
+        let protoset: ProtoSet = ProtoSet::from_slice(&[(1, 3), (4, 5), (8, 20)]).unwrap();
 
-            if has_range {
-                let first: Version = match v.first() {
-                    Some(x) => *x,
-                    None => continue,
-                };
-                let last: Version = match v.get(index) {
-                    Some(x) => *x,
-                    None => continue,
-                };
-                debug_assert!(last == end, format!("last = {}, end = {}", last, end));
+        // Exactly a stored pair, or inside one
+        assert!(protoset.covers_range(8, 20));
+        assert!(protoset.covers_range(10, 10));
+        // Spanning adjacent stored pairs
+        assert!(protoset.covers_range(1, 5));
+        assert!(protoset.covers_range(3, 4));
+        // Partially covered
+        assert!(!protoset.covers_range(5, 8));
+        assert!(!protoset.covers_range(15, 21));
+        assert!(!protoset.covers_range(0, 3));
+        // Not covered at all
+        assert!(!protoset.covers_range(6, 7));
+        assert!(!protoset.covers_range(21, u32::MAX));
+        // Empty ranges are always covered
+        assert!(protoset.covers_range(7, 6));
+        assert!(ProtoSet::default().covers_range(2, 1));
+        assert!(!ProtoSet::default().covers_range(1, 1));
+    }
 
-                version_pairs.push((first, last));
-                v = v.split_off(index + 1);
+    #[test]
+    fn test_protoset_covers_range_matches_contains() {
+        let protoset: ProtoSet = "1-3,4-5,8-20,22".parse().unwrap();
 
-                if v.len() == 0 {
-                    break 'vector;
-                }
-            } else {
-                let last: Version = match v.get(index) {
-                    Some(x) => *x,
-                    None => continue,
-                };
-                version_pairs.push((last, last));
-                v.remove(index);
+        for lo in 0..25 {
+            for hi in lo..25 {
+                let expected = (lo..=hi).all(|v| protoset.contains(&v));
+                assert_eq!(expected, protoset.covers_range(lo, hi), "{}-{}", lo, hi);
             }
         }
-        ProtoSet::from_slice(&version_pairs[..]).unwrap_or(ProtoSet::default())
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::*;
+    #[test]
+    fn test_protoset_from_unsorted() {
+        let ps = ProtoSet::from_unsorted(vec![(7, 9), (1, 3), (2, 5), (10, 10), (20, 21)]);
+
+        ps.check();
+        assert_eq!(ps.pairs, vec![(1, 5), (7, 10), (20, 21)]);
+        assert_eq!(11, ps.len());
+
+        ProtoSet::from_unsorted(Vec::new()).check();
+    }
 
     #[test]
-    fn test_find_range() {
-        assert_eq!((false, 0, 0), find_range(&vec![]));
-        assert_eq!((false, 1, 0), find_range(&vec![1]));
-        assert_eq!((true, 2, 1), find_range(&vec![1, 2]));
-        assert_eq!((true, 3, 2), find_range(&vec![1, 2, 3]));
-        assert_eq!((true, 3, 2), find_range(&vec![1, 2, 3, 5]));
+    fn test_protoset_union_is_valid() {
+        let a: ProtoSet = "1-3,10,20-30".parse().unwrap();
+        let b: ProtoSet = "2-5,8,25-40".parse().unwrap();
+        let union = a.union(&b);
+
+        union.check();
+        assert_eq!("1-5,8,10,20-40", union.to_string());
     }
 
-    macro_rules! assert_contains_each {
-        ($protoset:expr, $versions:expr) => {
-            for version in $versions {
-                assert!($protoset.contains(version));
-            }
-        };
+    #[test]
+    fn test_protoset_from_btreeset() {
+        let versions: BTreeSet<Version> = [9, 2, 3, 4, 7, 8, 12].iter().cloned().collect();
+        let ps: ProtoSet = versions.into();
+
+        assert_eq!(vec![(2, 4), (7, 9), (12, 12)], ps.pairs);
+        assert_eq!(ProtoSet::default(), ProtoSet::from(BTreeSet::new()));
     }
 
-    macro_rules! test_protoset_contains_versions {
-        ($list:expr, $str:expr) => {
-            let versions: &[Version] = $list;
-            let protoset: Result<ProtoSet, ProtoverError> = ProtoSet::from_str($str);
+    #[test]
+    fn test_protoset_from_btreeset_invalid() {
+        let versions: BTreeSet<Version> = [1, u32::MAX].iter().cloned().collect();
 
-            assert!(protoset.is_ok());
-            let p = protoset.unwrap();
-            assert_contains_each!(p, versions);
-        };
+        assert_eq!(ProtoSet::default(), ProtoSet::from(versions));
     }
 
     #[test]
-    fn test_versions_from_str() {
-        test_protoset_contains_versions!(&[], "");
-        test_protoset_contains_versions!(&[1], "1");
-        test_protoset_contains_versions!(&[1, 2], "1,2");
-        test_protoset_contains_versions!(&[1, 2, 3], "1-3");
-        test_protoset_contains_versions!(&[1, 2, 5], "1-2,5");
-        test_protoset_contains_versions!(&[1, 3, 4, 5], "1,3-5");
-        test_protoset_contains_versions!(&[42, 55, 56, 57, 58], "42,55-58");
+    fn test_protoset_btreeset_round_trip() {
+        let versions: BTreeSet<Version> =
+            [1, 2, 3, 5, 10, 11, 4294967294].iter().cloned().collect();
+        let ps: ProtoSet = versions.clone().into();
+
+        assert_eq!("1-3,5,10-11,4294967294", ps.to_string());
+        assert_eq!(versions, BTreeSet::from(ps));
     }
 
     #[test]
-    fn test_versions_from_str_ab() {
-        assert_eq!(Err(ProtoverError::Unparseable), ProtoSet::from_str("a,b"));
+    fn test_protoset_from_pairs() {
+        let ps = ProtoSet::from_pairs(
+            vec![(8, 9), (1, 2), (8, 9)]
+                .into_iter()
+                .map(|(l, h)| (l, h + 1)),
+        );
+
+        assert_eq!(Ok(vec![(1, 3), (8, 10)]), ps.map(|ps| ps.pairs));
+        assert_eq!(
+            Err(ProtoverError::Overlap),
+            ProtoSet::from_pairs(vec![(1, 5), (3, 7)])
+        );
+        assert_eq!(
+            Err(ProtoverError::LowGreaterThanHigh),
+            ProtoSet::from_pairs(vec![(3, 2)])
+        );
+        assert_eq!(Ok(ProtoSet::default()), ProtoSet::from_pairs(Vec::new()));
+    }
+
+    macro_rules! assert_subset {
+        ($a:expr, $b:expr, $expected:expr) => {{
+            let a: ProtoSet = $a.parse().unwrap();
+            let b: ProtoSet = $b.parse().unwrap();
+            assert_eq!($expected, a.is_subset_of(&b), "{} <= {}", $a, $b);
+            assert_eq!($expected, b.is_superset_of(&a), "{} >= {}", $b, $a);
+        }};
     }
 
     #[test]
-    fn test_versions_from_str_negative_1() {
-        assert_eq!(Err(ProtoverError::Unparseable), ProtoSet::from_str("-1"));
+    fn test_protoset_is_subset_of_equal_sets() {
+        assert_subset!("", "", true);
+        assert_subset!("1-3,5", "1-3,5", true);
+        assert_subset!("1-6", "1-3,4-6", true);
+        assert_subset!("1-3,4-6", "1-6", true);
+        assert_subset!("4294967294", "4294967294", true);
     }
 
     #[test]
-    fn test_versions_from_str_commas() {
-        assert_eq!(Err(ProtoverError::Unparseable), ProtoSet::from_str(","));
-        assert_eq!(Err(ProtoverError::Unparseable), ProtoSet::from_str("1,,2"));
-        assert_eq!(Err(ProtoverError::Unparseable), ProtoSet::from_str("1,2,"));
+    fn test_protoset_is_subset_of_proper_subsets() {
+        assert_subset!("", "1", true);
+        assert_subset!("2-3,7", "1-4,6-9", true);
+        assert_subset!("2-3,4", "1-10", true);
+        assert_subset!("3-8", "1-4,5,6-9", true);
+        assert_subset!("1-4,6-9", "2-3,7", false);
+        assert_subset!("1-10", "1-4,6-10", false);
+        assert_subset!("1", "", false);
     }
 
     #[test]
-    fn test_versions_from_str_hyphens() {
-        assert_eq!(Err(ProtoverError::Unparseable), ProtoSet::from_str("--1"));
-        assert_eq!(Err(ProtoverError::Unparseable), ProtoSet::from_str("-1-2"));
-        assert_eq!(Err(ProtoverError::Unparseable), ProtoSet::from_str("1--2"));
+    fn test_protoset_is_subset_of_disjoint_sets() {
+        assert_subset!("1-3", "4-6", false);
+        assert_subset!("4-6", "1-3", false);
+        assert_subset!("1,3,5", "2,4,6", false);
+        assert_subset!("2-5", "1-3,5-7", false);
     }
 
     #[test]
-    fn test_versions_from_str_triple() {
-        assert_eq!(Err(ProtoverError::Unparseable), ProtoSet::from_str("1-2-3"));
+    fn test_protoset_drain() {
+        let mut ps: ProtoSet = "1-3,5,4294967294".parse().unwrap();
+
+        assert_eq!(
+            vec![1, 2, 3, 5, 4294967294],
+            ps.drain().collect::<Vec<Version>>()
+        );
+        assert_eq!(ProtoSet::default(), ps);
+        assert_eq!(None, ps.drain().next());
     }
 
     #[test]
-    fn test_versions_from_str_1exclam() {
-        assert_eq!(Err(ProtoverError::Unparseable), ProtoSet::from_str("1,!"));
+    fn test_protoset_drain_partially_consumed() {
+        let mut ps: ProtoSet = "1-10".parse().unwrap();
+
+        assert_eq!(Some(1), ps.drain().next());
+        assert!(ps.is_empty());
+
+        ps.insert(7).unwrap();
+        assert_eq!("7", ps.to_string());
     }
 
     #[test]
-    fn test_versions_from_str_percent_equal() {
-        assert_eq!(Err(ProtoverError::Unparseable), ProtoSet::from_str("%="));
+    fn test_protoset_encode_decode_empty() {
+        let ps = ProtoSet::default();
+
+        assert_eq!(vec![0, 0, 0, 0], ps.encode());
+        assert_eq!(Ok(ps.clone()), ProtoSet::decode(&ps.encode()));
     }
 
     #[test]
-    fn test_versions_from_str_whitespace() {
-        assert_eq!(Err(ProtoverError::Unparseable), ProtoSet::from_str("1,2\n"));
-        assert_eq!(Err(ProtoverError::Unparseable), ProtoSet::from_str("1\r,2"));
-        assert_eq!(Err(ProtoverError::Unparseable), ProtoSet::from_str("1,\t2"));
+    fn test_protoset_encode_decode_single_pair() {
+        let ps: ProtoSet = "258-4294967294".parse().unwrap();
+        let encoded = ps.encode();
+
+        assert_eq!(vec![0, 0, 0, 1, 0, 0, 1, 2, 255, 255, 255, 254], encoded);
+        assert_eq!(Ok(ps), ProtoSet::decode(&encoded));
     }
 
     #[test]
-    fn test_versions_from_str_overlap() {
-        assert_eq!(Err(ProtoverError::Overlap), ProtoSet::from_str("1-3,2-4"));
+    fn test_protoset_encode_decode_many_pairs() {
+        let pairs: Vec<(Version, Version)> = (1..150).map(|n| (n * 10, n * 10 + n % 4)).collect();
+        let ps = ProtoSet::from_slice(&pairs).unwrap();
+        let encoded = ps.encode();
+
+        assert_eq!(4 + 149 * 8, encoded.len());
+        assert_eq!(Ok(ps), ProtoSet::decode(&encoded));
     }
 
     #[test]
-    fn test_versions_from_slice_overlap() {
+    fn test_protoset_decode_malformed() {
+        let unparseable = Err(ProtoverError::Unparseable);
+
+        assert_eq!(unparseable, ProtoSet::decode(&[]));
+        assert_eq!(unparseable, ProtoSet::decode(&[0, 0, 0]));
+        assert_eq!(unparseable, ProtoSet::decode(&[0, 0, 0, 1, 0, 0, 0, 1]));
+        assert_eq!(unparseable, ProtoSet::decode(&[0, 0, 0, 0, 0]));
+        assert_eq!(unparseable, ProtoSet::decode(&[255, 255, 255, 255]));
+
+        let unsorted = [0, 0, 0, 2, 0, 0, 0, 5, 0, 0, 0, 5, 0, 0, 0, 1, 0, 0, 0, 1];
+        assert_eq!(Err(ProtoverError::Overlap), ProtoSet::decode(&unsorted));
+
+        let backwards = [0, 0, 0, 1, 0, 0, 0, 5, 0, 0, 0, 1];
         assert_eq!(
-            Err(ProtoverError::Overlap),
-            ProtoSet::from_slice(&[(1, 3), (2, 4)])
+            Err(ProtoverError::LowGreaterThanHigh),
+            ProtoSet::decode(&backwards)
         );
+
+        let too_high = [0, 0, 0, 1, 0, 0, 0, 5, 255, 255, 255, 255];
+        assert_eq!(Err(ProtoverError::ExceedsMax), ProtoSet::decode(&too_high));
     }
 
     #[test]
-    fn test_versions_from_str_max() {
+    fn test_protoset_from_sorted() {
+        let sorted = vec![(1, 3), (5, 5), (7, 9)];
+
         assert_eq!(
-            Err(ProtoverError::ExceedsMax),
-            ProtoSet::from_str("4294967295")
+            Ok(sorted.clone()),
+            ProtoSet::from_sorted(sorted.clone()).map(|ps| ps.pairs)
+        );
+        assert_eq!(
+            sorted.clone(),
+            ProtoSet::from_sorted_unchecked(sorted).pairs
         );
+        assert_eq!(Ok(ProtoSet::default()), ProtoSet::from_sorted(Vec::new()));
     }
 
     #[test]
-    fn test_versions_from_slice_max() {
+    fn test_protoset_from_sorted_rejects_what_unchecked_accepts() {
+        let unsorted = vec![(7, 9), (1, 3)];
+        let overlapping = vec![(1, 5), (3, 7)];
+        let backwards = vec![(3, 1)];
+
         assert_eq!(
-            Err(ProtoverError::ExceedsMax),
-            ProtoSet::from_slice(&[(4294967295, 4294967295)])
+            Err(ProtoverError::Overlap),
+            ProtoSet::from_sorted(unsorted.clone())
+        );
+        assert_eq!(
+            Err(ProtoverError::Overlap),
+            ProtoSet::from_sorted(overlapping.clone())
+        );
+        assert_eq!(
+            Err(ProtoverError::LowGreaterThanHigh),
+            ProtoSet::from_sorted(backwards.clone())
+        );
+
+        assert_eq!(
+            unsorted.clone(),
+            ProtoSet::from_sorted_unchecked(unsorted).pairs
+        );
+        assert_eq!(
+            overlapping.clone(),
+            ProtoSet::from_sorted_unchecked(overlapping).pairs
+        );
+        assert_eq!(
+            backwards.clone(),
+            ProtoSet::from_sorted_unchecked(backwards).pairs
         );
     }
 
     #[test]
-    fn test_protoset_contains() {
-        let protoset: ProtoSet = ProtoSet::from_slice(&[(1, 5), (7, 9), (13, 14)]).unwrap();
+    fn test_protoset_shift() {
+        let ps: ProtoSet = "2-4,7,10-12".parse().unwrap();
 
-        for x in 1..6 {
-            assert!(protoset.contains(&x), format!("should contain {}", x));
-        }
-        for x in 7..10 {
-            assert!(protoset.contains(&x), format!("should contain {}", x));
-        }
-        for x in 13..15 {
-            assert!(protoset.contains(&x), format!("should contain {}", x));
-        }
+        assert_eq!("102-104,107,110-112", ps.shift(100).unwrap().to_string());
+        assert_eq!("1-3,6,9-11", ps.shift(-1).unwrap().to_string());
+        assert_eq!(ps, ps.shift(0).unwrap());
+        assert_eq!(ps, ps.shift(1000).unwrap().shift(-1000).unwrap());
+    }
 
-        for x in [6, 10, 11, 12, 15, 42, 43, 44, 45, 1234584].iter() {
-            assert!(!protoset.contains(&x), format!("should not contain {}", x));
-        }
+    #[test]
+    fn test_protoset_shift_out_of_range() {
+        let ps: ProtoSet = "2-4,4294967290".parse().unwrap();
+
+        assert_eq!(Err(ProtoverError::ExceedsMax), ps.shift(5));
+        assert_eq!(Err(ProtoverError::ExceedsMax), ps.shift(-3));
+        assert_eq!(Err(ProtoverError::ExceedsMax), ps.shift(i64::MAX));
+        assert_eq!(Err(ProtoverError::ExceedsMax), ps.shift(i64::MIN));
+        assert_eq!("6-8,4294967294", ps.shift(4).unwrap().to_string());
     }
 
     #[test]
-    fn test_protoset_contains_1_3() {
-        let protoset: ProtoSet = ProtoSet::from_slice(&[(1, 3)]).unwrap();
+    fn test_protoset_shift_empty() {
+        let ps = ProtoSet::default();
 
-        for x in 1..4 {
-            assert!(protoset.contains(&x), format!("should contain {}", x));
+        assert_eq!(Ok(ProtoSet::default()), ps.shift(7));
+        assert_eq!(Ok(ProtoSet::default()), ps.shift(-7));
+    }
+
+    #[test]
+    fn test_protoset_shift_to_zero() {
+        let ps: ProtoSet = "1-3".parse().unwrap();
+
+        assert_eq!(Err(ProtoverError::ExceedsMax), ps.shift(-1));
+        assert_eq!("2-4", ps.shift(1).unwrap().to_string());
+    }
+
+    #[test]
+    fn test_protoset_len_after_mutations() {
+        let mut ps: ProtoSet = "1-3,5,8-10".parse().unwrap();
+
+        assert_eq!(7, ps.len());
+        assert_eq!(ps.len(), ps.len());
+
+        ps.insert(4).unwrap();
+        ps.insert(4).unwrap();
+        assert_eq!(8, ps.len());
+
+        ps.remove(9);
+        ps.remove(9);
+        assert_eq!(7, ps.len());
+
+        ps.normalize();
+        assert_eq!(7, ps.len());
+
+        let union = ps.union(&"2-6,20".parse().unwrap());
+        assert_eq!(9, union.len());
+        assert_eq!(1, ProtoSet::default().union(&"7".parse().unwrap()).len());
+
+        let not_in = union.and_not_in(&"1,5-8".parse().unwrap());
+        assert_eq!(5, not_in.len());
+        assert_eq!(not_in.len(), not_in.to_vec().len());
+
+        let mut shifted = not_in.shift(100).unwrap();
+        assert_eq!(5, shifted.len());
+
+        assert_eq!(5, shifted.drain().count());
+        assert_eq!(0, shifted.len());
+    }
+
+    #[test]
+    fn test_protoset_first_missing_after_inside_range() {
+        let ps: ProtoSet = "1-3,5-7,8-9".parse().unwrap();
+
+        assert_eq!(Some(4), ps.first_missing_after(1));
+        assert_eq!(Some(4), ps.first_missing_after(2));
+        assert_eq!(Some(10), ps.first_missing_after(6));
+    }
+
+    #[test]
+    fn test_protoset_first_missing_after_in_gap() {
+        let ps: ProtoSet = "1-3,5-7,10".parse().unwrap();
+
+        assert_eq!(Some(8), ps.first_missing_after(4));
+        assert_eq!(Some(9), ps.first_missing_after(8));
+        assert_eq!(Some(1), ProtoSet::default().first_missing_after(0));
+    }
+
+    #[test]
+    fn test_protoset_first_missing_after_range_boundary() {
+        let ps: ProtoSet = "1-3,5-7".parse().unwrap();
+
+        assert_eq!(Some(4), ps.first_missing_after(3));
+        assert_eq!(Some(8), ps.first_missing_after(7));
+        assert_eq!(Some(8), ps.first_missing_after(4));
+    }
+
+    #[test]
+    fn test_protoset_first_missing_after_above_all_ranges() {
+        let ps: ProtoSet = "1-3,5-7".parse().unwrap();
+
+        assert_eq!(Some(21), ps.first_missing_after(20));
+        assert_eq!(None, ps.first_missing_after(u32::MAX - 1));
+        assert_eq!(None, ps.first_missing_after(u32::MAX));
+
+        let top: ProtoSet = "4294967290-4294967294".parse().unwrap();
+        assert_eq!(None, top.first_missing_after(4294967289));
+        assert_eq!(Some(4294967289), top.first_missing_after(4294967288));
+    }
+
+    #[test]
+    fn test_protoset_adjacent_touching_exactly() {
+        let a: ProtoSet = "1-3,10".parse().unwrap();
+
+        assert!(a.adjacent(&"4-6".parse().unwrap()));
+        assert!(a.adjacent(&"7-9".parse().unwrap()));
+        assert!(a.adjacent(&"11,20".parse().unwrap()));
+        assert!("4-6".parse::<ProtoSet>().unwrap().adjacent(&a));
+
+        let merged = ProtoSet::merge_if_adjacent(a, "4-6".parse().unwrap());
+        assert_eq!(Ok("1-6,10".to_string()), merged.map(|ps| ps.to_string()));
+    }
+
+    #[test]
+    fn test_protoset_adjacent_gap_of_one() {
+        let a: ProtoSet = "1-3,10".parse().unwrap();
+        let b: ProtoSet = "5-6,8".parse().unwrap();
+
+        assert!(!a.adjacent(&b));
+        assert!(!b.adjacent(&a));
+        assert!(!a.adjacent(&ProtoSet::default()));
+
+        let merged = ProtoSet::merge_if_adjacent(a.clone(), b.clone());
+        assert_eq!(Err((a, b)), merged);
+    }
+
+    #[test]
+    fn test_protoset_adjacent_overlapping() {
+        let a: ProtoSet = "1-3,10".parse().unwrap();
+        let b: ProtoSet = "4-5,9-12".parse().unwrap();
+
+        assert!(!a.adjacent(&b));
+        assert!(!b.adjacent(&a));
+        assert!(!a.adjacent(&a));
+
+        let merged = ProtoSet::merge_if_adjacent(a, b);
+        assert_eq!(Ok("1-5,9-12".to_string()), merged.map(|ps| ps.to_string()));
+    }
+
+    #[test]
+    fn test_protoset_pack_unpack_round_trip() {
+        let mut rng = TestRng(0x2545_f491_4f6c_dd1d);
+
+        for _ in 0..1000 {
+            // Version 0 is never in a valid ProtoSet.
+            let bits: u64 = rng.next() & !1;
+            let ps = ProtoSet::unpack(bits);
+
+            assert_eq!(ps, ps.to_string().parse().unwrap());
+            assert_eq!(bits.count_ones() as usize, ps.len());
+            assert_eq!(Some(bits), ps.pack());
         }
     }
 
-    macro_rules! assert_protoset_from_vec_contains_all {
-        ($($x:expr),*) => (
-            let vec: Vec<Version> = vec!($($x),*);
-            let protoset: ProtoSet = vec.clone().into();
+    #[test]
+    fn test_protoset_pack_unpack_edge_cases() {
+        assert_eq!(Some(0), ProtoSet::default().pack());
+        assert_eq!(ProtoSet::default(), ProtoSet::unpack(0));
+        assert_eq!(ProtoSet::default(), ProtoSet::unpack(1));
 
-            for x in vec.iter() {
-                assert!(protoset.contains(&x));
-            }
-        )
+        let all: ProtoSet = "1-63".parse().unwrap();
+        assert_eq!(Some(!1), all.pack());
+        assert_eq!(all, ProtoSet::unpack(u64::MAX));
     }
 
     #[test]
-    fn test_protoset_from_vec_123() {
-        assert_protoset_from_vec_contains_all!(1, 2, 3);
+    fn test_protoset_pack_large_versions() {
+        assert_eq!(None, "64".parse::<ProtoSet>().unwrap().pack());
+        assert_eq!(None, "1-3,63-64".parse::<ProtoSet>().unwrap().pack());
+        assert_eq!(None, "4294967294".parse::<ProtoSet>().unwrap().pack());
+        assert_eq!(Some(1 << 63), "63".parse::<ProtoSet>().unwrap().pack());
     }
 
     #[test]
-    fn test_protoset_from_vec_1_315() {
-        assert_protoset_from_vec_contains_all!(1, 2, 3, 15);
+    fn test_protoset_insert() {
+        let mut ps: ProtoSet = "2-3,6,9-10".parse().unwrap();
+
+        assert_eq!(Ok(()), ps.insert(1));
+        assert_eq!(Ok(()), ps.insert(5));
+        assert_eq!(Ok(()), ps.insert(7));
+        assert_eq!(Ok(()), ps.insert(8));
+        assert_eq!(Ok(()), ps.insert(8));
+        assert_eq!(Ok(()), ps.insert(12));
+        assert_eq!(vec![(1, 3), (5, 10), (12, 12)], ps.pairs);
+
+        assert_eq!(Err(ProtoverError::ExceedsMax), ps.insert(u32::MAX));
+        assert_eq!(Err(ProtoverError::Overlap), ps.insert(0));
+        assert_eq!(vec![(1, 3), (5, 10), (12, 12)], ps.pairs);
     }
 
     #[test]
-    fn test_protoset_from_vec_unordered() {
-        let v: Vec<Version> = vec![2, 3, 8, 4, 3, 9, 7, 2];
-        let ps: ProtoSet = v.into();
+    fn test_protoset_remove() {
+        let mut ps: ProtoSet = "1-3,5-10,12".parse().unwrap();
 
-        assert_eq!(ps.to_string(), "2-4,7-9");
+        assert!(ps.remove(12));
+        assert!(ps.remove(1));
+        assert!(ps.remove(10));
+        assert!(ps.remove(7));
+        assert!(!ps.remove(7));
+        assert!(!ps.remove(4));
+        assert_eq!(vec![(2, 3), (5, 6), (8, 9)], ps.pairs);
     }
 
     #[test]
@@ -681,6 +4274,30 @@ mod test {
         assert!(v.contains(&9001));
         assert!(v.contains(&4294967294));
     }
+
+    #[test]
+    fn test_protoset_vec_round_trip() {
+        let ps: ProtoSet = "1-3,4,9,11-13,4294967294".parse().unwrap();
+
+        let first: Vec<Version> = Vec::from(&ps);
+        let second: Vec<Version> = ProtoSet::from(first.clone()).into();
+
+        assert_eq!(first, second);
+        assert_eq!(first, Vec::<Version>::from(ps.clone()));
+
+        let pairs: Vec<(Version, Version)> = ps.into();
+        assert_eq!(
+            vec![(1, 3), (4, 4), (9, 9), (11, 13), (4294967294, 4294967294)],
+            pairs
+        );
+        assert_eq!(
+            Ok(vec![(1, 4), (9, 9), (11, 13), (4294967294, 4294967294)]),
+            ProtoSet::from_slice(&pairs).map(|mut ps| {
+                ps.normalize();
+                ps.pairs
+            })
+        );
+    }
 }
 
 #[cfg(all(test, feature = "bench"))]
@@ -1,19 +1,24 @@
 // Copyright (c) 2016-2019, The Tor Project, Inc. */
 // See LICENSE for licensing information */
-
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::btree_map;
 use std::collections::hash_map;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::ffi::CStr;
 use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Sub;
 use std::str;
 use std::str::FromStr;
 use std::string::String;
 
-use external::c_tor_version_as_new_as;
-
 use errors::ProtoverError;
 use protoset::ProtoSet;
 use protoset::Version;
+use MAX_PROTOCOLS_TO_EXPAND;
 
 /// The first version of Tor that included "proto" entries in its descriptors.
 /// Authorities should use this to decide whether to guess proto lines.
@@ -22,11 +27,11 @@ use protoset::Version;
 ///     protover.h `FIRST_TOR_VERSION_TO_ADVERTISE_PROTOCOLS`
 const FIRST_TOR_VERSION_TO_ADVERTISE_PROTOCOLS: &'static str = "0.2.9.3-alpha";
 
-/// The maximum number of subprotocol version numbers we will attempt to expand
-/// before concluding that someone is trying to DoS us
+/// The oldest version of Tor whose subprotocols we guess in
+/// `compute_for_old_tor()`. Older versions are assumed to support nothing.
 ///
-/// C_RUST_COUPLED: protover.c `MAX_PROTOCOLS_TO_EXPAND`
-const MAX_PROTOCOLS_TO_EXPAND: usize = 1 << 16;
+/// C_RUST_COUPLED: src/core/or/protover.c `compute_for_old_tor`
+const OLDEST_TOR_VERSION_TO_GUESS_PROTOCOLS: &str = "0.2.4.19";
 
 /// The maximum size an `UnknownProtocol`'s name may be.
 pub(crate) const MAX_PROTOCOL_NAME_LENGTH: usize = 100;
@@ -50,12 +55,122 @@ pub enum Protocol {
     FlowCtrl,
 }
 
+impl Protocol {
+    /// Get every known `Protocol`, in the same order as C's `protocol_type_t`.
+    ///
+    /// C_RUST_COUPLED: protover.h `protocol_type_t`
+    pub fn variants() -> &'static [Protocol] {
+        const VARIANTS: [Protocol; PROTOCOL_COUNT] = [
+            Protocol::Link,
+            Protocol::LinkAuth,
+            Protocol::Relay,
+            Protocol::DirCache,
+            Protocol::HSDir,
+            Protocol::HSIntro,
+            Protocol::HSRend,
+            Protocol::Desc,
+            Protocol::Microdesc,
+            Protocol::Cons,
+            Protocol::Padding,
+            Protocol::FlowCtrl,
+        ];
+        &VARIANTS
+    }
+
+    /// Get the index of this `Protocol` in `Protocol::variants()`, which is
+    /// also its value in C's `protocol_type_t`.
+    ///
+    /// C_RUST_COUPLED: protover.h `protocol_type_t`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use protover::Protocol;
+    ///
+    /// assert_eq!(Protocol::Link.to_index(), 0);
+    /// assert_eq!(Protocol::variants()[Protocol::Cons.to_index()], Protocol::Cons);
+    /// ```
+    pub fn to_index(&self) -> usize {
+        match *self {
+            Protocol::Link => 0,
+            Protocol::LinkAuth => 1,
+            Protocol::Relay => 2,
+            Protocol::DirCache => 3,
+            Protocol::HSDir => 4,
+            Protocol::HSIntro => 5,
+            Protocol::HSRend => 6,
+            Protocol::Desc => 7,
+            Protocol::Microdesc => 8,
+            Protocol::Cons => 9,
+            Protocol::Padding => 10,
+            Protocol::FlowCtrl => 11,
+        }
+    }
+
+    /// Determine if this `Protocol` is used for onion services.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use protover::Protocol;
+    ///
+    /// assert!(Protocol::HSIntro.is_hidden_service_related());
+    /// assert!(!Protocol::Link.is_hidden_service_related());
+    /// ```
+    pub fn is_hidden_service_related(&self) -> bool {
+        match *self {
+            Protocol::HSDir | Protocol::HSIntro | Protocol::HSRend => true,
+            _ => false,
+        }
+    }
+}
+
 impl fmt::Display for Protocol {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{:?}", self)
     }
 }
 
+/// `Protocol`s are ordered alphabetically by name, like `UnknownProtocol`s,
+/// rather than in declaration order.
+impl Ord for Protocol {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.to_string().cmp(&other.to_string())
+    }
+}
+
+impl PartialOrd for Protocol {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// The number of known protocols.
+///
+/// `Protocol::variants()` and `PROTOCOL_NAMES` are both arrays of this length,
+/// so this fails to compile if a name is added to `PROTOCOL_NAMES` without
+/// adding its `Protocol` to `Protocol::variants()`, or the other way round.
+const PROTOCOL_COUNT: usize = 12;
+
+/// Every protocol name recognised by `Protocol::from_str()`, with the
+/// `Protocol` it is parsed as.
+///
+/// C_RUST_COUPLED: protover.c `PROTOCOL_NAMES`
+const PROTOCOL_NAMES: [(&str, Protocol); PROTOCOL_COUNT] = [
+    ("Cons", Protocol::Cons),
+    ("Desc", Protocol::Desc),
+    ("DirCache", Protocol::DirCache),
+    ("HSDir", Protocol::HSDir),
+    ("HSIntro", Protocol::HSIntro),
+    ("HSRend", Protocol::HSRend),
+    ("Link", Protocol::Link),
+    ("LinkAuth", Protocol::LinkAuth),
+    ("Microdesc", Protocol::Microdesc),
+    ("Relay", Protocol::Relay),
+    ("Padding", Protocol::Padding),
+    ("FlowCtrl", Protocol::FlowCtrl),
+];
+
 /// Translates a string representation of a protocol into a Proto type.
 /// Error if the string is an unrecognized protocol name.
 ///
@@ -64,27 +179,56 @@ impl FromStr for Protocol {
     type Err = ProtoverError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "Cons" => Ok(Protocol::Cons),
-            "Desc" => Ok(Protocol::Desc),
-            "DirCache" => Ok(Protocol::DirCache),
-            "HSDir" => Ok(Protocol::HSDir),
-            "HSIntro" => Ok(Protocol::HSIntro),
-            "HSRend" => Ok(Protocol::HSRend),
-            "Link" => Ok(Protocol::Link),
-            "LinkAuth" => Ok(Protocol::LinkAuth),
-            "Microdesc" => Ok(Protocol::Microdesc),
-            "Relay" => Ok(Protocol::Relay),
-            "Padding" => Ok(Protocol::Padding),
-            "FlowCtrl" => Ok(Protocol::FlowCtrl),
-            _ => Err(ProtoverError::UnknownProtocol),
-        }
+        PROTOCOL_NAMES
+            .iter()
+            .find(|&&(name, _)| name == s)
+            .map(|(_, protocol)| protocol.clone())
+            .ok_or(ProtoverError::UnknownProtocol)
+    }
+}
+
+/// Translates a string representation of a protocol into a Proto type, like
+/// `Protocol::from_str()`.
+///
+/// # Examples
+///
+/// ```
+/// use std::convert::TryFrom;
+/// use protover::*;
+/// use protover::errors::ProtoverError;
+///
+/// # fn do_test() -> Result<Protocol, ProtoverError> {
+/// let protocol = Protocol::try_from("Cons")?;
+///
+/// assert_eq!(protocol, Protocol::Cons);
+/// assert_eq!(Protocol::try_from("Fribble"), Err(ProtoverError::UnknownProtocol));
+/// # Ok(protocol)
+/// # }
+/// # fn main() { do_test().unwrap(); }  // wrap the test so we can use the ? operator
+/// ```
+impl<'a> TryFrom<&'a str> for Protocol {
+    type Error = ProtoverError;
+
+    fn try_from(s: &'a str) -> Result<Self, Self::Error> {
+        Protocol::from_str(s)
+    }
+}
+
+/// Translates a string representation of a protocol into a Proto type, like
+/// `Protocol::from_str()`.
+impl TryFrom<String> for Protocol {
+    type Error = ProtoverError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Protocol::from_str(&s)
     }
 }
 
 /// A protocol string which is not one of the `Protocols` we currently know
 /// about.
-#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+///
+/// `UnknownProtocol`s are ordered alphabetically, by the bytes of their names.
+#[derive(Clone, Debug, Hash, Eq, PartialEq, Ord, PartialOrd)]
 pub struct UnknownProtocol(String);
 
 impl fmt::Display for UnknownProtocol {
@@ -111,7 +255,64 @@ impl FromStr for UnknownProtocol {
     }
 }
 
+/// Create an `UnknownProtocol` from a string, like
+/// `UnknownProtocol::from_str()`.
+///
+/// # Examples
+///
+/// ```
+/// use std::convert::TryFrom;
+/// use protover::*;
+/// use protover::errors::ProtoverError;
+///
+/// # fn do_test() -> Result<UnknownProtocol, ProtoverError> {
+/// let protocol = UnknownProtocol::try_from("Fribble")?;
+///
+/// assert_eq!(protocol.to_string(), "Fribble");
+/// assert_eq!(UnknownProtocol::try_from("a b"), Err(ProtoverError::InvalidProtocol));
+/// # Ok(protocol)
+/// # }
+/// # fn main() { do_test().unwrap(); }  // wrap the test so we can use the ? operator
+/// ```
+impl<'a> TryFrom<&'a str> for UnknownProtocol {
+    type Error = ProtoverError;
+
+    fn try_from(s: &'a str) -> Result<Self, Self::Error> {
+        UnknownProtocol::from_str(s)
+    }
+}
+
+/// Create an `UnknownProtocol` from a string, like
+/// `UnknownProtocol::from_str()`.
+impl TryFrom<String> for UnknownProtocol {
+    type Error = ProtoverError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        UnknownProtocol::from_str(&s)
+    }
+}
+
 impl UnknownProtocol {
+    /// Determine if `s` is a well-formed protocol name, without allocating an
+    /// `UnknownProtocol`.
+    ///
+    /// Valid names are not empty, are at most `MAX_PROTOCOL_NAME_LENGTH`
+    /// bytes long, and only contain ASCII letters, digits, and hyphens, like
+    /// other keywords in Tor's directory documents.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use protover::UnknownProtocol;
+    ///
+    /// assert!(UnknownProtocol::is_valid_format("Fribble-2"));
+    /// assert!(!UnknownProtocol::is_valid_format(""));
+    /// assert!(!UnknownProtocol::is_valid_format("a_b"));
+    /// ```
+    pub fn is_valid_format(s: &str) -> bool {
+        !s.is_empty() && s.len() <= MAX_PROTOCOL_NAME_LENGTH && is_valid_proto(s)
+    }
+
     /// Create an `UnknownProtocol`, ignoring whether or not it
     /// exceeds MAX_PROTOCOL_NAME_LENGTH.
     fn from_str_any_len(s: &str) -> Result<Self, ProtoverError> {
@@ -139,6 +340,45 @@ fn have_linkauth_v1() -> bool {
     !c_tor_is_using_nss()
 }
 
+#[cfg(not(test))]
+fn tor_version_as_new_as(platform: &str, cutoff: &str) -> bool {
+    use external::c_tor_version_as_new_as;
+    c_tor_version_as_new_as(platform, cutoff)
+}
+
+/// A mock of the C `tor_version_as_new_as()`, for the unit tests.
+///
+/// Like the C function, `platform` is a platform string like
+/// `"Tor 0.2.9.1-alpha (git-0123456789abcdef)"`.  Platforms without the
+/// `"Tor "` prefix, or with a version we can't parse, are as new as any
+/// cutoff.  Versions are compared by their dotted numbers (missing numbers
+/// are 0), then by their status tags, using strcmp() ordering.  The old
+/// "pre" and "rc" version formats aren't supported.
+#[cfg(test)]
+fn tor_version_as_new_as(platform: &str, cutoff: &str) -> bool {
+    fn parse(version: &str) -> Option<([u32; 4], &str)> {
+        let mut parts = version.splitn(2, '-');
+        let mut numbers = [0; 4];
+        for (i, n) in parts.next()?.split('.').enumerate() {
+            *numbers.get_mut(i)? = n.parse().ok()?;
+        }
+        let tag: &str = parts.next().unwrap_or("");
+
+        Some((numbers, tag))
+    }
+    let cutoff = match parse(cutoff) {
+        Some(cutoff) => cutoff,
+        None => return false,
+    };
+    if !platform.starts_with("Tor ") {
+        return true;
+    }
+    match platform[4..].split_whitespace().next().and_then(parse) {
+        Some(version) => version >= cutoff,
+        None => true,
+    }
+}
+
 /// Get a CStr representation of current supported protocols, for
 /// passing to C, or for converting to a `&str` for Rust.
 ///
@@ -189,8 +429,28 @@ pub(crate) fn get_supported_protocols_cstr() -> &'static CStr {
     }
 }
 
+/// Add `versions` of `protocol` to a protocol map, taking the union with any
+/// versions of `protocol` which are already in it.
+///
+/// Like `ProtoEntry::insert()`, this never stores an empty `ProtoSet`: if
+/// `versions` is empty, `map` is left unchanged.
+fn merge_versions<P: Hash + Eq>(map: &mut HashMap<P, ProtoSet>, protocol: P, versions: ProtoSet) {
+    if versions.is_empty() {
+        return;
+    }
+    match map.entry(protocol) {
+        hash_map::Entry::Occupied(mut existing) => {
+            let merged: ProtoSet = existing.get().union(&versions);
+            existing.insert(merged);
+        }
+        hash_map::Entry::Vacant(vacant) => {
+            vacant.insert(versions);
+        }
+    }
+}
+
 /// A map of protocol names to the versions of them which are supported.
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, PartialEq, Eq)]
 pub struct ProtoEntry(HashMap<Protocol, ProtoSet>);
 
 impl Default for ProtoEntry {
@@ -205,6 +465,14 @@ impl ProtoEntry {
         self.0.iter()
     }
 
+    /// Get an iterator over the `Protocol`s in this `ProtoEntry`, with mutable
+    /// references to their `ProtoSet`s.
+    ///
+    /// Any `ProtoSet`s which are emptied are left in this `ProtoEntry`.
+    pub fn iter_mut(&mut self) -> hash_map::IterMut<'_, Protocol, ProtoSet> {
+        self.0.iter_mut()
+    }
+
     /// Translate the supported tor versions from a string into a
     /// ProtoEntry, which is useful when looking up a specific
     /// subprotocol.
@@ -215,127 +483,145 @@ impl ProtoEntry {
         supported.parse()
     }
 
-    pub fn len(&self) -> usize {
-        self.0.len()
-    }
-
-    pub fn get(&self, protocol: &Protocol) -> Option<&ProtoSet> {
-        self.0.get(protocol)
-    }
-
-    pub fn insert(&mut self, key: Protocol, value: ProtoSet) {
-        self.0.insert(key, value);
-    }
-
-    pub fn remove(&mut self, key: &Protocol) -> Option<ProtoSet> {
-        self.0.remove(key)
-    }
-
-    pub fn is_empty(&self) -> bool {
-        self.0.is_empty()
-    }
-}
-
-impl FromStr for ProtoEntry {
-    type Err = ProtoverError;
-
-    /// Parse a string of subprotocol types and their version numbers.
+    /// Build a `ProtoEntry` from separate `"Protocol=versions"` strings, like
+    /// `["Cons=1-2", "Link=1-5"]`.
     ///
-    /// # Inputs
+    /// Protocols with no versions, like `"Relay="`, are left out.
     ///
-    /// * A `protocol_entry` string, comprised of a keywords, an "=" sign, and
-    /// one or more version numbers, each separated by a space.  For example,
-    /// `"Cons=3-4 HSDir=1"`.
+    /// # Errors
     ///
-    /// # Returns
+    /// Returns `ProtoverError::Unparseable` if a string is not a single
+    /// `"Protocol=versions"` pair, or if a protocol is listed more than once.
+    /// Otherwise, returns the same errors as `ProtoEntry::from_str()`.
     ///
-    /// A `Result` whose `Ok` value is a `ProtoEntry`.
-    /// Otherwise, the `Err` value of this `Result` is a `ProtoverError`.
-    fn from_str(protocol_entry: &str) -> Result<ProtoEntry, ProtoverError> {
+    /// # Examples
+    ///
+    /// ```
+    /// use protover::ProtoEntry;
+    ///
+    /// let entry = ProtoEntry::from_slice_of_strings(&["Link=1-5", "Cons=1-2"]).unwrap();
+    ///
+    /// assert_eq!("Cons=1-2 Link=1-5", entry.to_string());
+    /// assert!(ProtoEntry::from_slice_of_strings(&["Link=1", "Link=2"]).is_err());
+    /// ```
+    pub fn from_slice_of_strings(strs: &[&str]) -> Result<Self, ProtoverError> {
         let mut proto_entry: ProtoEntry = ProtoEntry::default();
-        let entries = protocol_entry.split(' ');
-
-        for entry in entries {
-            let mut parts = entry.splitn(2, '=');
-
-            let proto = match parts.next() {
-                Some(n) => n,
-                None => return Err(ProtoverError::Unparseable),
-            };
 
-            let vers = match parts.next() {
-                Some(n) => n,
-                None => return Err(ProtoverError::Unparseable),
-            };
+        for string in strs {
+            let (name, vers) = UnvalidatedProtoEntry::parse_single_protocol_and_version(string)?;
             let versions: ProtoSet = vers.parse()?;
-            let proto_name: Protocol = proto.parse()?;
+            let protocol: Protocol = name.parse()?;
 
-            proto_entry.insert(proto_name, versions);
-
-            if proto_entry.len() > MAX_PROTOCOLS_TO_EXPAND {
-                return Err(ProtoverError::ExceedsMax);
+            if proto_entry.0.contains_key(&protocol) {
+                return Err(ProtoverError::Unparseable);
             }
+            proto_entry.0.insert(protocol, versions);
         }
+        // Duplicate checks need every protocol, so remove the empty ones last.
+        proto_entry.0.retain(|_, versions| !versions.is_empty());
         Ok(proto_entry)
     }
-}
 
-/// Generate an implementation of `ToString` for either a `ProtoEntry` or an
-/// `UnvalidatedProtoEntry`.
-macro_rules! impl_to_string_for_proto_entry {
-    ($t:ty) => {
-        impl ToString for $t {
-            fn to_string(&self) -> String {
-                let mut parts: Vec<String> = Vec::new();
+    /// Build a `ProtoEntry` from several protocol entry strings, like
+    /// `["Cons=1 Link=1-3", "Cons=2 Relay=1"]`, merging them with
+    /// `ProtoEntry::merge()`.
+    ///
+    /// Unlike `ProtoEntry::from_slice_of_strings()`, each string may contain
+    /// several protocols, and a protocol may appear in more than one string.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error from `ProtoEntry::from_str()` when parsing any
+    /// of the `entries`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use protover::ProtoEntry;
+    ///
+    /// let entry = ProtoEntry::from_multiple(&["Cons=1 Link=1-3", "Cons=2 Relay=1"]).unwrap();
+    ///
+    /// assert_eq!("Cons=1-2 Link=1-3 Relay=1", entry.to_string());
+    /// ```
+    pub fn from_multiple(entries: &[&str]) -> Result<Self, ProtoverError> {
+        let mut proto_entry: ProtoEntry = ProtoEntry::default();
 
-                for (protocol, versions) in self.iter() {
-                    parts.push(format!("{}={}", protocol.to_string(), versions.to_string()));
-                }
-                parts.sort_unstable();
-                parts.join(" ")
-            }
+        for entry in entries {
+            proto_entry.merge(entry.parse()?);
         }
-    };
-}
-
-impl_to_string_for_proto_entry!(ProtoEntry);
-impl_to_string_for_proto_entry!(UnvalidatedProtoEntry);
-
-/// A `ProtoEntry`, but whose `Protocols` can be any `UnknownProtocol`, not just
-/// the supported ones enumerated in `Protocols`.  The protocol versions are
-/// validated, however.
-#[derive(Clone, Debug, PartialEq, Eq)]
-pub struct UnvalidatedProtoEntry(HashMap<UnknownProtocol, ProtoSet>);
-
-impl Default for UnvalidatedProtoEntry {
-    fn default() -> UnvalidatedProtoEntry {
-        UnvalidatedProtoEntry(HashMap::new())
+        Ok(proto_entry)
     }
-}
 
-impl UnvalidatedProtoEntry {
-    /// Get an iterator over the `Protocol`s and their `ProtoSet`s in this `ProtoEntry`.
-    pub fn iter(&self) -> hash_map::Iter<UnknownProtocol, ProtoSet> {
-        self.0.iter()
+    /// Merge all the protocols and versions in `other` into this `ProtoEntry`.
+    ///
+    /// If a protocol is in both entries, its versions are the union of both
+    /// `ProtoSet`s.  Protocols with no versions in `other` are skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use protover::ProtoEntry;
+    ///
+    /// let mut entry: ProtoEntry = "Cons=1 Link=1-3".parse().unwrap();
+    ///
+    /// entry.merge("Link=4-5 Relay=2".parse().unwrap());
+    /// assert_eq!("Cons=1 Link=1-5 Relay=2", entry.to_string());
+    /// ```
+    pub fn merge(&mut self, other: ProtoEntry) {
+        for (protocol, versions) in other.0 {
+            merge_versions(&mut self.0, protocol, versions);
+        }
     }
 
-    pub fn get(&self, protocol: &UnknownProtocol) -> Option<&ProtoSet> {
-        self.0.get(protocol)
-    }
+    /// Parse a "proto" line from a directory document, such as
+    /// `"proto Cons=1-2 Link=1-5"`, into a `ProtoEntry`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtoverError::Unparseable` if `line` does not start with the
+    /// `"proto "` keyword, or any error from `ProtoEntry::from_str()` when
+    /// parsing the rest of the line.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use protover::*;
+    /// use protover::errors::ProtoverError;
+    ///
+    /// let entry = ProtoEntry::from_consensus_str("proto Cons=1-2 Link=1-5").unwrap();
+    /// assert_eq!(entry.to_string(), "Cons=1-2 Link=1-5");
+    ///
+    /// let err = ProtoEntry::from_consensus_str("Cons=1-2 Link=1-5");
+    /// assert_eq!(err, Err(ProtoverError::Unparseable));
+    /// ```
+    pub fn from_consensus_str(line: &str) -> Result<Self, ProtoverError> {
+        const KEYWORD: &str = "proto ";
 
-    pub fn insert(&mut self, key: UnknownProtocol, value: ProtoSet) {
-        self.0.insert(key, value);
+        if !line.starts_with(KEYWORD) {
+            return Err(ProtoverError::Unparseable);
+        }
+        line[KEYWORD.len()..].parse()
     }
 
-    pub fn remove(&mut self, key: &UnknownProtocol) -> Option<ProtoSet> {
-        self.0.remove(key)
+    /// Get the number of distinct `Protocol`s in this `ProtoEntry`.
+    pub fn protocol_count(&self) -> usize {
+        self.0.len()
     }
 
-    pub fn is_empty(&self) -> bool {
-        self.0.is_empty()
+    /// Get the number of distinct `Protocol`s in this `ProtoEntry`.
+    ///
+    /// Note that the deprecated `UnvalidatedProtoEntry::len()` counts versions
+    /// instead.
+    #[deprecated(note = "use protocol_count() or total_version_count() instead")]
+    pub fn len(&self) -> usize {
+        self.protocol_count()
     }
 
-    pub fn len(&self) -> usize {
+    /// Get the total number of individual `Version`s of all the `Protocol`s
+    /// in this `ProtoEntry`.
+    ///
+    /// This is consistent with `UnvalidatedProtoEntry::total_version_count()`.
+    pub fn total_version_count(&self) -> usize {
         let mut total: usize = 0;
 
         for (_, versions) in self.iter() {
@@ -344,592 +630,3293 @@ impl UnvalidatedProtoEntry {
         total
     }
 
-    /// Determine if we support every protocol a client supports, and if not,
-    /// determine which protocols we do not have support for.
+    /// Get the number of `Version`s of the protocol called `name` in this
+    /// `ProtoEntry`.
     ///
     /// # Returns
     ///
-    /// Optionally, return parameters which the client supports but which we do not.
+    /// The number of versions, or 0 if `name` isn't a known `Protocol`, or
+    /// isn't in this `ProtoEntry`.
     ///
     /// # Examples
+    ///
     /// ```
-    /// use protover::UnvalidatedProtoEntry;
+    /// use protover::ProtoEntry;
     ///
-    /// let protocols: UnvalidatedProtoEntry = "LinkAuth=1 Microdesc=1-2 Relay=2".parse().unwrap();
-    /// let unsupported: Option<UnvalidatedProtoEntry> = protocols.all_supported();
-    /// assert_eq!(true, unsupported.is_none());
+    /// let entry: ProtoEntry = "Cons=1-2 Link=1-5".parse().unwrap();
     ///
-    /// let protocols: UnvalidatedProtoEntry = "Link=1-2 Wombat=9".parse().unwrap();
-    /// let unsupported: Option<UnvalidatedProtoEntry> = protocols.all_supported();
-    /// assert_eq!(true, unsupported.is_some());
-    /// assert_eq!("Wombat=9", &unsupported.unwrap().to_string());
+    /// assert_eq!(entry.version_count_for("Link"), 5);
+    /// assert_eq!(entry.version_count_for("Relay"), 0);
+    /// assert_eq!(entry.version_count_for("Fribble"), 0);
     /// ```
-    pub fn all_supported(&self) -> Option<UnvalidatedProtoEntry> {
-        let mut unsupported: UnvalidatedProtoEntry = UnvalidatedProtoEntry::default();
-        let supported: ProtoEntry = match ProtoEntry::supported() {
-            Ok(x) => x,
-            Err(_) => return None,
-        };
-
-        for (protocol, versions) in self.iter() {
-            let is_supported: Result<Protocol, ProtoverError> = protocol.0.parse();
-            let supported_protocol: Protocol;
-
-            // If the protocol wasn't even in the enum, then we definitely don't
-            // know about it and don't support any of its versions.
-            if is_supported.is_err() {
-                if !versions.is_empty() {
-                    unsupported.insert(protocol.clone(), versions.clone());
-                }
-                continue;
-            } else {
-                supported_protocol = is_supported.unwrap();
-            }
-
-            let maybe_supported_versions: Option<&ProtoSet> = supported.get(&supported_protocol);
-            let supported_versions: &ProtoSet;
+    pub fn version_count_for(&self, name: &str) -> usize {
+        match name.parse::<Protocol>() {
+            Ok(protocol) => self.supported_version_count_for(protocol),
+            Err(_) => 0,
+        }
+    }
 
-            // If the protocol wasn't in the map, then we don't know about it
-            // and don't support any of its versions.  Add its versions to the
-            // map (if it has versions).
-            if maybe_supported_versions.is_none() {
-                if !versions.is_empty() {
-                    unsupported.insert(protocol.clone(), versions.clone());
-                }
-                continue;
-            } else {
-                supported_versions = maybe_supported_versions.unwrap();
-            }
-            let unsupported_versions = versions.and_not_in(supported_versions);
-
-            if !unsupported_versions.is_empty() {
-                unsupported.insert(protocol.clone(), unsupported_versions);
-            }
-        }
+    /// Get the number of `Version`s of `protocol` in this `ProtoEntry`, or 0
+    /// if it isn't in this `ProtoEntry`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use protover::*;
+    ///
+    /// let entry: ProtoEntry = "Cons=1-2 Link=1-5".parse().unwrap();
+    ///
+    /// assert_eq!(entry.supported_version_count_for(Protocol::Link), 5);
+    /// assert_eq!(entry.supported_version_count_for(Protocol::Relay), 0);
+    /// ```
+    pub fn supported_version_count_for(&self, protocol: Protocol) -> usize {
+        self.get_or_empty(&protocol).len()
+    }
 
-        if unsupported.is_empty() {
-            return None;
-        }
-        Some(unsupported)
+    pub fn get(&self, protocol: &Protocol) -> Option<&ProtoSet> {
+        self.0.get(protocol)
     }
 
-    /// Determine if we have support for some protocol and version.
+    /// Determine if `proto` is listed in this `ProtoEntry`, regardless of
+    /// its versions.
     ///
-    /// # Inputs
+    /// Protocols listed with no versions, like `"Relay="`, are included.
     ///
-    /// * `proto`, an `UnknownProtocol` to test support for
-    /// * `vers`, a `Version` which we will go on to determine whether the
-    /// specified protocol supports.
+    /// # Examples
     ///
-    /// # Return
+    /// ```
+    /// use protover::*;
     ///
-    /// Returns `true` iff this `UnvalidatedProtoEntry` includes support for the
-    /// indicated protocol and version, and `false` otherwise.
+    /// let entry: ProtoEntry = "Cons=1-2 Link=1-5 Relay=".parse().unwrap();
+    ///
+    /// assert!(entry.has_protocol(&Protocol::Link));
+    /// assert!(entry.has_protocol(&Protocol::Relay));
+    /// assert!(!entry.has_protocol(&Protocol::Desc));
+    /// ```
+    pub fn has_protocol(&self, proto: &Protocol) -> bool {
+        self.0.contains_key(proto)
+    }
+
+    /// Get the `ProtoSet` for `protocol` in this `ProtoEntry`, or an empty
+    /// `ProtoSet` if it isn't in this `ProtoEntry`.
+    ///
+    /// The `ProtoSet` is borrowed if it exists.  Otherwise, a new empty
+    /// `ProtoSet` is returned, which doesn't allocate.
     ///
     /// # Examples
     ///
     /// ```
-    /// # use std::str::FromStr;
     /// use protover::*;
-    /// # use protover::errors::ProtoverError;
     ///
-    /// # fn do_test () -> Result<UnvalidatedProtoEntry, ProtoverError> {
-    /// let proto: UnvalidatedProtoEntry = "Link=3-4 Cons=1 Doggo=3-5".parse()?;
-    /// assert_eq!(true, proto.supports_protocol(&Protocol::Cons.into(), &1));
-    /// assert_eq!(false, proto.supports_protocol(&Protocol::Cons.into(), &5));
-    /// assert_eq!(true, proto.supports_protocol(&UnknownProtocol::from_str("Doggo")?, &4));
-    /// # Ok(proto)
-    /// # } fn main () { do_test(); }
+    /// let entry: ProtoEntry = "Cons=1-2 Link=1-5".parse().unwrap();
+    ///
+    /// assert_eq!(entry.get_or_empty(&Protocol::Link).len(), 5);
+    /// assert!(entry.get_or_empty(&Protocol::Relay).is_empty());
     /// ```
-    pub fn supports_protocol(&self, proto: &UnknownProtocol, vers: &Version) -> bool {
-        let supported_versions: &ProtoSet = match self.get(proto) {
-            Some(n) => n,
-            None => return false,
-        };
-        supported_versions.contains(&vers)
+    pub fn get_or_empty(&self, protocol: &Protocol) -> Cow<ProtoSet> {
+        match self.0.get(protocol) {
+            Some(versions) => Cow::Borrowed(versions),
+            None => Cow::Owned(ProtoSet::default()),
+        }
     }
 
-    /// As `UnvalidatedProtoEntry::supports_protocol()`, but also returns `true`
-    /// if any later version of the protocol is supported.
+    /// Get the highest `Version` of `proto` in this `ProtoEntry`, or `None`
+    /// if it has no versions of `proto`.
     ///
     /// # Examples
+    ///
     /// ```
     /// use protover::*;
-    /// # use protover::errors::ProtoverError;
     ///
-    /// # fn do_test () -> Result<UnvalidatedProtoEntry, ProtoverError> {
-    /// let proto: UnvalidatedProtoEntry = "Link=3-4 Cons=5".parse()?;
+    /// // Check whether a relay supports at least Link=5.
+    /// let relay: ProtoEntry = "Cons=1-2 Link=3-5".parse().unwrap();
+    /// let compatible = relay.highest_version(&Protocol::Link).map_or(false, |v| v >= 5);
     ///
-    /// assert_eq!(true, proto.supports_protocol_or_later(&Protocol::Cons.into(), &5));
-    /// assert_eq!(true, proto.supports_protocol_or_later(&Protocol::Cons.into(), &4));
-    /// assert_eq!(false, proto.supports_protocol_or_later(&Protocol::Cons.into(), &6));
-    /// # Ok(proto)
-    /// # } fn main () { do_test(); }
+    /// assert!(compatible);
+    /// assert_eq!(None, relay.highest_version(&Protocol::HSDir));
     /// ```
-    pub fn supports_protocol_or_later(&self, proto: &UnknownProtocol, vers: &Version) -> bool {
-        let supported_versions: &ProtoSet = match self.get(&proto) {
-            Some(n) => n,
-            None => return false,
-        };
-        supported_versions.iter().any(|v| v.1 >= *vers)
+    pub fn highest_version(&self, proto: &Protocol) -> Option<Version> {
+        self.get(proto)
+            .and_then(|versions| versions.iter().next_back())
+            .map(|&(_, high)| high)
     }
 
-    /// Split a string containing (potentially) several protocols and their
-    /// versions into a `Vec` of tuples of string in `(protocol, versions)`
-    /// form.
+    /// Get the lowest `Version` of `proto` in this `ProtoEntry`, or `None`
+    /// if it has no versions of `proto`.
     ///
-    /// # Inputs
+    /// # Examples
     ///
-    /// A &str in the form `"Link=3-4 Cons=5"`.
+    /// ```
+    /// use protover::*;
     ///
-    /// # Returns
+    /// // Check whether a relay still supports the original Cons=1.
+    /// let relay: ProtoEntry = "Cons=2 Link=3-5".parse().unwrap();
+    /// let compatible = relay.lowest_version(&Protocol::Cons).map_or(false, |v| v <= 1);
     ///
-    /// A `Result` whose `Ok` variant is a `Vec<(&str, &str)>` of `(protocol,
-    /// versions)`, or whose `Err` variant is a `ProtoverError`.
+    /// assert!(!compatible);
+    /// assert_eq!(Some(3), relay.lowest_version(&Protocol::Link));
+    /// ```
+    pub fn lowest_version(&self, proto: &Protocol) -> Option<Version> {
+        self.get(proto)
+            .and_then(|versions| versions.iter().next())
+            .map(|&(low, _)| low)
+    }
+
+    /// Set the supported versions of `key` to `value`, replacing any versions
+    /// which were already there.
     ///
-    /// # Errors
+    /// An empty `value` means `key` has no supported versions, so instead of
+    /// storing it, any existing entry for `key` is removed.
     ///
-    /// This will error with a `ProtoverError::Unparseable` if any of the
-    /// following are true:
+    /// # Examples
     ///
-    /// * If a protocol name is an empty string, e.g. `"Cons=1,3 =3-5"`.
-    /// * If an entry has no equals sign, e.g. `"Cons=1,3 Desc"`.
-    /// * If there is leading or trailing whitespace, e.g. `" Cons=1,3 Link=3"`.
-    /// * If there is any other extra whitespice, e.g. `"Cons=1,3  Link=3"`.
-    fn parse_protocol_and_version_str<'a>(
-        protocol_string: &'a str,
-    ) -> Result<Vec<(&'a str, &'a str)>, ProtoverError> {
-        let mut protovers: Vec<(&str, &str)> = Vec::new();
-
-        for subproto in protocol_string.split(' ') {
-            let mut parts = subproto.splitn(2, '=');
-
-            let name = match parts.next() {
-                Some("") => return Err(ProtoverError::Unparseable),
-                Some(n) => n,
-                None => return Err(ProtoverError::Unparseable),
-            };
-            let vers = match parts.next() {
-                Some(n) => n,
-                None => return Err(ProtoverError::Unparseable),
-            };
-            protovers.push((name, vers));
+    /// ```
+    /// use protover::*;
+    /// use protover::protoset::ProtoSet;
+    ///
+    /// let mut entry: ProtoEntry = "Cons=1-2 Link=1-5".parse().unwrap();
+    ///
+    /// entry.insert(Protocol::Link, ProtoSet::default());
+    /// assert_eq!("Cons=1-2", entry.to_string());
+    /// ```
+    pub fn insert(&mut self, key: Protocol, value: ProtoSet) {
+        if value.is_empty() {
+            self.0.remove(&key);
+        } else {
+            self.0.insert(key, value);
         }
-        Ok(protovers)
     }
-}
 
-impl FromStr for UnvalidatedProtoEntry {
-    type Err = ProtoverError;
+    pub fn remove(&mut self, key: &Protocol) -> Option<ProtoSet> {
+        self.0.remove(key)
+    }
 
-    /// Parses a protocol list without validating the protocol names.
+    /// Returns `true` if this `ProtoEntry` does not support any versions of
+    /// any protocol.
     ///
-    /// # Inputs
-    ///
-    /// * `protocol_string`, a string comprised of keys and values, both which are
-    /// strings. The keys are the protocol names while values are a string
-    /// representation of the supported versions.
+    /// This includes entries where every protocol is listed with no versions,
+    /// like `"Relay="`.
     ///
-    /// The input is _not_ expected to be a subset of the Protocol types
+    /// # Examples
     ///
-    /// # Returns
+    /// ```
+    /// use protover::*;
     ///
-    /// A `Result` whose `Ok` value is an `UnvalidatedProtoEntry`.
+    /// let mut entry: ProtoEntry = "Cons= Link=5".parse().unwrap();
+    /// assert!(!entry.is_empty());
     ///
-    /// The returned `Result`'s `Err` value is an `ProtoverError`.
+    /// entry.remove_protocol_version(&Protocol::Link, 5);
+    /// assert!(entry.is_empty());
+    /// assert_eq!("Cons=", entry.to_string());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.0.values().all(|versions| versions.is_empty())
+    }
+
+    /// Add a single `version` of `protocol` to this `ProtoEntry`, creating an
+    /// entry for `protocol` if it is not already present.
     ///
     /// # Errors
     ///
-    /// This function will error if:
+    /// * `ProtoverError::Overlap`: if `version` is 0, and
+    /// * `ProtoverError::ExceedsMax`: if `version` is `u32::MAX`.
     ///
-    /// * The protocol string does not follow the "protocol_name=version_list"
-    ///   expected format, or
-    /// * If the version string is malformed. See `impl FromStr for ProtoSet`.
-    fn from_str(protocol_string: &str) -> Result<UnvalidatedProtoEntry, ProtoverError> {
-        let mut parsed: UnvalidatedProtoEntry = UnvalidatedProtoEntry::default();
-        let parts: Vec<(&str, &str)> =
-            UnvalidatedProtoEntry::parse_protocol_and_version_str(protocol_string)?;
-
-        for &(name, vers) in parts.iter() {
-            let versions = ProtoSet::from_str(vers)?;
-            let protocol = UnknownProtocol::from_str(name)?;
-
-            parsed.insert(protocol, versions);
+    /// In either case, this `ProtoEntry` is unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use protover::*;
+    ///
+    /// let mut entry: ProtoEntry = "Link=1-2".parse().unwrap();
+    ///
+    /// entry.add_protocol_version(Protocol::Link, 3).unwrap();
+    /// entry.add_protocol_version(Protocol::Cons, 1).unwrap();
+    ///
+    /// assert_eq!(entry.to_string(), "Cons=1 Link=1-3");
+    /// ```
+    pub fn add_protocol_version(
+        &mut self,
+        protocol: Protocol,
+        version: Version,
+    ) -> Result<(), ProtoverError> {
+        match self.0.entry(protocol) {
+            hash_map::Entry::Occupied(entry) => entry.into_mut().insert(version),
+            hash_map::Entry::Vacant(entry) => {
+                let mut versions = ProtoSet::default();
+
+                versions.insert(version)?;
+                entry.insert(versions);
+                Ok(())
+            }
         }
-        Ok(parsed)
     }
-}
-
-impl UnvalidatedProtoEntry {
-    /// Create an `UnknownProtocol`, ignoring whether or not it
-    /// exceeds MAX_PROTOCOL_NAME_LENGTH.
-    pub(crate) fn from_str_any_len(
-        protocol_string: &str,
-    ) -> Result<UnvalidatedProtoEntry, ProtoverError> {
-        let mut parsed: UnvalidatedProtoEntry = UnvalidatedProtoEntry::default();
-        let parts: Vec<(&str, &str)> =
-            UnvalidatedProtoEntry::parse_protocol_and_version_str(protocol_string)?;
 
-        for &(name, vers) in parts.iter() {
-            let versions = ProtoSet::from_str(vers)?;
-            let protocol = UnknownProtocol::from_str_any_len(name)?;
+    /// Remove a single `version` of `protocol` from this `ProtoEntry`.
+    ///
+    /// If that was the last version of `protocol`, the entry for `protocol` is
+    /// removed as well.
+    ///
+    /// # Returns
+    ///
+    /// `true` if `version` of `protocol` was in this `ProtoEntry`; `false`
+    /// otherwise.
+    pub fn remove_protocol_version(&mut self, protocol: &Protocol, version: Version) -> bool {
+        let (removed, now_empty) = match self.0.get_mut(protocol) {
+            Some(versions) => (versions.remove(version), versions.is_empty()),
+            None => return false,
+        };
 
-            parsed.insert(protocol, versions);
+        if now_empty {
+            self.0.remove(protocol);
         }
-        Ok(parsed)
+        removed
     }
-}
-
-/// Pretend a `ProtoEntry` is actually an `UnvalidatedProtoEntry`.
-impl From<ProtoEntry> for UnvalidatedProtoEntry {
-    fn from(proto_entry: ProtoEntry) -> UnvalidatedProtoEntry {
-        let mut unvalidated: UnvalidatedProtoEntry = UnvalidatedProtoEntry::default();
 
-        for (protocol, versions) in proto_entry.iter() {
-            unvalidated.insert(UnknownProtocol::from(protocol.clone()), versions.clone());
-        }
-        unvalidated
-    }
-}
+    /// Remove every version of `protocol` below `min_version` from this
+    /// `ProtoEntry`.
+    ///
+    /// If no versions of `protocol` are left, the entry for `protocol` is
+    /// removed as well.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use protover::*;
+    ///
+    /// let mut entry: ProtoEntry = "Cons=1-2 Link=1-5".parse().unwrap();
+    ///
+    /// entry.remove_below_version(&Protocol::Link, 3);
+    /// entry.remove_below_version(&Protocol::Cons, 3);
+    ///
+    /// assert_eq!(entry.to_string(), "Link=3-5");
+    /// ```
+    pub fn remove_below_version(&mut self, protocol: &Protocol, min_version: Version) {
+        let remaining: ProtoSet = match self.0.get(protocol) {
+            Some(versions) if min_version > 0 => {
+                // Ranges starting at version 0 fail validation, so build it directly.
+                let below = ProtoSet::from_sorted_unchecked(vec![(0, min_version - 1)]);
 
-/// A mapping of protocols to a count of how many times each of their `Version`s
-/// were voted for or supported.
-///
-/// # Warning
-///
-/// The "protocols" are *not* guaranteed to be known/supported `Protocol`s, in
-/// order to allow new subprotocols to be introduced even if Directory
-/// Authorities don't yet know of them.
-pub struct ProtoverVote(HashMap<UnknownProtocol, HashMap<Version, usize>>);
+                versions.and_not_in(&below)
+            }
+            Some(versions) => versions.clone(),
+            None => return,
+        };
 
-impl Default for ProtoverVote {
-    fn default() -> ProtoverVote {
-        ProtoverVote(HashMap::new())
+        if remaining.is_empty() {
+            self.0.remove(protocol);
+        } else {
+            self.0.insert(protocol.clone(), remaining);
+        }
     }
-}
-
-impl IntoIterator for ProtoverVote {
-    type Item = (UnknownProtocol, HashMap<Version, usize>);
-    type IntoIter = hash_map::IntoIter<UnknownProtocol, HashMap<Version, usize>>;
 
-    fn into_iter(self) -> Self::IntoIter {
-        self.0.into_iter()
-    }
-}
+    /// Remove every version of `protocol` above `max` from this `ProtoEntry`.
+    ///
+    /// If no versions of `protocol` are left, the entry for `protocol` is
+    /// removed as well.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use protover::*;
+    ///
+    /// let mut entry: ProtoEntry = "Cons=3-4 Link=1-5".parse().unwrap();
+    ///
+    /// entry.downgrade_to_max_version(&Protocol::Link, 3);
+    /// entry.downgrade_to_max_version(&Protocol::Cons, 2);
+    ///
+    /// assert_eq!(entry.to_string(), "Link=1-3");
+    /// ```
+    pub fn downgrade_to_max_version(&mut self, protocol: &Protocol, max: Version) {
+        let remaining: ProtoSet = match self.0.get(protocol) {
+            // Sets can't contain u32::MAX, so saturating keeps every version.
+            Some(versions) => versions.split_at(max.saturating_add(1)).0,
+            None => return,
+        };
 
-impl ProtoverVote {
-    pub fn entry(
-        &mut self,
-        key: UnknownProtocol,
-    ) -> hash_map::Entry<UnknownProtocol, HashMap<Version, usize>> {
-        self.0.entry(key)
+        if remaining.is_empty() {
+            self.0.remove(protocol);
+        } else {
+            self.0.insert(protocol.clone(), remaining);
+        }
     }
 
-    /// Protocol voting implementation.
+    /// Remove every version above `max` from every protocol in this
+    /// `ProtoEntry`, like `downgrade_to_max_version()`.
     ///
-    /// Given a slice of `UnvalidatedProtoEntry`s and a vote `threshold`, return
-    /// a new `UnvalidatedProtoEntry` encoding all of the protocols that are
-    /// listed by at least `threshold` of the inputs.
+    /// Any protocols which are left with no versions are removed as well.
     ///
     /// # Examples
     ///
     /// ```
-    /// use protover::ProtoverVote;
-    /// use protover::UnvalidatedProtoEntry;
+    /// use protover::*;
     ///
-    /// let protos: &[UnvalidatedProtoEntry] = &["Link=3-4".parse().unwrap(),
-    ///                                          "Link=3".parse().unwrap()];
-    /// let vote = ProtoverVote::compute(protos, &2);
-    /// assert_eq!("Link=3", vote.to_string());
+    /// let mut entry: ProtoEntry = "Cons=1-2 HSIntro=3-5 Link=1-5".parse().unwrap();
+    ///
+    /// entry.cap_all_protocols_at(2);
+    ///
+    /// assert_eq!(entry.to_string(), "Cons=1-2 Link=1-2");
     /// ```
-    // C_RUST_COUPLED: protover.c protover_compute_vote
-    pub fn compute(
-        proto_entries: &[UnvalidatedProtoEntry],
-        threshold: &usize,
-    ) -> UnvalidatedProtoEntry {
-        let mut all_count: ProtoverVote = ProtoverVote::default();
-        let mut final_output: UnvalidatedProtoEntry = UnvalidatedProtoEntry::default();
+    pub fn cap_all_protocols_at(&mut self, max: Version) {
+        for versions in self.0.values_mut() {
+            *versions = versions.split_at(max.saturating_add(1)).0;
+        }
+        self.0.retain(|_, versions| !versions.is_empty());
+    }
+
+    /// Copy the versions of every protocol in this `ProtoEntry` which are at
+    /// least `min` into a new `ProtoEntry`.
+    ///
+    /// Ranges which contain `min` are split.  Protocols with no versions of
+    /// at least `min` are not included.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use protover::ProtoEntry;
+    ///
+    /// let entry: ProtoEntry = "Cons=1-2 HSIntro=3-5 Link=1-5".parse().unwrap();
+    ///
+    /// assert_eq!(entry.to_required_entry_at_version(4).to_string(), "HSIntro=4-5 Link=4-5");
+    /// ```
+    pub fn to_required_entry_at_version(&self, min: Version) -> ProtoEntry {
+        let mut required = ProtoEntry::default();
+
+        for (protocol, versions) in self.iter() {
+            let (_, at_or_above) = versions.split_at(min);
+
+            if !at_or_above.is_empty() {
+                required.insert(protocol.clone(), at_or_above);
+            }
+        }
+        required
+    }
+
+    /// Remove every version listed in the deprecation `policy` from this
+    /// `ProtoEntry`.
+    ///
+    /// Any protocols which are left with no versions are removed as well.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use protover::*;
+    ///
+    /// let mut entry: ProtoEntry = "Cons=1-2 Link=1-5 Relay=1-2".parse().unwrap();
+    /// let policy: ProtoEntry = "Cons=1-2 Link=1-2,4 Desc=1".parse().unwrap();
+    ///
+    /// entry.remove_deprecated_by(&policy);
+    ///
+    /// assert_eq!(entry.to_string(), "Link=3,5 Relay=1-2");
+    /// ```
+    pub fn remove_deprecated_by(&mut self, policy: &ProtoEntry) {
+        for (protocol, deprecated) in policy.iter() {
+            let remaining: ProtoSet = match self.0.get(protocol) {
+                Some(versions) => versions.and_not_in(deprecated),
+                None => continue,
+            };
+
+            if remaining.is_empty() {
+                self.0.remove(protocol);
+            } else {
+                self.0.insert(protocol.clone(), remaining);
+            }
+        }
+    }
+
+    /// Determine which of the `required` protocol versions are missing from
+    /// this `ProtoEntry`.
+    ///
+    /// # Returns
+    ///
+    /// A map from each `required` protocol to the versions of it which this
+    /// `ProtoEntry` does not have.  Protocols with no missing versions are
+    /// left out, so an empty map means that every requirement is satisfied.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use protover::*;
+    ///
+    /// let relay: ProtoEntry = "Cons=1-2 Link=3-5".parse().unwrap();
+    /// let required: ProtoEntry = "Cons=2 Link=4-6 Relay=2".parse().unwrap();
+    /// let missing = relay.check_required_protocols(&required);
+    ///
+    /// assert_eq!(2, missing.len());
+    /// assert_eq!("6", missing[&Protocol::Link].to_string());
+    /// assert_eq!("2", missing[&Protocol::Relay].to_string());
+    ///
+    /// assert!(relay.check_required_protocols(&relay).is_empty());
+    /// ```
+    pub fn check_required_protocols(&self, required: &ProtoEntry) -> HashMap<Protocol, ProtoSet> {
+        let mut missing: HashMap<Protocol, ProtoSet> = HashMap::new();
+
+        for (protocol, versions) in required.iter() {
+            let missing_versions: ProtoSet = match self.get(protocol) {
+                Some(ours) => versions.and_not_in(ours),
+                None => versions.clone(),
+            };
+
+            if !missing_versions.is_empty() {
+                missing.insert(protocol.clone(), missing_versions);
+            }
+        }
+        missing
+    }
+
+    /// Count the individual `(protocol, Version)` pairs in `required` which
+    /// are missing from this `ProtoEntry`.
+    ///
+    /// This is the scalar form of `check_required_protocols()`: 0 means that
+    /// every requirement is satisfied.  Each protocol's ranges are compared
+    /// in a single pass with `ProtoSet::retain_range_set()`, without
+    /// expanding any versions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use protover::ProtoEntry;
+    ///
+    /// let relay: ProtoEntry = "Cons=1-2 Link=3-5".parse().unwrap();
+    /// let required: ProtoEntry = "Cons=2 Link=4-6 Relay=2".parse().unwrap();
+    ///
+    /// assert_eq!(relay.count_protocols_missing_from(&required), 2);
+    /// assert_eq!(relay.count_protocols_missing_from(&relay), 0);
+    /// ```
+    pub fn count_protocols_missing_from(&self, required: &ProtoEntry) -> usize {
+        let mut count: usize = 0;
+
+        for (protocol, versions) in required.iter() {
+            count += match self.get(protocol) {
+                Some(ours) => versions.len() - versions.retain_range_set(ours).len(),
+                None => versions.len(),
+            };
+        }
+        count
+    }
+
+    /// Count the individual `(protocol, Version)` pairs in this `ProtoEntry`
+    /// which are also in `other`.
+    ///
+    /// This is a simple compatibility score between two nodes.  It compares
+    /// ranges, rather than expanding every version, so it is fast even for
+    /// very large `ProtoSet`s.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use protover::ProtoEntry;
+    ///
+    /// let ours: ProtoEntry = "Cons=1-2 Link=1-5".parse().unwrap();
+    /// let theirs: ProtoEntry = "Cons=2-3 Link=4-9 Relay=1".parse().unwrap();
+    ///
+    /// assert_eq!(ours.count_supported_by(&theirs), 3);
+    /// assert_eq!(theirs.count_supported_by(&ours), 3);
+    /// ```
+    pub fn count_supported_by(&self, other: &ProtoEntry) -> usize {
+        let mut count: usize = 0;
+
+        for (protocol, versions) in self.iter() {
+            if let Some(theirs) = other.get(protocol) {
+                count += versions.len() - versions.and_not_in(theirs).len();
+            }
+        }
+        count
+    }
+
+    /// Count the individual `(Protocol, Version)` pairs which this
+    /// `ProtoEntry` and `other` have in common.
+    ///
+    /// The count is symmetric, so this is the same as `count_supported_by()`,
+    /// which should be used instead.
+    #[deprecated(note = "use count_supported_by() instead")]
+    pub fn intersection_count(&self, other: &ProtoEntry) -> usize {
+        self.count_supported_by(other)
+    }
+
+    /// Get a lazy iterator over every individual `(Protocol, Version)` pair in
+    /// this `ProtoEntry`.
+    ///
+    /// The versions of each protocol are yielded in ascending order, but the
+    /// protocols themselves are in no particular order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use protover::*;
+    ///
+    /// let entry: ProtoEntry = "Cons=1-2 Link=4".parse().unwrap();
+    /// let mut pairs: Vec<(&Protocol, u32)> = entry.flat_iter().collect();
+    /// pairs.sort_by_key(|&(p, v)| (p.to_string(), v));
+    ///
+    /// assert_eq!(pairs, vec![(&Protocol::Cons, 1), (&Protocol::Cons, 2), (&Protocol::Link, 4)]);
+    /// ```
+    pub fn flat_iter<'a>(&'a self) -> impl Iterator<Item = (&'a Protocol, Version)> + 'a {
+        self.iter().flat_map(|(protocol, versions)| {
+            versions
+                .iter()
+                .flat_map(move |&(low, high)| (low..high + 1).map(move |v| (protocol, v)))
+        })
+    }
+
+    /// Get a lazy iterator over every individual `(Protocol, Version)` pair in
+    /// this `ProtoEntry`, in a deterministic order.
+    ///
+    /// This is like `flat_iter()`, but the protocols are sorted by
+    /// `Protocol::to_index()`, and each protocol's versions are in ascending
+    /// order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use protover::*;
+    ///
+    /// let entry: ProtoEntry = "Cons=1-2 Link=4".parse().unwrap();
+    /// let pairs: Vec<(&Protocol, u32)> = entry.iter_all().collect();
+    ///
+    /// assert_eq!(pairs, vec![(&Protocol::Link, 4), (&Protocol::Cons, 1), (&Protocol::Cons, 2)]);
+    /// ```
+    pub fn iter_all<'a>(&'a self) -> impl Iterator<Item = (&'a Protocol, Version)> + 'a {
+        let mut protocols: Vec<&Protocol> = self.0.keys().collect();
+
+        protocols.sort_unstable_by_key(|protocol| protocol.to_index());
+        protocols.into_iter().flat_map(move |protocol| {
+            self.0[protocol]
+                .iter()
+                .flat_map(move |&(low, high)| (low..high + 1).map(move |v| (protocol, v)))
+        })
+    }
+
+    /// Get the `Protocol`s in this `ProtoEntry`, sorted alphabetically by name.
+    ///
+    /// This sorts the names, so it takes O(n log n) time in the number of
+    /// protocols.
+    pub fn protocol_names(&self) -> Vec<&Protocol> {
+        let mut names: Vec<&Protocol> = self.0.keys().collect();
+
+        names.sort_unstable();
+        names
+    }
+
+    /// Format this `ProtoEntry` in the canonical form used in the "proto"
+    /// lines of relay descriptors.
+    ///
+    /// Protocols are sorted alphabetically by name, and each protocol's
+    /// versions are compressed into as few ranges as possible.  This is the
+    /// same as `to_string()`, except that `to_string()` keeps any adjacent
+    /// ranges which were parsed separately, like `"1-2,3"`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use protover::*;
+    ///
+    /// let entry: ProtoEntry = "Link=3,1-2 Cons=1".parse().unwrap();
+    ///
+    /// assert_eq!(entry.to_string(), "Cons=1 Link=1-2,3");
+    /// assert_eq!(entry.format_for_descriptor(), "Cons=1 Link=1-3");
+    /// ```
+    //  C_RUST_COUPLED: protover.c contract_protocol_list
+    pub fn format_for_descriptor(&self) -> String {
+        let mut parts: Vec<String> = Vec::new();
+
+        for protocol in self.protocol_names() {
+            let mut versions: ProtoSet = self.0[protocol].clone();
+
+            versions.normalize();
+            parts.push(format!("{}={}", protocol, versions.to_string()));
+        }
+        parts.join(" ")
+    }
+
+    /// Copy the onion service protocols in this `ProtoEntry` into a new
+    /// `ProtoEntry`.
+    ///
+    /// See `Protocol::is_hidden_service_related()` for the list of protocols.
+    pub fn hidden_service_protocols(&self) -> ProtoEntry {
+        let mut hs_protocols = ProtoEntry::default();
+
+        for (protocol, versions) in self.iter() {
+            if protocol.is_hidden_service_related() {
+                hs_protocols.0.insert(protocol.clone(), versions.clone());
+            }
+        }
+        hs_protocols
+    }
+
+    /// Copy only the listed `protocols` in this `ProtoEntry` into a new
+    /// `ProtoEntry`.
+    ///
+    /// Listed protocols which aren't in this `ProtoEntry` are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use protover::*;
+    ///
+    /// let entry: ProtoEntry = "Cons=1-2 Link=1-5 Relay=1-2".parse().unwrap();
+    /// let link: ProtoEntry = entry.clone_with_only(&[Protocol::Link, Protocol::Relay]);
+    ///
+    /// assert_eq!(link.to_string(), "Link=1-5 Relay=1-2");
+    /// ```
+    pub fn clone_with_only(&self, protocols: &[Protocol]) -> ProtoEntry {
+        let mut filtered = ProtoEntry::default();
+
+        for (protocol, versions) in self.iter() {
+            if protocols.contains(protocol) {
+                filtered.0.insert(protocol.clone(), versions.clone());
+            }
+        }
+        filtered
+    }
+
+    /// Get the protocol versions in this `ProtoEntry` which are not in the
+    /// `minimum` requirements, such as a relay's capabilities above the
+    /// network's required protocols.
+    ///
+    /// Protocols which aren't in `minimum` keep all their versions.
+    /// Protocols with no versions left over are not included.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use protover::ProtoEntry;
+    ///
+    /// let relay: ProtoEntry = "Cons=1-2 Link=1-5 Padding=2".parse().unwrap();
+    /// let minimum: ProtoEntry = "Cons=1-2 Link=1-3 Relay=2".parse().unwrap();
+    ///
+    /// assert_eq!("Link=4-5 Padding=2", relay.protocols_supported_above(&minimum).to_string());
+    /// ```
+    pub fn protocols_supported_above(&self, minimum: &ProtoEntry) -> ProtoEntry {
+        let mut above = ProtoEntry::default();
+
+        for (protocol, versions) in self.iter() {
+            let remaining: ProtoSet = match minimum.get(protocol) {
+                Some(required) => versions.and_not_in(required),
+                None => versions.clone(),
+            };
+
+            if !remaining.is_empty() {
+                above.insert(protocol.clone(), remaining);
+            }
+        }
+        above
+    }
+
+    /// Copy this `ProtoEntry` into an `UnvalidatedProtoEntry`, leaving this
+    /// `ProtoEntry` intact.
+    pub fn to_unvalidated(&self) -> UnvalidatedProtoEntry {
+        UnvalidatedProtoEntry::from(self)
+    }
+}
+
+impl IntoIterator for ProtoEntry {
+    type Item = (Protocol, ProtoSet);
+    type IntoIter = hash_map::IntoIter<Protocol, ProtoSet>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a ProtoEntry {
+    type Item = (&'a Protocol, &'a ProtoSet);
+    type IntoIter = hash_map::Iter<'a, Protocol, ProtoSet>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl FromStr for ProtoEntry {
+    type Err = ProtoverError;
+
+    /// Parse a string of subprotocol types and their version numbers.
+    ///
+    /// # Inputs
+    ///
+    /// * A `protocol_entry` string, comprised of a keywords, an "=" sign, and
+    /// one or more version numbers, each separated by a space.  For example,
+    /// `"Cons=3-4 HSDir=1"`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` whose `Ok` value is a `ProtoEntry`.
+    /// Otherwise, the `Err` value of this `Result` is a `ProtoverError`:
+    /// `ProtoverError::EmptyInput` if `protocol_entry` is empty or only
+    /// whitespace, or `ProtoverError::Unparseable` if it is malformed.
+    fn from_str(protocol_entry: &str) -> Result<ProtoEntry, ProtoverError> {
+        if protocol_entry.trim().is_empty() {
+            return Err(ProtoverError::EmptyInput);
+        }
+
+        let mut proto_entry: ProtoEntry = ProtoEntry::default();
+        let entries = protocol_entry.split(' ');
+
+        for entry in entries {
+            let mut parts = entry.splitn(2, '=');
+
+            let proto = match parts.next() {
+                Some(n) => n,
+                None => return Err(ProtoverError::Unparseable),
+            };
+
+            let vers = match parts.next() {
+                Some(n) => n,
+                None => return Err(ProtoverError::Unparseable),
+            };
+            let versions: ProtoSet = ProtoSet::from_str_strict(vers)?;
+            let proto_name: Protocol = proto.parse()?;
+
+            proto_entry.0.insert(proto_name, versions);
+
+            if proto_entry.protocol_count() > MAX_PROTOCOLS_TO_EXPAND {
+                return Err(ProtoverError::ExceedsMax);
+            }
+        }
+        Ok(proto_entry)
+    }
+}
+
+/// Generate an implementation of `ToString` for either a `ProtoEntry` or an
+/// `UnvalidatedProtoEntry`.
+///
+/// Protocols are sorted by name, to match the C implementation.
+//
+// C_RUST_COUPLED: protover.c `contract_protocol_list`
+macro_rules! impl_to_string_for_proto_entry {
+    ($t:ty) => {
+        impl ToString for $t {
+            fn to_string(&self) -> String {
+                let mut entries: Vec<_> = self.0.iter().collect();
+                entries.sort_unstable_by(|&(a, _), &(b, _)| a.cmp(b));
+
+                let parts: Vec<String> = entries
+                    .into_iter()
+                    .map(|(protocol, versions)| format!("{}={}", protocol, versions.to_string()))
+                    .collect();
+                parts.join(" ")
+            }
+        }
+    };
+}
+
+impl_to_string_for_proto_entry!(ProtoEntry);
+impl_to_string_for_proto_entry!(UnvalidatedProtoEntry);
+
+/// Hash a `ProtoEntry` or `UnvalidatedProtoEntry` by feeding its protocols and
+/// their `ProtoSet`s to the `Hasher` in alphabetical order, so that equal
+/// entries hash the same way, regardless of `HashMap` iteration order.
+macro_rules! impl_hash_for_proto_entry {
+    ($t:ty) => {
+        impl Hash for $t {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                self.0.len().hash(state);
+                for protocol in self.protocol_names() {
+                    protocol.hash(state);
+                    self.0[protocol].hash(state);
+                }
+            }
+        }
+    };
+}
+
+impl_hash_for_proto_entry!(ProtoEntry);
+impl_hash_for_proto_entry!(UnvalidatedProtoEntry);
+
+/// `ProtoSet`s with fewer than this many `Version`s are shown as a list of
+/// versions in `Debug` output, and larger ones are shown as ranges.
+const DEBUG_EXPAND_LIMIT: usize = 20;
+
+/// Format a `ProtoSet` for `Debug` output, so that small sets are easy to
+/// compare in test failure messages.
+struct DebugVersions<'a>(&'a ProtoSet);
+
+impl<'a> fmt::Debug for DebugVersions<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.0.len() < DEBUG_EXPAND_LIMIT {
+            f.debug_list().entries(self.0.to_vec()).finish()
+        } else {
+            f.write_str(&self.0.to_string())
+        }
+    }
+}
+
+/// Show the protocols in alphabetical order, with small `ProtoSet`s expanded,
+/// like `ProtoEntry {Cons: [1, 2], Link: 1-30}`.
+impl fmt::Debug for ProtoEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("ProtoEntry ")?;
+        f.debug_map()
+            .entries(
+                self.protocol_names()
+                    .into_iter()
+                    .map(|protocol| (protocol, DebugVersions(&self.0[protocol]))),
+            )
+            .finish()
+    }
+}
+
+/// A `ProtoEntry`, but whose `Protocols` can be any `UnknownProtocol`, not just
+/// the supported ones enumerated in `Protocols`.  The protocol versions are
+/// validated, however.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnvalidatedProtoEntry(HashMap<UnknownProtocol, ProtoSet>);
+
+impl Default for UnvalidatedProtoEntry {
+    fn default() -> UnvalidatedProtoEntry {
+        UnvalidatedProtoEntry(HashMap::new())
+    }
+}
+
+impl UnvalidatedProtoEntry {
+    /// Get an iterator over the `Protocol`s and their `ProtoSet`s in this `ProtoEntry`.
+    pub fn iter(&self) -> hash_map::Iter<UnknownProtocol, ProtoSet> {
+        self.0.iter()
+    }
+
+    /// Get an iterator over the protocols in this `UnvalidatedProtoEntry`,
+    /// with mutable references to their `ProtoSet`s.
+    ///
+    /// Any `ProtoSet`s which are emptied are left in this
+    /// `UnvalidatedProtoEntry`.
+    pub fn iter_mut(&mut self) -> hash_map::IterMut<'_, UnknownProtocol, ProtoSet> {
+        self.0.iter_mut()
+    }
+
+    pub fn get(&self, protocol: &UnknownProtocol) -> Option<&ProtoSet> {
+        self.0.get(protocol)
+    }
+
+    /// Get the `ProtoSet` for `protocol` in this `UnvalidatedProtoEntry`, or
+    /// an empty `ProtoSet` if it isn't in this `UnvalidatedProtoEntry`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use protover::UnvalidatedProtoEntry;
+    ///
+    /// let entry: UnvalidatedProtoEntry = "Fribble=1-2 Link=1-5".parse().unwrap();
+    ///
+    /// assert_eq!(entry.get_or_empty(&"Fribble".parse().unwrap()).len(), 2);
+    /// assert!(entry.get_or_empty(&"Wombat".parse().unwrap()).is_empty());
+    /// ```
+    pub fn get_or_empty(&self, protocol: &UnknownProtocol) -> Cow<ProtoSet> {
+        match self.0.get(protocol) {
+            Some(versions) => Cow::Borrowed(versions),
+            None => Cow::Owned(ProtoSet::default()),
+        }
+    }
+
+    /// Get the highest `Version` of `proto` in this `UnvalidatedProtoEntry`,
+    /// or `None` if it has no versions of `proto`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use protover::*;
+    ///
+    /// let relay: UnvalidatedProtoEntry = "Link=3-5 Wombat=2,7".parse().unwrap();
+    ///
+    /// assert_eq!(Some(5), relay.highest_version(&Protocol::Link.into()));
+    /// assert_eq!(Some(7), relay.highest_version(&"Wombat".parse().unwrap()));
+    /// assert_eq!(None, relay.highest_version(&Protocol::Cons.into()));
+    /// ```
+    pub fn highest_version(&self, proto: &UnknownProtocol) -> Option<Version> {
+        self.get(proto)
+            .and_then(|versions| versions.iter().next_back())
+            .map(|&(_, high)| high)
+    }
+
+    /// Get the lowest `Version` of `proto` in this `UnvalidatedProtoEntry`,
+    /// or `None` if it has no versions of `proto`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use protover::*;
+    ///
+    /// let relay: UnvalidatedProtoEntry = "Link=3-5 Wombat=2,7".parse().unwrap();
+    ///
+    /// assert_eq!(Some(3), relay.lowest_version(&Protocol::Link.into()));
+    /// assert_eq!(Some(2), relay.lowest_version(&"Wombat".parse().unwrap()));
+    /// assert_eq!(None, relay.lowest_version(&Protocol::Cons.into()));
+    /// ```
+    pub fn lowest_version(&self, proto: &UnknownProtocol) -> Option<Version> {
+        self.get(proto)
+            .and_then(|versions| versions.iter().next())
+            .map(|&(low, _)| low)
+    }
+
+    /// Set the supported versions of `key` to `value`, replacing any versions
+    /// which were already there.
+    ///
+    /// Like `ProtoEntry::insert()`, an empty `value` removes any existing
+    /// entry for `key`, rather than being stored.
+    pub fn insert(&mut self, key: UnknownProtocol, value: ProtoSet) {
+        if value.is_empty() {
+            self.0.remove(&key);
+        } else {
+            self.0.insert(key, value);
+        }
+    }
+
+    pub fn remove(&mut self, key: &UnknownProtocol) -> Option<ProtoSet> {
+        self.0.remove(key)
+    }
+
+    /// Returns `true` if this `UnvalidatedProtoEntry` does not support any
+    /// versions of any protocol, including when every protocol is listed
+    /// with no versions, like `"Fribble="`.
+    pub fn is_empty(&self) -> bool {
+        self.0.values().all(|versions| versions.is_empty())
+    }
+
+    /// Add a single `version` of `protocol` to this `UnvalidatedProtoEntry`,
+    /// creating an entry for `protocol` if it is not already present.
+    ///
+    /// # Errors
+    ///
+    /// * `ProtoverError::Overlap`: if `version` is 0, and
+    /// * `ProtoverError::ExceedsMax`: if `version` is `u32::MAX`.
+    ///
+    /// In either case, this `UnvalidatedProtoEntry` is unchanged.
+    pub fn add_protocol_version(
+        &mut self,
+        protocol: UnknownProtocol,
+        version: Version,
+    ) -> Result<(), ProtoverError> {
+        match self.0.entry(protocol) {
+            hash_map::Entry::Occupied(entry) => entry.into_mut().insert(version),
+            hash_map::Entry::Vacant(entry) => {
+                let mut versions = ProtoSet::default();
+
+                versions.insert(version)?;
+                entry.insert(versions);
+                Ok(())
+            }
+        }
+    }
+
+    /// Remove a single `version` of `protocol` from this
+    /// `UnvalidatedProtoEntry`.
+    ///
+    /// If that was the last version of `protocol`, the entry for `protocol` is
+    /// removed as well.
+    ///
+    /// # Returns
+    ///
+    /// `true` if `version` of `protocol` was in this `UnvalidatedProtoEntry`;
+    /// `false` otherwise.
+    pub fn remove_protocol_version(
+        &mut self,
+        protocol: &UnknownProtocol,
+        version: Version,
+    ) -> bool {
+        let (removed, now_empty) = match self.0.get_mut(protocol) {
+            Some(versions) => (versions.remove(version), versions.is_empty()),
+            None => return false,
+        };
+
+        if now_empty {
+            self.0.remove(protocol);
+        }
+        removed
+    }
+
+    /// Get a lazy iterator over every individual `(UnknownProtocol, Version)`
+    /// pair in this `UnvalidatedProtoEntry`.
+    ///
+    /// The versions of each protocol are yielded in ascending order, but the
+    /// protocols themselves are in no particular order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use protover::*;
+    ///
+    /// let entry: UnvalidatedProtoEntry = "Fribble=1-2 Link=4".parse().unwrap();
+    /// let mut pairs: Vec<(String, u32)> =
+    ///     entry.flat_iter().map(|(p, v)| (p.to_string(), v)).collect();
+    /// pairs.sort();
+    ///
+    /// assert_eq!(pairs, vec![("Fribble".to_string(), 1),
+    ///                        ("Fribble".to_string(), 2),
+    ///                        ("Link".to_string(), 4)]);
+    /// ```
+    pub fn flat_iter<'a>(&'a self) -> impl Iterator<Item = (&'a UnknownProtocol, Version)> + 'a {
+        self.iter().flat_map(|(protocol, versions)| {
+            versions
+                .iter()
+                .flat_map(move |&(low, high)| (low..high + 1).map(move |v| (protocol, v)))
+        })
+    }
+
+    /// Get the `UnknownProtocol`s in this `UnvalidatedProtoEntry`, sorted
+    /// alphabetically by name.
+    ///
+    /// This sorts the names, so it takes O(n log n) time in the number of
+    /// protocols.
+    pub fn protocol_names(&self) -> Vec<&UnknownProtocol> {
+        let mut names: Vec<&UnknownProtocol> = self.0.keys().collect();
+
+        names.sort_unstable();
+        names
+    }
+
+    /// Get the number of distinct protocols in this `UnvalidatedProtoEntry`.
+    pub fn protocol_count(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Get the total number of individual `Version`s of all the protocols in
+    /// this `UnvalidatedProtoEntry`.
+    pub fn total_version_count(&self) -> usize {
+        let mut total: usize = 0;
+
+        for (_, versions) in self.iter() {
+            total += versions.len();
+        }
+        total
+    }
+
+    /// Get the number of `Version`s of the protocol called `name` in this
+    /// `UnvalidatedProtoEntry`.
+    ///
+    /// # Returns
+    ///
+    /// The number of versions, or 0 if `name` isn't a valid protocol name, or
+    /// isn't in this `UnvalidatedProtoEntry`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use protover::UnvalidatedProtoEntry;
+    ///
+    /// let entry: UnvalidatedProtoEntry = "Fribble=1-2 Link=1-5".parse().unwrap();
+    ///
+    /// assert_eq!(entry.version_count_for("Fribble"), 2);
+    /// assert_eq!(entry.version_count_for("Wombat"), 0);
+    /// assert_eq!(entry.version_count_for("not valid"), 0);
+    /// ```
+    pub fn version_count_for(&self, name: &str) -> usize {
+        match name.parse::<UnknownProtocol>() {
+            Ok(protocol) => self.get_or_empty(&protocol).len(),
+            Err(_) => 0,
+        }
+    }
+
+    /// Get the number of `Version`s of the protocol called `name` in this
+    /// `UnvalidatedProtoEntry`, or 0 if it isn't in this
+    /// `UnvalidatedProtoEntry`.
+    ///
+    /// This is the same as `version_count_for()`, and is named to match
+    /// `ProtoEntry::supported_version_count_for()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use protover::UnvalidatedProtoEntry;
+    ///
+    /// let entry: UnvalidatedProtoEntry = "Fribble=1-2 Link=1-5".parse().unwrap();
+    ///
+    /// assert_eq!(entry.supported_version_count_for("Fribble"), 2);
+    /// assert_eq!(entry.supported_version_count_for("Wombat"), 0);
+    /// assert_eq!(entry.supported_version_count_for(""), 0);
+    /// ```
+    pub fn supported_version_count_for(&self, name: &str) -> usize {
+        self.version_count_for(name)
+    }
+
+    /// Get the `ProtoSet` for the protocol called `name` in this
+    /// `UnvalidatedProtoEntry`, without building an `UnknownProtocol` first.
+    ///
+    /// # Returns
+    ///
+    /// The versions, or `None` if `name` isn't in this
+    /// `UnvalidatedProtoEntry`, or isn't a valid protocol name (see
+    /// `UnknownProtocol::is_valid_format()`).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use protover::UnvalidatedProtoEntry;
+    ///
+    /// let entry: UnvalidatedProtoEntry = "Fribble=1-2 Link=1-5".parse().unwrap();
+    ///
+    /// assert_eq!(entry.get_versions_for("Fribble").unwrap().to_string(), "1-2");
+    /// assert!(entry.get_versions_for("Wombat").is_none());
+    /// assert!(entry.get_versions_for("").is_none());
+    /// ```
+    pub fn get_versions_for(&self, name: &str) -> Option<&ProtoSet> {
+        if !UnknownProtocol::is_valid_format(name) {
+            return None;
+        }
+        self.get(&UnknownProtocol(name.to_string()))
+    }
+
+    /// Determine if this `UnvalidatedProtoEntry` has any versions of the
+    /// protocol called `name`.
+    ///
+    /// Returns `false` if `name` isn't a valid protocol name, or if the
+    /// protocol is listed with no versions, like `"Fribble="`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use protover::UnvalidatedProtoEntry;
+    ///
+    /// let entry: UnvalidatedProtoEntry = "Fribble=1-2 Link=1-5 Wombat=".parse().unwrap();
+    ///
+    /// assert!(entry.contains_protocol("Fribble"));
+    /// assert!(!entry.contains_protocol("Wombat"));
+    /// assert!(!entry.contains_protocol("Cons"));
+    /// assert!(!entry.contains_protocol(""));
+    /// assert!(!entry.contains_protocol(&"a".repeat(1000)));
+    /// ```
+    pub fn contains_protocol(&self, name: &str) -> bool {
+        self.get_versions_for(name)
+            .map_or(false, |versions| !versions.is_empty())
+    }
+
+    /// Determine if the protocol called `name` is listed in this
+    /// `UnvalidatedProtoEntry`, regardless of its versions.
+    ///
+    /// Unlike `contains_protocol()`, protocols listed with no versions, like
+    /// `"Fribble="`, are included.  Returns `false` if `name` isn't a valid
+    /// protocol name.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use protover::UnvalidatedProtoEntry;
+    ///
+    /// let entry: UnvalidatedProtoEntry = "Link=1-5 Wombat=".parse().unwrap();
+    ///
+    /// assert!(entry.has_protocol_named("Link"));
+    /// assert!(entry.has_protocol_named("Wombat"));
+    /// assert!(!entry.has_protocol_named("Cons"));
+    /// assert!(!entry.has_protocol_named(""));
+    /// ```
+    pub fn has_protocol_named(&self, name: &str) -> bool {
+        self.get_versions_for(name).is_some()
+    }
+
+    /// Copy only the protocols called one of `names` in this
+    /// `UnvalidatedProtoEntry` into a new `UnvalidatedProtoEntry`.
+    ///
+    /// Names are compared exactly, so they are case-sensitive.  Listed names
+    /// which aren't in this `UnvalidatedProtoEntry` are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use protover::UnvalidatedProtoEntry;
+    ///
+    /// let entry: UnvalidatedProtoEntry = "Fribble=1-2 Link=1-5 Wombat=9".parse().unwrap();
+    /// let filtered = entry.clone_with_only_names(&["Wombat", "Link", "Cons"]);
+    ///
+    /// assert_eq!(filtered.to_string(), "Link=1-5 Wombat=9");
+    /// ```
+    pub fn clone_with_only_names(&self, names: &[&str]) -> UnvalidatedProtoEntry {
+        let mut filtered = UnvalidatedProtoEntry::default();
+
+        for (protocol, versions) in self.iter() {
+            if names.contains(&protocol.0.as_str()) {
+                filtered.0.insert(protocol.clone(), versions.clone());
+            }
+        }
+        filtered
+    }
+
+    /// Count the individual `(protocol, Version)` pairs in this `UnvalidatedProtoEntry`
+    /// which are also in `other`.
+    ///
+    /// This is a simple compatibility score between two nodes.  It compares
+    /// ranges, rather than expanding every version, so it is fast even for
+    /// very large `ProtoSet`s.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use protover::UnvalidatedProtoEntry;
+    ///
+    /// let ours: UnvalidatedProtoEntry = "Fribble=1-2 Link=1-5".parse().unwrap();
+    /// let theirs: UnvalidatedProtoEntry = "Fribble=2-3 Link=4-9".parse().unwrap();
+    ///
+    /// assert_eq!(ours.count_supported_by(&theirs), 3);
+    /// ```
+    pub fn count_supported_by(&self, other: &UnvalidatedProtoEntry) -> usize {
+        let mut count: usize = 0;
+
+        for (protocol, versions) in self.iter() {
+            if let Some(theirs) = other.get(protocol) {
+                count += versions.len() - versions.and_not_in(theirs).len();
+            }
+        }
+        count
+    }
+
+    /// Get the total number of individual `Version`s of all the protocols in
+    /// this `UnvalidatedProtoEntry`.
+    ///
+    /// Note that `ProtoEntry::len()` counts protocols instead.
+    #[deprecated(note = "use protocol_count() or total_version_count() instead")]
+    pub fn len(&self) -> usize {
+        self.total_version_count()
+    }
+
+    /// Determine if we support every protocol a client supports, and if not,
+    /// determine which protocols we do not have support for.
+    ///
+    /// # Returns
+    ///
+    /// Optionally, return parameters which the client supports but which we do not.
+    ///
+    /// Returns `None` without copying `self` if our own supported protocol
+    /// list can't be parsed.  That list is a constant, so callers never pay
+    /// for a clone of the whole entry: only unsupported protocols are copied.
+    ///
+    /// # Examples
+    /// ```
+    /// use protover::UnvalidatedProtoEntry;
+    ///
+    /// let protocols: UnvalidatedProtoEntry = "LinkAuth=1 Microdesc=1-2 Relay=2".parse().unwrap();
+    /// let unsupported: Option<UnvalidatedProtoEntry> = protocols.all_supported();
+    /// assert_eq!(true, unsupported.is_none());
+    ///
+    /// let protocols: UnvalidatedProtoEntry = "Link=1-2 Wombat=9".parse().unwrap();
+    /// let unsupported: Option<UnvalidatedProtoEntry> = protocols.all_supported();
+    /// assert_eq!(true, unsupported.is_some());
+    /// assert_eq!("Wombat=9", &unsupported.unwrap().to_string());
+    /// ```
+    pub fn all_supported(&self) -> Option<UnvalidatedProtoEntry> {
+        let supported: UnvalidatedProtoEntry = match ProtoEntry::supported() {
+            Ok(x) => x.to_unvalidated(),
+            Err(_) => return None,
+        };
+
+        // Protocols which aren't in the supported list, including unknown
+        // protocols, keep all their versions.
+        let unsupported: UnvalidatedProtoEntry = self.difference(&supported);
+
+        if unsupported.is_empty() {
+            return None;
+        }
+        Some(unsupported)
+    }
+
+    /// Get the versions of each protocol in this `UnvalidatedProtoEntry`
+    /// which are not in `other`.
+    ///
+    /// Protocols which are not in `other` keep all their versions, and
+    /// protocols which are left with no versions are omitted.  This is also
+    /// available as the `-` operator.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use protover::UnvalidatedProtoEntry;
+    ///
+    /// let ours: UnvalidatedProtoEntry = "Link=1-5 Relay=1-2 Wombat=9".parse().unwrap();
+    /// let theirs: UnvalidatedProtoEntry = "Link=2-3 Relay=1-4".parse().unwrap();
+    ///
+    /// assert_eq!("Link=1,4-5 Wombat=9", ours.difference(&theirs).to_string());
+    /// assert_eq!(ours.difference(&theirs), &ours - &theirs);
+    /// ```
+    pub fn difference(&self, other: &UnvalidatedProtoEntry) -> UnvalidatedProtoEntry {
+        let mut difference: UnvalidatedProtoEntry = UnvalidatedProtoEntry::default();
+
+        for (protocol, versions) in self.iter() {
+            let remaining: ProtoSet = match other.get(protocol) {
+                Some(theirs) => versions.and_not_in(theirs),
+                None => versions.clone(),
+            };
+
+            if !remaining.is_empty() {
+                difference.insert(protocol.clone(), remaining);
+            }
+        }
+        difference
+    }
+
+    /// Determine if we have support for some protocol and version.
+    ///
+    /// # Inputs
+    ///
+    /// * `proto`, an `UnknownProtocol` to test support for
+    /// * `vers`, a `Version` which we will go on to determine whether the
+    /// specified protocol supports.
+    ///
+    /// # Return
+    ///
+    /// Returns `true` iff this `UnvalidatedProtoEntry` includes support for the
+    /// indicated protocol and version, and `false` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::str::FromStr;
+    /// use protover::*;
+    /// # use protover::errors::ProtoverError;
+    ///
+    /// # fn do_test () -> Result<UnvalidatedProtoEntry, ProtoverError> {
+    /// let proto: UnvalidatedProtoEntry = "Link=3-4 Cons=1 Doggo=3-5".parse()?;
+    /// assert_eq!(true, proto.supports_protocol(&Protocol::Cons.into(), &1));
+    /// assert_eq!(false, proto.supports_protocol(&Protocol::Cons.into(), &5));
+    /// assert_eq!(true, proto.supports_protocol(&UnknownProtocol::from_str("Doggo")?, &4));
+    /// # Ok(proto)
+    /// # } fn main () { do_test(); }
+    /// ```
+    pub fn supports_protocol(&self, proto: &UnknownProtocol, vers: &Version) -> bool {
+        let supported_versions: &ProtoSet = match self.get(proto) {
+            Some(n) => n,
+            None => return false,
+        };
+        supported_versions.contains(&vers)
+    }
+
+    /// As `UnvalidatedProtoEntry::supports_protocol()`, but also returns `true`
+    /// if any later version of the protocol is supported.
+    ///
+    /// # Examples
+    /// ```
+    /// use protover::*;
+    /// # use protover::errors::ProtoverError;
+    ///
+    /// # fn do_test () -> Result<UnvalidatedProtoEntry, ProtoverError> {
+    /// let proto: UnvalidatedProtoEntry = "Link=3-4 Cons=5".parse()?;
+    ///
+    /// assert_eq!(true, proto.supports_protocol_or_later(&Protocol::Cons.into(), &5));
+    /// assert_eq!(true, proto.supports_protocol_or_later(&Protocol::Cons.into(), &4));
+    /// assert_eq!(false, proto.supports_protocol_or_later(&Protocol::Cons.into(), &6));
+    /// # Ok(proto)
+    /// # } fn main () { do_test(); }
+    /// ```
+    pub fn supports_protocol_or_later(&self, proto: &UnknownProtocol, vers: &Version) -> bool {
+        let supported_versions: &ProtoSet = match self.get(&proto) {
+            Some(n) => n,
+            None => return false,
+        };
+        supported_versions.iter().any(|v| v.1 >= *vers)
+    }
+
+    /// Split a string containing (potentially) several protocols and their
+    /// versions into a `Vec` of tuples of string in `(protocol, versions)`
+    /// form.
+    ///
+    /// # Inputs
+    ///
+    /// A &str in the form `"Link=3-4 Cons=5"`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` whose `Ok` variant is a `Vec<(&str, &str)>` of `(protocol,
+    /// versions)`, or whose `Err` variant is a `ProtoverError`.
+    ///
+    /// # Errors
+    ///
+    /// This will error with a `ProtoverError::Unparseable` if any of the
+    /// following are true:
+    ///
+    /// * If a protocol name is an empty string, e.g. `"Cons=1,3 =3-5"`.
+    /// * If an entry has no equals sign, e.g. `"Cons=1,3 Desc"`.
+    /// * If there is leading or trailing whitespace, e.g. `" Cons=1,3 Link=3"`.
+    /// * If there is any other extra whitespice, e.g. `"Cons=1,3  Link=3"`.
+    fn parse_protocol_and_version_str<'a>(
+        protocol_string: &'a str,
+    ) -> Result<Vec<(&'a str, &'a str)>, ProtoverError> {
+        let mut protovers: Vec<(&str, &str)> = Vec::new();
+
+        for subproto in protocol_string.split(' ') {
+            let mut parts = subproto.splitn(2, '=');
+
+            let name = match parts.next() {
+                Some("") => return Err(ProtoverError::Unparseable),
+                Some(n) => n,
+                None => return Err(ProtoverError::Unparseable),
+            };
+            let vers = match parts.next() {
+                Some(n) => n,
+                None => return Err(ProtoverError::Unparseable),
+            };
+            protovers.push((name, vers));
+        }
+        Ok(protovers)
+    }
+
+    /// Split a single `"protocol=versions"` string into its protocol name and
+    /// versions string.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtoverError::Unparseable` if `protocol_string` does not
+    /// contain exactly one `"protocol=versions"` pair.
+    fn parse_single_protocol_and_version(
+        protocol_string: &str,
+    ) -> Result<(&str, &str), ProtoverError> {
+        let parts: Vec<(&str, &str)> =
+            UnvalidatedProtoEntry::parse_protocol_and_version_str(protocol_string)?;
+
+        match parts.as_slice() {
+            &[pair] => Ok(pair),
+            _ => Err(ProtoverError::Unparseable),
+        }
+    }
+}
+
+impl IntoIterator for UnvalidatedProtoEntry {
+    type Item = (UnknownProtocol, ProtoSet);
+    type IntoIter = hash_map::IntoIter<UnknownProtocol, ProtoSet>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a UnvalidatedProtoEntry {
+    type Item = (&'a UnknownProtocol, &'a ProtoSet);
+    type IntoIter = hash_map::Iter<'a, UnknownProtocol, ProtoSet>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl FromStr for UnvalidatedProtoEntry {
+    type Err = ProtoverError;
+
+    /// Parses a protocol list without validating the protocol names.
+    ///
+    /// # Inputs
+    ///
+    /// * `protocol_string`, a string comprised of keys and values, both which are
+    /// strings. The keys are the protocol names while values are a string
+    /// representation of the supported versions.
+    ///
+    /// The input is _not_ expected to be a subset of the Protocol types
+    ///
+    /// # Returns
+    ///
+    /// A `Result` whose `Ok` value is an `UnvalidatedProtoEntry`.
+    ///
+    /// The returned `Result`'s `Err` value is an `ProtoverError`.
+    ///
+    /// # Errors
+    ///
+    /// This function will error if:
+    ///
+    /// * The protocol string does not follow the "protocol_name=version_list"
+    ///   expected format, or
+    /// * If the version string is malformed. See `impl FromStr for ProtoSet`.
+    fn from_str(protocol_string: &str) -> Result<UnvalidatedProtoEntry, ProtoverError> {
+        let mut parsed: UnvalidatedProtoEntry = UnvalidatedProtoEntry::default();
+        let parts: Vec<(&str, &str)> =
+            UnvalidatedProtoEntry::parse_protocol_and_version_str(protocol_string)?;
+
+        for &(name, vers) in parts.iter() {
+            let versions = ProtoSet::from_str(vers)?;
+            let protocol = UnknownProtocol::from_str(name)?;
+
+            parsed.0.insert(protocol, versions);
+        }
+        Ok(parsed)
+    }
+}
+
+impl UnvalidatedProtoEntry {
+    /// Parse a protocol list like `UnvalidatedProtoEntry::from_str()`, but if
+    /// a protocol is listed more than once, take the union of all its versions
+    /// rather than just the last ones.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use protover::UnvalidatedProtoEntry;
+    ///
+    /// let entry = UnvalidatedProtoEntry::from_str_merge_duplicates("Link=1-3 Link=4-5").unwrap();
+    /// assert_eq!("Link=1-5", entry.to_string());
+    /// ```
+    pub fn from_str_merge_duplicates(
+        protocol_string: &str,
+    ) -> Result<UnvalidatedProtoEntry, ProtoverError> {
+        let mut parsed: UnvalidatedProtoEntry = UnvalidatedProtoEntry::default();
+        let parts: Vec<(&str, &str)> =
+            UnvalidatedProtoEntry::parse_protocol_and_version_str(protocol_string)?;
+
+        for &(name, vers) in parts.iter() {
+            let versions = ProtoSet::from_str(vers)?;
+            let protocol = UnknownProtocol::from_str(name)?;
+
+            let merged: ProtoSet = match parsed.get(&protocol) {
+                Some(existing) => existing.union(&versions),
+                None => versions,
+            };
+            parsed.0.insert(protocol, merged);
+        }
+        Ok(parsed)
+    }
+
+    /// Build an `UnvalidatedProtoEntry` from separate `"protocol=versions"`
+    /// strings, like `["Fribble=1-2", "Link=1-5"]`.
+    ///
+    /// Protocols with no versions, like `"Fribble="`, are left out.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtoverError::Unparseable` if a string is not a single
+    /// `"protocol=versions"` pair, or if a protocol is listed more than once.
+    /// Otherwise, returns the same errors as `UnvalidatedProtoEntry::from_str()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use protover::UnvalidatedProtoEntry;
+    ///
+    /// let entry = UnvalidatedProtoEntry::from_slice_of_strings(&["Link=1-5", "Fribble=1-2"]);
+    ///
+    /// assert_eq!("Fribble=1-2 Link=1-5", entry.unwrap().to_string());
+    /// ```
+    pub fn from_slice_of_strings(strs: &[&str]) -> Result<UnvalidatedProtoEntry, ProtoverError> {
+        let mut parsed: UnvalidatedProtoEntry = UnvalidatedProtoEntry::default();
+
+        for string in strs {
+            let (name, vers) = UnvalidatedProtoEntry::parse_single_protocol_and_version(string)?;
+            let versions = ProtoSet::from_str(vers)?;
+            let protocol = UnknownProtocol::from_str(name)?;
+
+            if parsed.0.contains_key(&protocol) {
+                return Err(ProtoverError::Unparseable);
+            }
+            parsed.0.insert(protocol, versions);
+        }
+        // Duplicate checks need every protocol, so remove the empty ones last.
+        parsed.0.retain(|_, versions| !versions.is_empty());
+        Ok(parsed)
+    }
+
+    /// Compact the versions of every protocol in this `UnvalidatedProtoEntry`,
+    /// so that each is stored using as few ranges as possible.
+    ///
+    /// Each protocol can only appear once, so there are never any duplicate
+    /// protocols to merge.  See `ProtoSet::normalize()`.
+    pub fn normalize(&mut self) {
+        for versions in self.0.values_mut() {
+            versions.normalize();
+        }
+    }
+
+    /// Create an `UnknownProtocol`, ignoring whether or not it
+    /// exceeds MAX_PROTOCOL_NAME_LENGTH.
+    pub(crate) fn from_str_any_len(
+        protocol_string: &str,
+    ) -> Result<UnvalidatedProtoEntry, ProtoverError> {
+        let mut parsed: UnvalidatedProtoEntry = UnvalidatedProtoEntry::default();
+        let parts: Vec<(&str, &str)> =
+            UnvalidatedProtoEntry::parse_protocol_and_version_str(protocol_string)?;
+
+        for &(name, vers) in parts.iter() {
+            let versions = ProtoSet::from_str(vers)?;
+            let protocol = UnknownProtocol::from_str_any_len(name)?;
+
+            parsed.0.insert(protocol, versions);
+        }
+        Ok(parsed)
+    }
+}
+
+/// Pretend a `ProtoEntry` is actually an `UnvalidatedProtoEntry`.
+impl From<ProtoEntry> for UnvalidatedProtoEntry {
+    fn from(proto_entry: ProtoEntry) -> UnvalidatedProtoEntry {
+        UnvalidatedProtoEntry::from(&proto_entry)
+    }
+}
+
+/// Pretend a borrowed `ProtoEntry` is actually an `UnvalidatedProtoEntry`,
+/// cloning its protocols and versions.
+impl<'a> From<&'a ProtoEntry> for UnvalidatedProtoEntry {
+    fn from(proto_entry: &'a ProtoEntry) -> UnvalidatedProtoEntry {
+        let mut unvalidated: UnvalidatedProtoEntry = UnvalidatedProtoEntry::default();
+
+        for (protocol, versions) in proto_entry.iter() {
+            unvalidated
+                .0
+                .insert(UnknownProtocol::from(protocol.clone()), versions.clone());
+        }
+        unvalidated
+    }
+}
+
+/// Subtract the versions in one `UnvalidatedProtoEntry` from another.  See
+/// `UnvalidatedProtoEntry::difference()`.
+impl Sub<&UnvalidatedProtoEntry> for &UnvalidatedProtoEntry {
+    type Output = UnvalidatedProtoEntry;
+
+    fn sub(self, other: &UnvalidatedProtoEntry) -> UnvalidatedProtoEntry {
+        self.difference(other)
+    }
+}
+
+/// Compare a `ProtoEntry` with an `UnvalidatedProtoEntry`, as if the
+/// `ProtoEntry` were also unvalidated.
+impl PartialEq<UnvalidatedProtoEntry> for ProtoEntry {
+    fn eq(&self, other: &UnvalidatedProtoEntry) -> bool {
+        self.to_unvalidated() == *other
+    }
+}
+
+/// Compare an `UnvalidatedProtoEntry` with a `ProtoEntry`, as if the
+/// `ProtoEntry` were also unvalidated.
+impl PartialEq<ProtoEntry> for UnvalidatedProtoEntry {
+    fn eq(&self, other: &ProtoEntry) -> bool {
+        *self == other.to_unvalidated()
+    }
+}
+
+/// Builds a `ProtoEntry` one protocol at a time.
+///
+/// If a protocol is added more than once, its versions are merged, like
+/// `ProtoEntry::merge()`.
+///
+/// # Examples
+///
+/// ```
+/// use protover::*;
+///
+/// let entry: ProtoEntry = ProtoEntryBuilder::new()
+///     .add(Protocol::Link, "1-3".parse().unwrap())
+///     .add(Protocol::Cons, "1-2".parse().unwrap())
+///     .add(Protocol::Link, "4-5".parse().unwrap())
+///     .build();
+///
+/// assert_eq!(entry.to_string(), "Cons=1-2 Link=1-5");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ProtoEntryBuilder {
+    entry: ProtoEntry,
+}
+
+impl ProtoEntryBuilder {
+    /// Create a builder for an empty `ProtoEntry`.
+    pub fn new() -> Self {
+        ProtoEntryBuilder::default()
+    }
+
+    /// Add `versions` of `protocol`, merging them with any versions of
+    /// `protocol` which were already added.
+    ///
+    /// Adding an empty `versions` does nothing.
+    pub fn add(&mut self, protocol: Protocol, versions: ProtoSet) -> &mut Self {
+        merge_versions(&mut self.entry.0, protocol, versions);
+        self
+    }
+
+    /// Get a `ProtoEntry` containing all the protocols added so far.
+    pub fn build(&self) -> ProtoEntry {
+        self.entry.clone()
+    }
+}
+
+/// Builds an `UnvalidatedProtoEntry` one protocol at a time.
+///
+/// If a protocol is added more than once, its versions are merged.
+///
+/// # Examples
+///
+/// ```
+/// use protover::*;
+///
+/// let entry: UnvalidatedProtoEntry = UnvalidatedProtoEntryBuilder::new()
+///     .add("Wombat".parse().unwrap(), "9".parse().unwrap())
+///     .add(Protocol::Link.into(), "1-5".parse().unwrap())
+///     .add("Wombat".parse().unwrap(), "7".parse().unwrap())
+///     .build();
+///
+/// assert_eq!(entry.to_string(), "Link=1-5 Wombat=7,9");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct UnvalidatedProtoEntryBuilder {
+    entry: UnvalidatedProtoEntry,
+}
+
+impl UnvalidatedProtoEntryBuilder {
+    /// Create a builder for an empty `UnvalidatedProtoEntry`.
+    pub fn new() -> Self {
+        UnvalidatedProtoEntryBuilder::default()
+    }
+
+    /// Add `versions` of `protocol`, merging them with any versions of
+    /// `protocol` which were already added.
+    ///
+    /// Adding an empty `versions` does nothing.
+    pub fn add(&mut self, protocol: UnknownProtocol, versions: ProtoSet) -> &mut Self {
+        merge_versions(&mut self.entry.0, protocol, versions);
+        self
+    }
+
+    /// Get an `UnvalidatedProtoEntry` containing all the protocols added so
+    /// far.
+    pub fn build(&self) -> UnvalidatedProtoEntry {
+        self.entry.clone()
+    }
+}
+
+/// A mapping of protocols to a count of how many times each of their `Version`s
+/// were voted for or supported.
+///
+/// # Warning
+///
+/// The "protocols" are *not* guaranteed to be known/supported `Protocol`s, in
+/// order to allow new subprotocols to be introduced even if Directory
+/// Authorities don't yet know of them.
+///
+/// The protocols and versions are kept in sorted order, so that voting is
+/// deterministic.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProtoverVote(BTreeMap<UnknownProtocol, BTreeMap<Version, usize>>);
+
+impl Default for ProtoverVote {
+    fn default() -> ProtoverVote {
+        ProtoverVote(BTreeMap::new())
+    }
+}
+
+/// Format a `ProtoverVote` as each of its protocols, sorted by name, followed by
+/// that protocol's `version->count` pairs, sorted by version.
+///
+/// For example, `"Cons: 1->3; Link: 3->2, 4->1"`.  This is not a Tor wire
+/// format, but it is stable, and it can be parsed back with `FromStr`.
+impl fmt::Display for ProtoverVote {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut parts: Vec<String> = Vec::new();
+
+        // The protocols and versions are already sorted.
+        for (protocol, counts) in self.0.iter() {
+            let counts: Vec<String> = counts
+                .iter()
+                .map(|(version, count)| format!("{}->{}", version, count))
+                .collect();
+
+            parts.push(format!("{}: {}", protocol, counts.join(", ")));
+        }
+
+        write!(f, "{}", parts.join("; "))
+    }
+}
+
+impl FromStr for ProtoverVote {
+    type Err = ProtoverError;
+
+    /// Parse a `ProtoverVote` from the format produced by its `Display`
+    /// implementation.
+    ///
+    /// # Errors
+    ///
+    /// This function will error with a `ProtoverError::Unparseable` if the
+    /// string is malformed, or if a protocol or a version within a protocol is
+    /// listed more than once.  Invalid protocol names produce the same errors
+    /// as `UnknownProtocol::from_str()`.
+    fn from_str(vote_string: &str) -> Result<ProtoverVote, ProtoverError> {
+        let mut vote: ProtoverVote = ProtoverVote::default();
+
+        if vote_string.is_empty() {
+            return Ok(vote);
+        }
+
+        for part in vote_string.split("; ") {
+            let mut name_and_counts = part.splitn(2, ": ");
+
+            let name = match name_and_counts.next() {
+                Some(n) => n,
+                None => return Err(ProtoverError::Unparseable),
+            };
+            let counts_string = match name_and_counts.next() {
+                Some(n) => n,
+                None => return Err(ProtoverError::Unparseable),
+            };
+            let protocol: UnknownProtocol = name.parse()?;
+            let mut counts: BTreeMap<Version, usize> = BTreeMap::new();
+
+            if !counts_string.is_empty() {
+                for pair in counts_string.split(", ") {
+                    let mut version_and_count = pair.splitn(2, "->");
+
+                    let version: Version = match version_and_count.next() {
+                        Some(n) => n.parse().or(Err(ProtoverError::Unparseable))?,
+                        None => return Err(ProtoverError::Unparseable),
+                    };
+                    let count: usize = match version_and_count.next() {
+                        Some(n) => n.parse().or(Err(ProtoverError::Unparseable))?,
+                        None => return Err(ProtoverError::Unparseable),
+                    };
+                    if counts.insert(version, count).is_some() {
+                        return Err(ProtoverError::Unparseable);
+                    }
+                }
+            }
+            if vote.0.insert(protocol, counts).is_some() {
+                return Err(ProtoverError::Unparseable);
+            }
+        }
+        Ok(vote)
+    }
+}
+
+impl IntoIterator for ProtoverVote {
+    type Item = (UnknownProtocol, BTreeMap<Version, usize>);
+    type IntoIter = btree_map::IntoIter<UnknownProtocol, BTreeMap<Version, usize>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl ProtoverVote {
+    pub fn entry(
+        &mut self,
+        key: UnknownProtocol,
+    ) -> btree_map::Entry<UnknownProtocol, BTreeMap<Version, usize>> {
+        self.0.entry(key)
+    }
+
+    /// Count how many of the `proto_entries` voted for each `Version` of each
+    /// protocol.
+    ///
+    /// This is the first half of `ProtoverVote::compute()`, before any
+    /// threshold is applied.  The counts can be inspected with
+    /// `ProtoverVote::tally()`, for example to understand why a version only
+    /// just missed the threshold.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use protover::ProtoverVote;
+    /// use protover::Protocol;
+    /// use protover::UnvalidatedProtoEntry;
+    ///
+    /// let protos: &[UnvalidatedProtoEntry] = &["Link=3-4".parse().unwrap(),
+    ///                                          "Link=3".parse().unwrap()];
+    /// let tally = ProtoverVote::compute_votes_per_version(protos).tally();
+    /// let link = &tally[&Protocol::Link.into()];
+    ///
+    /// assert_eq!(Some(&2), link.get(&3));
+    /// assert_eq!(Some(&1), link.get(&4));
+    /// ```
+    pub fn compute_votes_per_version(proto_entries: &[UnvalidatedProtoEntry]) -> ProtoverVote {
+        let mut all_count: ProtoverVote = ProtoverVote::default();
+
+        // parse and collect all of the protos and their versions and collect them
+        for vote in proto_entries {
+            all_count.add_vote(vote);
+        }
+        all_count
+    }
+
+    /// Add the versions in a single `vote` to the counts in this
+    /// `ProtoverVote`.
+    ///
+    /// This allows votes to be counted as they arrive, rather than all at
+    /// once.  Use `ProtoverVote::finish()` to apply a threshold to the counts.
+    ///
+    /// Votes which would expand to more than `MAX_PROTOCOLS_TO_EXPAND`
+    /// versions are ignored, just like in `ProtoverVote::compute()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use protover::ProtoverVote;
+    ///
+    /// let mut vote = ProtoverVote::default();
+    ///
+    /// vote.add_vote(&"Link=3-4".parse().unwrap());
+    /// vote.add_vote(&"Link=3".parse().unwrap());
+    ///
+    /// assert_eq!("Link=3", vote.finish(2).to_string());
+    /// ```
+    pub fn add_vote(&mut self, vote: &UnvalidatedProtoEntry) {
+        // C_RUST_DIFFERS: This doesn't actually differ, bu this check on
+        // the total is here to make it match.  Because the C version calls
+        // expand_protocol_list() which checks if there would be too many
+        // subprotocols *or* individual version numbers, i.e. more than
+        // MAX_PROTOCOLS_TO_EXPAND, and does this *per vote*, we need to
+        // match it's behaviour and ensure we're not allowing more than it
+        // would.
+        if vote.total_version_count() > MAX_PROTOCOLS_TO_EXPAND {
+            return;
+        }
+
+        for (protocol, versions) in vote.iter() {
+            // Only clone the protocol name the first time it is voted for.
+            if !self.0.contains_key(protocol) {
+                self.0.insert(protocol.clone(), BTreeMap::new());
+            }
+            let supported_vers: &mut BTreeMap<Version, usize> = match self.0.get_mut(protocol) {
+                Some(counts) => counts,
+                None => continue,
+            };
+
+            // The total check above limits the number of versions, so we can
+            // count them straight from the ranges, without expanding them.
+            for &(low, high) in versions.iter() {
+                for version in low..=high {
+                    let counter: &mut usize = supported_vers.entry(version).or_insert(0);
+                    *counter += 1;
+                }
+            }
+        }
+    }
+
+    /// Return a new `UnvalidatedProtoEntry` encoding all of the protocol
+    /// versions which have been voted for at least `threshold` times so far.
+    ///
+    /// This is the second half of `ProtoverVote::compute()`.  The counts in
+    /// this `ProtoverVote` are left intact, so more votes can be added later.
+    pub fn finish(&self, threshold: usize) -> UnvalidatedProtoEntry {
+        let mut final_output: UnvalidatedProtoEntry = UnvalidatedProtoEntry::default();
+
+        for (protocol, versions) in self.0.iter() {
+            // Go through and remove versions that are less than the threshold.
+            // The counts are sorted by version, so the rest can be contracted
+            // into ranges without sorting them again.
+            let voted_versions = versions
+                .iter()
+                .filter(|&(_, &count)| count >= threshold)
+                .map(|(&version, _)| version);
+            let voted_protoset: ProtoSet =
+                ProtoSet::from_sorted_versions(voted_versions).unwrap_or_default();
+
+            // insert() skips empty sets.
+            final_output.insert(protocol.clone(), voted_protoset);
+        }
+        final_output
+    }
+
+    /// Clear all the votes counted so far, so that this `ProtoverVote` can
+    /// be reused for a new voting round.
+    ///
+    /// This is equivalent to `*self = ProtoverVote::default()`.  After a
+    /// reset, `finish()` returns an empty `UnvalidatedProtoEntry`, until more
+    /// votes are added.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use protover::ProtoverVote;
+    ///
+    /// let mut vote = ProtoverVote::default();
+    ///
+    /// vote.add_vote(&"Link=3-4".parse().unwrap());
+    /// vote.reset();
+    ///
+    /// assert!(vote.finish(1).is_empty());
+    /// ```
+    pub fn reset(&mut self) {
+        self.0.clear();
+    }
+
+    /// Get a snapshot of the number of votes each `Version` of each protocol
+    /// has received so far.
+    ///
+    /// The protocols and versions are sorted.
+    pub fn tally(&self) -> BTreeMap<UnknownProtocol, BTreeMap<Version, usize>> {
+        self.0.clone()
+    }
+
+    /// Protocol voting implementation.
+    ///
+    /// Given a slice of `UnvalidatedProtoEntry`s and a vote `threshold`, return
+    /// a new `UnvalidatedProtoEntry` encoding all of the protocols that are
+    /// listed by at least `threshold` of the inputs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use protover::ProtoverVote;
+    /// use protover::UnvalidatedProtoEntry;
+    ///
+    /// let protos: &[UnvalidatedProtoEntry] = &["Link=3-4".parse().unwrap(),
+    ///                                          "Link=3".parse().unwrap()];
+    /// let vote = ProtoverVote::compute(protos, &2);
+    /// assert_eq!("Link=3", vote.to_string());
+    /// ```
+    // C_RUST_COUPLED: protover.c protover_compute_vote
+    pub fn compute(
+        proto_entries: &[UnvalidatedProtoEntry],
+        threshold: &usize,
+    ) -> UnvalidatedProtoEntry {
+        if proto_entries.is_empty() {
+            return UnvalidatedProtoEntry::default();
+        }
+
+        ProtoverVote::compute_votes_per_version(proto_entries).finish(*threshold)
+    }
+
+    /// Protocol voting, ignoring some protocols.
+    ///
+    /// This is `ProtoverVote::compute()`, but any protocols in `exclude` are
+    /// removed from the result, no matter how many votes they received.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use protover::ProtoverVote;
+    /// use protover::UnknownProtocol;
+    /// use protover::UnvalidatedProtoEntry;
+    ///
+    /// let protos: &[UnvalidatedProtoEntry] = &["Link=3-4 Fribble=1".parse().unwrap(),
+    ///                                          "Link=3 Fribble=1".parse().unwrap()];
+    /// let exclude: &[UnknownProtocol] = &["Fribble".parse().unwrap()];
+    /// let vote = ProtoverVote::compute_with_exclusion(protos, 2, exclude);
+    /// assert_eq!("Link=3", vote.to_string());
+    /// ```
+    pub fn compute_with_exclusion(
+        proto_entries: &[UnvalidatedProtoEntry],
+        threshold: usize,
+        exclude: &[UnknownProtocol],
+    ) -> UnvalidatedProtoEntry {
+        let mut final_output: UnvalidatedProtoEntry =
+            ProtoverVote::compute(proto_entries, &threshold);
+
+        for protocol in exclude {
+            final_output.remove(protocol);
+        }
+        final_output
+    }
+
+    /// Compute a unanimous vote: return a new `UnvalidatedProtoEntry` encoding
+    /// only the protocol versions which are listed by every one of the
+    /// `proto_entries`.
+    ///
+    /// This is `ProtoverVote::compute()` with a threshold of
+    /// `proto_entries.len()`.  If `proto_entries` is empty, the result is
+    /// empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use protover::ProtoverVote;
+    /// use protover::UnvalidatedProtoEntry;
+    ///
+    /// let protos: &[UnvalidatedProtoEntry] = &["Link=3-5 Cons=1".parse().unwrap(),
+    ///                                          "Link=4-6".parse().unwrap()];
+    /// let vote = ProtoverVote::compute_minimum(protos);
+    /// assert_eq!("Link=4-5", vote.to_string());
+    /// ```
+    pub fn compute_minimum(proto_entries: &[UnvalidatedProtoEntry]) -> UnvalidatedProtoEntry {
+        ProtoverVote::compute(proto_entries, &proto_entries.len())
+    }
+
+    /// Protocol voting, with a guaranteed minimum result.
+    ///
+    /// This is `ProtoverVote::compute()`, but every protocol version in
+    /// `floor` is added to the result, even if no entries voted for it.
+    /// Protocols which are in `floor` with no versions are left out.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use protover::ProtoverVote;
+    /// use protover::UnvalidatedProtoEntry;
+    ///
+    /// let protos: &[UnvalidatedProtoEntry] = &["Link=3-4".parse().unwrap(),
+    ///                                          "Link=3".parse().unwrap()];
+    /// let floor: UnvalidatedProtoEntry = "Cons=1 Link=1-2".parse().unwrap();
+    /// let vote = ProtoverVote::compute_with_floor(protos, 2, &floor);
+    /// assert_eq!("Cons=1 Link=1-3", vote.to_string());
+    /// ```
+    pub fn compute_with_floor(
+        proto_entries: &[UnvalidatedProtoEntry],
+        threshold: usize,
+        floor: &UnvalidatedProtoEntry,
+    ) -> UnvalidatedProtoEntry {
+        let mut final_output: UnvalidatedProtoEntry =
+            ProtoverVote::compute(proto_entries, &threshold);
+
+        for (protocol, versions) in floor.iter() {
+            merge_versions(&mut final_output.0, protocol.clone(), versions.clone());
+        }
+        final_output
+    }
+}
+
+/// Returns a boolean indicating whether the given protocol and version is
+/// supported in any of the existing Tor protocols
+///
+/// # Examples
+/// ```
+/// use protover::is_supported_here;
+/// use protover::Protocol;
+///
+/// let is_supported = is_supported_here(&Protocol::Link, &10);
+/// assert_eq!(false, is_supported);
+///
+/// let is_supported = is_supported_here(&Protocol::Link, &1);
+/// assert_eq!(true, is_supported);
+/// ```
+pub fn is_supported_here(proto: &Protocol, vers: &Version) -> bool {
+    let currently_supported: ProtoEntry = match ProtoEntry::supported() {
+        Ok(result) => result,
+        Err(_) => return false,
+    };
+    let supported_versions = match currently_supported.get(proto) {
+        Some(n) => n,
+        None => return false,
+    };
+    supported_versions.contains(vers)
+}
+
+/// Since older versions of Tor cannot infer their own subprotocols,
+/// determine which subprotocols are supported by older Tor versions.
+///
+/// # Inputs
+///
+/// * `version`, a string comprised of "[0-9a-z.-]"
+///
+/// # Returns
+///
+/// A `&'static CStr` encoding a list of protocol names and supported
+/// versions. The string takes the following format:
+///
+/// "HSDir=1-1 LinkAuth=1"
+///
+/// This function returns the protocols that are supported by the version input,
+/// only for tor versions older than `FIRST_TOR_VERSION_TO_ADVERTISE_PROTOCOLS`
+/// (but not older than 0.2.4.19).  For newer tors (or older than 0.2.4.19), it
+/// returns an empty string.
+///
+/// # Note
+///
+/// This function is meant to be called for/within FFI code.  If you'd
+/// like to use this code in Rust, please see `compute_for_old_tor()`.
+//
+// C_RUST_COUPLED: src/rust/protover.c `compute_for_old_tor`
+pub(crate) fn compute_for_old_tor_cstr(version: &str) -> &'static CStr {
+    for &(cutoff, protocols) in old_tor_protocols().iter() {
+        if tor_version_as_new_as(version, cutoff) {
+            return protocols;
+        }
+    }
+    // Tors older than the oldest protocols support nothing we know.
+    cstr!("")
+}
+
+/// Get the protocols guessed for old Tor versions, for
+/// `compute_for_old_tor_cstr()`.
+///
+/// Each row is a cutoff Tor version, and the protocols supported by Tors at
+/// least as new as that version.  Rows are newest first, so the first cutoff
+/// that a version is as new as wins.
+fn old_tor_protocols() -> [(&'static str, &'static CStr); 4] {
+    [
+        (FIRST_TOR_VERSION_TO_ADVERTISE_PROTOCOLS, cstr!("")),
+        (
+            "0.2.9.1-alpha",
+            cstr!(
+                "Cons=1-2 Desc=1-2 DirCache=1 HSDir=1 HSIntro=3 HSRend=1-2 \
+                 Link=1-4 LinkAuth=1 Microdesc=1-2 Relay=1-2"
+            ),
+        ),
+        (
+            "0.2.7.5",
+            cstr!(
+                "Cons=1-2 Desc=1-2 DirCache=1 HSDir=1 HSIntro=3 HSRend=1 \
+                 Link=1-4 LinkAuth=1 Microdesc=1-2 Relay=1-2"
+            ),
+        ),
+        (
+            OLDEST_TOR_VERSION_TO_GUESS_PROTOCOLS,
+            cstr!(
+                "Cons=1 Desc=1 DirCache=1 HSDir=1 HSIntro=3 HSRend=1 \
+                 Link=1-4 LinkAuth=1 Microdesc=1 Relay=1-2"
+            ),
+        ),
+    ]
+}
+
+/// Since older versions of Tor cannot infer their own subprotocols,
+/// determine which subprotocols are supported by older Tor versions.
+///
+/// # Inputs
+///
+/// * `version`, a string comprised of "[0-9a-z.-]"
+///
+/// # Returns
+///
+/// A `Result` whose `Ok` value is an `&'static str` encoding a list of protocol
+/// names and supported versions. The string takes the following format:
+///
+/// "HSDir=1-1 LinkAuth=1"
+///
+/// This function returns the protocols that are supported by the version input,
+/// only for tor versions older than `FIRST_TOR_VERSION_TO_ADVERTISE_PROTOCOLS`.
+/// (but not older than 0.2.4.19).  For newer tors (or older than 0.2.4.19), its
+/// `Ok` `Result` contains an empty string.
+///
+/// Otherwise, its `Err` contains a `ProtoverError::Unparseable` if the
+/// `version` string was invalid utf-8.
+///
+/// # Note
+///
+/// This function is meant to be called for/within non-FFI Rust code.
+//
+// C_RUST_COUPLED: src/rust/protover.c `compute_for_old_tor`
+pub fn compute_for_old_tor(version: &str) -> Result<&'static str, ProtoverError> {
+    // .to_str() fails with a Utf8Error if it couldn't validate the
+    // utf-8, so convert that here into an Unparseable ProtoverError.
+    compute_for_old_tor_cstr(version)
+        .to_str()
+        .or(Err(ProtoverError::Unparseable))
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+    use std::string::ToString;
+    use std::u32;
+
+    use super::*;
+
+    macro_rules! parse_proto {
+        ($e:expr) => {{
+            let proto: Result<UnknownProtocol, _> = $e.parse();
+            let proto2 = UnknownProtocol::from_str_any_len($e);
+            assert_eq!(proto, proto2);
+            proto
+        }};
+    }
+
+    #[test]
+    fn test_protocol_from_str() {
+        assert!(parse_proto!("Cons").is_ok());
+        assert!(parse_proto!("123").is_ok());
+        assert!(parse_proto!("1-2-3").is_ok());
+
+        let err = Err(ProtoverError::InvalidProtocol);
+        assert_eq!(err, parse_proto!("a_b_c"));
+        assert_eq!(err, parse_proto!("a b"));
+        assert_eq!(err, parse_proto!("a,"));
+        assert_eq!(err, parse_proto!("b."));
+        assert_eq!(err, parse_proto!("é"));
+    }
+
+    #[test]
+    fn test_protocol_to_index_matches_variants() {
+        for (index, protocol) in Protocol::variants().iter().enumerate() {
+            assert_eq!(index, protocol.to_index());
+        }
+    }
+
+    #[test]
+    fn test_protoentry_iter_all() {
+        let entry: ProtoEntry = "Relay=2 Cons=1-2 Link=1-2,4 HSDir=3".parse().unwrap();
+        let pairs: Vec<(&Protocol, Version)> = entry.iter_all().collect();
+
+        assert_eq!(entry.total_version_count(), pairs.len());
+        assert_eq!(
+            vec![
+                (&Protocol::Link, 1),
+                (&Protocol::Link, 2),
+                (&Protocol::Link, 4),
+                (&Protocol::Relay, 2),
+                (&Protocol::HSDir, 3),
+                (&Protocol::Cons, 1),
+                (&Protocol::Cons, 2),
+            ],
+            pairs
+        );
+
+        let empty = ProtoEntry::default();
+        assert_eq!(None, empty.iter_all().next());
+    }
+
+    #[test]
+    fn test_protocol_ord_is_alphabetical() {
+        let mut protocols: Vec<Protocol> = Protocol::variants().to_vec();
+        protocols.sort();
+
+        let names: Vec<String> = protocols.iter().map(|p| p.to_string()).collect();
+        let mut sorted_names: Vec<String> = names.clone();
+        sorted_names.sort();
+
+        assert_eq!(sorted_names, names);
+        assert!(Protocol::Link < Protocol::LinkAuth);
+        assert!(Protocol::HSRend > Protocol::HSDir);
+    }
+
+    #[test]
+    fn test_unknownprotocol_ord_matches_protocol_ord() {
+        for a in Protocol::variants() {
+            for b in Protocol::variants() {
+                let unknown_a: UnknownProtocol = a.clone().into();
+                let unknown_b: UnknownProtocol = b.clone().into();
+
+                assert_eq!(a.cmp(b), unknown_a.cmp(&unknown_b));
+            }
+        }
+
+        // The ordering is by bytes, so upper case sorts first.
+        let upper: UnknownProtocol = "Zebra".parse().unwrap();
+        let lower: UnknownProtocol = "apple".parse().unwrap();
+        assert!(upper < lower);
+    }
+
+    #[test]
+    fn test_unknownprotocol_is_valid_format() {
+        for name in &["Cons", "Fribble", "123", "1-2-3", "a", "Z-9"] {
+            assert!(UnknownProtocol::is_valid_format(name), "{}", name);
+            assert!(name.parse::<UnknownProtocol>().is_ok());
+        }
+
+        let longest: String = "a".repeat(MAX_PROTOCOL_NAME_LENGTH);
+        assert!(UnknownProtocol::is_valid_format(&longest));
+    }
+
+    #[test]
+    fn test_unknownprotocol_is_valid_format_too_long() {
+        let too_long: String = "a".repeat(MAX_PROTOCOL_NAME_LENGTH + 1);
+
+        assert!(!UnknownProtocol::is_valid_format(&too_long));
+    }
+
+    #[test]
+    fn test_unknownprotocol_is_valid_format_empty() {
+        assert!(!UnknownProtocol::is_valid_format(""));
+    }
+
+    #[test]
+    fn test_unknownprotocol_is_valid_format_illegal_characters() {
+        for name in &["a_b_c", "a b", "a,", "b.", "é", "Link=1", " Cons"] {
+            assert!(!UnknownProtocol::is_valid_format(name), "{}", name);
+            assert!(name.parse::<UnknownProtocol>().is_err());
+        }
+    }
+
+    #[test]
+    fn test_protocol_variants_round_trip_through_from_str() {
+        for protocol in Protocol::variants() {
+            assert_eq!(Ok(protocol.clone()), protocol.to_string().parse());
+        }
+    }
+
+    #[test]
+    fn test_protocol_variants_are_unique() {
+        let mut names: Vec<String> = Protocol::variants().iter().map(|p| p.to_string()).collect();
+
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(PROTOCOL_NAMES.len(), names.len());
+    }
+
+    macro_rules! assert_protoentry_is_parseable {
+        ($e:expr) => {
+            let protoentry: Result<ProtoEntry, ProtoverError> = $e.parse();
+
+            assert!(protoentry.is_ok(), format!("{:?}", protoentry.err()));
+        };
+    }
+
+    macro_rules! assert_protoentry_is_unparseable {
+        ($e:expr) => {
+            let protoentry: Result<ProtoEntry, ProtoverError> = $e.parse();
+
+            assert!(protoentry.is_err());
+        };
+    }
+
+    #[test]
+    fn test_protoentry_from_str_multiple_protocols_multiple_versions() {
+        assert_protoentry_is_parseable!("Cons=3-4 Link=1,3-5");
+    }
+
+    #[test]
+    fn test_protoentry_from_str_empty() {
+        assert_protoentry_is_unparseable!("");
+        assert_eq!(Err(ProtoverError::EmptyInput), "".parse::<ProtoEntry>());
+        assert_eq!(Err(ProtoverError::EmptyInput), " \t ".parse::<ProtoEntry>());
+    }
+
+    #[test]
+    fn test_protoentry_from_str_rejects_padded_versions() {
+        assert_protoentry_is_unparseable!("Cons=1\t");
+        assert_protoentry_is_unparseable!("Cons=1-2\n Link=1");
+        assert_protoentry_is_unparseable!("Cons=\t1");
+    }
+
+    #[test]
+    fn test_protoentry_from_str_malformed_is_not_empty_input() {
+        assert_eq!(
+            Err(ProtoverError::Unparseable),
+            "Cons".parse::<ProtoEntry>()
+        );
+        assert_eq!(
+            Err(ProtoverError::Unparseable),
+            " Cons=1".parse::<ProtoEntry>()
+        );
+    }
+
+    #[test]
+    fn test_protoentry_from_str_single_protocol_single_version() {
+        assert_protoentry_is_parseable!("HSDir=1");
+    }
+
+    #[test]
+    fn test_protoentry_from_str_unknown_protocol() {
+        assert_protoentry_is_unparseable!("Ducks=5-7,8");
+    }
+
+    #[test]
+    fn test_protoentry_from_str_allowed_number_of_versions() {
+        assert_protoentry_is_parseable!("Desc=1-4294967294");
+    }
+
+    #[test]
+    fn test_protoentry_from_str_too_many_versions() {
+        assert_protoentry_is_unparseable!("Desc=1-4294967295");
+    }
+
+    #[test]
+    fn test_protoentry_from_str_() {
+        assert_protoentry_is_unparseable!("");
+    }
+
+    #[test]
+    fn test_protoentry_all_supported_single_protocol_single_version() {
+        let protocol: UnvalidatedProtoEntry = "Cons=1".parse().unwrap();
+        let unsupported: Option<UnvalidatedProtoEntry> = protocol.all_supported();
+        assert_eq!(true, unsupported.is_none());
+    }
+
+    #[test]
+    fn test_protoentry_all_supported_multiple_protocol_multiple_versions() {
+        let protocols: UnvalidatedProtoEntry = "Link=3-4 Desc=2".parse().unwrap();
+        let unsupported: Option<UnvalidatedProtoEntry> = protocols.all_supported();
+        assert_eq!(true, unsupported.is_none());
+    }
+
+    #[test]
+    fn test_protoentry_all_supported_three_values() {
+        let protocols: UnvalidatedProtoEntry = "LinkAuth=1 Microdesc=1-2 Relay=2".parse().unwrap();
+        let unsupported: Option<UnvalidatedProtoEntry> = protocols.all_supported();
+        assert_eq!(true, unsupported.is_none());
+    }
+
+    #[test]
+    fn test_protoentry_all_supported_unknown_protocol() {
+        let protocols: UnvalidatedProtoEntry = "Wombat=9".parse().unwrap();
+        let unsupported: Option<UnvalidatedProtoEntry> = protocols.all_supported();
+        assert_eq!(true, unsupported.is_some());
+        assert_eq!("Wombat=9", &unsupported.unwrap().to_string());
+    }
+
+    #[test]
+    fn test_protoentry_all_supported_unsupported_high_version() {
+        let protocols: UnvalidatedProtoEntry = "HSDir=12-100".parse().unwrap();
+        let unsupported: Option<UnvalidatedProtoEntry> = protocols.all_supported();
+        assert_eq!(true, unsupported.is_some());
+        assert_eq!("HSDir=12-100", &unsupported.unwrap().to_string());
+    }
+
+    #[test]
+    fn test_protoentry_all_supported_unsupported_low_version() {
+        let protocols: UnvalidatedProtoEntry = "HSIntro=2-3".parse().unwrap();
+        let unsupported: Option<UnvalidatedProtoEntry> = protocols.all_supported();
+        assert_eq!(true, unsupported.is_some());
+        assert_eq!("HSIntro=2", &unsupported.unwrap().to_string());
+    }
+
+    #[test]
+    fn test_supported_protocols_include_every_variant() {
+        let supported: ProtoEntry = ProtoEntry::supported().unwrap();
+
+        // If this fails, a Protocol was added without updating
+        // get_supported_protocols_cstr().
+        for protocol in Protocol::variants() {
+            match supported.get(protocol) {
+                Some(versions) => assert!(!versions.is_empty(), "{} has no versions", protocol),
+                None => panic!(
+                    "{} is missing from get_supported_protocols_cstr()",
+                    protocol
+                ),
+            }
+        }
+        assert_eq!(Protocol::variants().len(), supported.protocol_count());
+    }
+
+    #[test]
+    fn test_protoentry_to_unvalidated_supported() {
+        let supported: ProtoEntry = ProtoEntry::supported().unwrap();
+        let unvalidated: UnvalidatedProtoEntry = supported.to_unvalidated();
+
+        assert_eq!(supported.to_string(), unvalidated.to_string());
+        assert_eq!(unvalidated, UnvalidatedProtoEntry::from(supported));
+    }
+
+    #[test]
+    fn test_protoentry_protocol_and_version_counts() {
+        let entry: ProtoEntry = "Cons=1-2 Desc=5 Link=1,3-5".parse().unwrap();
+
+        assert_eq!(3, entry.protocol_count());
+        assert_eq!(7, entry.total_version_count());
+        assert_eq!(
+            entry.to_unvalidated().total_version_count(),
+            entry.total_version_count()
+        );
+
+        let empty: ProtoEntry = ProtoEntry::default();
+
+        assert_eq!(0, empty.protocol_count());
+        assert_eq!(0, empty.total_version_count());
+    }
+
+    #[test]
+    fn test_unvalidatedprotoentry_protocol_and_version_counts() {
+        let entry: UnvalidatedProtoEntry = "Cons=1-2 Fribble=5 Link=1,3-5".parse().unwrap();
+
+        assert_eq!(3, entry.protocol_count());
+        assert_eq!(7, entry.total_version_count());
+
+        let empty: UnvalidatedProtoEntry = UnvalidatedProtoEntry::default();
+
+        assert_eq!(0, empty.protocol_count());
+        assert_eq!(0, empty.total_version_count());
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_unvalidatedprotoentry_len_is_total_version_count() {
+        let entry: UnvalidatedProtoEntry = "Cons=1-2 Link=1,3-5".parse().unwrap();
+
+        assert_eq!(entry.total_version_count(), entry.len());
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_protoentry_len_is_protocol_count() {
+        let entry: ProtoEntry = "Cons=1-2 Link=1,3-5".parse().unwrap();
+
+        assert_eq!(entry.protocol_count(), entry.len());
+    }
+
+    #[test]
+    fn test_protoentry_flat_iter() {
+        let entry: ProtoEntry = "Cons=1-2 Desc=5 Link=1,3-5".parse().unwrap();
+        let pairs: Vec<(&Protocol, Version)> = entry.flat_iter().collect();
+
+        assert_eq!(7, pairs.len());
+        assert!(pairs.contains(&(&Protocol::Cons, 2)));
+        assert!(pairs.contains(&(&Protocol::Desc, 5)));
+        assert!(pairs.contains(&(&Protocol::Link, 4)));
+        assert!(!pairs.contains(&(&Protocol::Link, 2)));
+    }
+
+    #[test]
+    fn test_protoentry_flat_iter_is_lazy() {
+        let entry: ProtoEntry = "Desc=1-4294967294".parse().unwrap();
+
+        assert_eq!(Some((&Protocol::Desc, 1)), entry.flat_iter().next());
+    }
+
+    #[test]
+    fn test_unvalidatedprotoentry_flat_iter() {
+        let entry: UnvalidatedProtoEntry = "Cons=1-2 Wombat=9,11 Fribble=".parse().unwrap();
+        let wombat: UnknownProtocol = "Wombat".parse().unwrap();
+        let pairs: Vec<(&UnknownProtocol, Version)> = entry.flat_iter().collect();
+
+        assert_eq!(entry.total_version_count(), pairs.len());
+        assert_eq!(4, pairs.len());
+        assert!(pairs.contains(&(&wombat, 9)));
+        assert!(pairs.contains(&(&wombat, 11)));
+        assert!(!pairs.contains(&(&wombat, 10)));
+    }
+
+    #[test]
+    fn test_unvalidatedprotoentry_flat_iter_many_versions() {
+        let entry: UnvalidatedProtoEntry = "Cons=1-2,4 Desc=7 Fribble=10-12,20 Link=1-5"
+            .parse()
+            .unwrap();
+        let fribble: UnknownProtocol = "Fribble".parse().unwrap();
+        let cons: UnknownProtocol = Protocol::Cons.into();
+        let pairs: Vec<(&UnknownProtocol, Version)> = entry.flat_iter().collect();
+
+        assert_eq!(13, pairs.len());
+        assert!(pairs.contains(&(&cons, 4)));
+        assert!(!pairs.contains(&(&cons, 3)));
+        assert!(pairs.contains(&(&fribble, 11)));
+        assert!(pairs.contains(&(&fribble, 20)));
+        assert!(!pairs.contains(&(&fribble, 13)));
+
+        let fribble_versions: Vec<Version> = pairs
+            .iter()
+            .filter(|&&(p, _)| p == &fribble)
+            .map(|&(_, v)| v)
+            .collect();
+        assert_eq!(vec![10, 11, 12, 20], fribble_versions);
+    }
+
+    #[test]
+    fn test_unvalidatedprotoentry_flat_iter_is_lazy() {
+        let entry: UnvalidatedProtoEntry = "Fribble=1-4294967294".parse().unwrap();
+        let fribble: UnknownProtocol = "Fribble".parse().unwrap();
+
+        assert_eq!(Some((&fribble, 1)), entry.flat_iter().next());
+    }
+
+    #[test]
+    fn test_protoentry_protocol_names() {
+        let entry: ProtoEntry = "Link=1 Cons=2 LinkAuth=3 Desc=4".parse().unwrap();
+
+        assert_eq!(
+            vec![
+                &Protocol::Cons,
+                &Protocol::Desc,
+                &Protocol::Link,
+                &Protocol::LinkAuth
+            ],
+            entry.protocol_names()
+        );
+        assert!(ProtoEntry::default().protocol_names().is_empty());
+    }
+
+    #[test]
+    fn test_unvalidatedprotoentry_protocol_names() {
+        let entry: UnvalidatedProtoEntry = "Foo-bar=1 Wombat=2 Foo=3 Cons=4".parse().unwrap();
+        let names: Vec<String> = entry
+            .protocol_names()
+            .iter()
+            .map(|name| name.to_string())
+            .collect();
+
+        assert_eq!(vec!["Cons", "Foo", "Foo-bar", "Wombat"], names);
+    }
+
+    #[test]
+    fn test_unvalidatedprotoentry_to_string_sorts_by_name() {
+        let entry: UnvalidatedProtoEntry = "Foo-bar=1 Foo=3 Foo1=2".parse().unwrap();
+
+        assert_eq!("Foo=3 Foo-bar=1 Foo1=2", entry.to_string());
+    }
+
+    #[test]
+    fn test_protoentry_into_iter() {
+        let entry: ProtoEntry = "Cons=1-2 Link=3-5".parse().unwrap();
+        let mut total: usize = 0;
+
+        for (protocol, versions) in &entry {
+            assert_eq!(Some(versions), entry.get(protocol));
+            total += versions.len();
+        }
+        assert_eq!(5, total);
+
+        let mut protocols: Vec<Protocol> = Vec::new();
+
+        for (protocol, _) in entry {
+            protocols.push(protocol);
+        }
+        protocols.sort_unstable();
+        assert_eq!(vec![Protocol::Cons, Protocol::Link], protocols);
+    }
+
+    #[test]
+    fn test_unvalidatedprotoentry_into_iter() {
+        let entry: UnvalidatedProtoEntry = "Cons=1-2 Wombat=9".parse().unwrap();
+        let mut total: usize = 0;
+
+        for (protocol, versions) in &entry {
+            assert_eq!(Some(versions), entry.get(protocol));
+            total += versions.len();
+        }
+        assert_eq!(entry.total_version_count(), total);
+
+        let mut names: Vec<String> = Vec::new();
+
+        for (protocol, _) in entry {
+            names.push(protocol.to_string());
+        }
+        names.sort_unstable();
+        assert_eq!(vec!["Cons", "Wombat"], names);
+    }
+
+    #[test]
+    fn test_unvalidatedprotoentry_from_str_merge_duplicates() {
+        let merged = UnvalidatedProtoEntry::from_str_merge_duplicates("Link=1-3 Link=4-5");
+        let last: UnvalidatedProtoEntry = "Link=1-3 Link=4-5".parse().unwrap();
+
+        assert_eq!("Link=1-5", merged.unwrap().to_string());
+        assert_eq!("Link=4-5", last.to_string());
+
+        let merged =
+            UnvalidatedProtoEntry::from_str_merge_duplicates("Cons=1 Link=7 Cons=3 Link=2");
+
+        assert_eq!("Cons=1,3 Link=2,7", merged.unwrap().to_string());
+    }
+
+    #[test]
+    fn test_unvalidatedprotoentry_from_str_merge_duplicates_errors() {
+        assert_eq!(
+            Err(ProtoverError::Unparseable),
+            UnvalidatedProtoEntry::from_str_merge_duplicates("Link=1-3 Link")
+        );
+        assert_eq!(
+            Err(ProtoverError::Overlap),
+            UnvalidatedProtoEntry::from_str_merge_duplicates("Link=1 Link=2-4,3")
+        );
+    }
+
+    #[test]
+    fn test_unvalidatedprotoentry_normalize() {
+        let mut entry: UnvalidatedProtoEntry = "Link=1-3,4-5 Cons=1,3".parse().unwrap();
+
+        entry.normalize();
+        assert_eq!("Cons=1,3 Link=1-5", entry.to_string());
+    }
+
+    #[test]
+    fn test_protoentry_eq_unvalidatedprotoentry() {
+        let supported: ProtoEntry = ProtoEntry::supported().unwrap();
+        let unvalidated: UnvalidatedProtoEntry = supported.to_string().parse().unwrap();
+
+        assert!(supported == unvalidated);
+        assert!(unvalidated == supported);
+
+        let mut modified: UnvalidatedProtoEntry = unvalidated.clone();
+        modified.insert(Protocol::Link.into(), "1-4".parse().unwrap());
+
+        assert!(supported != modified);
+        assert!(modified != supported);
+
+        let mut extra: UnvalidatedProtoEntry = unvalidated.clone();
+        extra.insert("Wombat".parse().unwrap(), "1".parse().unwrap());
+
+        assert!(supported != extra);
+        assert!(extra != supported);
+    }
+
+    #[test]
+    fn test_protoentry_add_protocol_version() {
+        let mut entry: ProtoEntry = "Link=1-2".parse().unwrap();
+
+        assert_eq!(Ok(()), entry.add_protocol_version(Protocol::Link, 3));
+        assert_eq!(Ok(()), entry.add_protocol_version(Protocol::Cons, 2));
+        assert_eq!("Cons=2 Link=1-3", entry.to_string());
+
+        let err = Err(ProtoverError::ExceedsMax);
+        assert_eq!(err, entry.add_protocol_version(Protocol::Desc, u32::MAX));
+        assert_eq!(None, entry.get(&Protocol::Desc));
+
+        let err = Err(ProtoverError::Overlap);
+        assert_eq!(err, entry.add_protocol_version(Protocol::Desc, 0));
+        assert_eq!(err, entry.add_protocol_version(Protocol::Link, 0));
+        assert_eq!(None, entry.get(&Protocol::Desc));
+        assert_eq!("Cons=2 Link=1-3", entry.to_string());
+    }
+
+    #[test]
+    fn test_protoentry_remove_protocol_version() {
+        let mut entry: ProtoEntry = "Cons=2 Link=1-3".parse().unwrap();
+
+        assert!(entry.remove_protocol_version(&Protocol::Link, 2));
+        assert!(!entry.remove_protocol_version(&Protocol::Link, 2));
+        assert!(!entry.remove_protocol_version(&Protocol::Desc, 1));
+        assert_eq!("Cons=2 Link=1,3", entry.to_string());
+
+        assert!(entry.remove_protocol_version(&Protocol::Cons, 2));
+        assert_eq!(None, entry.get(&Protocol::Cons));
+        assert_eq!(1, entry.protocol_count());
+    }
+
+    #[test]
+    fn test_unvalidatedprotoentry_add_remove_protocol_version() {
+        let mut entry: UnvalidatedProtoEntry = "Link=1".parse().unwrap();
+        let fribble: UnknownProtocol = "Fribble".parse().unwrap();
+        let link: UnknownProtocol = Protocol::Link.into();
+
+        assert_eq!(Ok(()), entry.add_protocol_version(fribble.clone(), 7));
+        assert_eq!("Fribble=7 Link=1", entry.to_string());
+
+        assert!(entry.remove_protocol_version(&fribble, 7));
+        assert!(!entry.remove_protocol_version(&fribble, 7));
+        assert!(entry.remove_protocol_version(&link, 1));
+        assert!(entry.is_empty());
+    }
+
+    #[test]
+    fn test_protoentry_check_required_protocols() {
+        let relay: ProtoEntry = "Cons=1-2 Link=3-5".parse().unwrap();
+        let required: ProtoEntry = "Cons=1-2 Desc=1-2 Link=2-4,6".parse().unwrap();
+        let missing = relay.check_required_protocols(&required);
+
+        assert_eq!(2, missing.len());
+        assert_protoset_contains_exactly!(missing[&Protocol::Desc], [1, 2]);
+        assert_protoset_contains_exactly!(missing[&Protocol::Link], [2, 6]);
+        assert_eq!(None, missing.get(&Protocol::Cons));
+
+        assert!(relay
+            .check_required_protocols(&ProtoEntry::default())
+            .is_empty());
+
+        let everything = ProtoEntry::default().check_required_protocols(&relay);
+        assert_eq!(relay.0, everything);
+    }
+
+    #[test]
+    fn test_protoentry_remove_below_version() {
+        let mut entry: ProtoEntry = "Cons=1-2 Desc=1-3,5-7 Link=1-5".parse().unwrap();
+
+        entry.remove_below_version(&Protocol::Desc, 4);
+        assert_eq!("Cons=1-2 Desc=5-7 Link=1-5", entry.to_string());
+
+        entry.remove_below_version(&Protocol::Desc, 6);
+        entry.remove_below_version(&Protocol::Link, 1);
+        entry.remove_below_version(&Protocol::Relay, 9);
+        assert_eq!("Cons=1-2 Desc=6-7 Link=1-5", entry.to_string());
+
+        entry.remove_below_version(&Protocol::Cons, 3);
+        assert_eq!(None, entry.get(&Protocol::Cons));
+        assert_eq!("Desc=6-7 Link=1-5", entry.to_string());
+    }
 
-        if proto_entries.is_empty() {
-            return final_output;
-        }
+    #[test]
+    fn test_protoentry_downgrade_to_max_version() {
+        let mut entry: ProtoEntry = "Cons=1-2 Desc=1-3,5-7 Link=3-5".parse().unwrap();
 
-        // parse and collect all of the protos and their versions and collect them
-        for vote in proto_entries {
-            // C_RUST_DIFFERS: This doesn't actually differ, bu this check on
-            // the total is here to make it match.  Because the C version calls
-            // expand_protocol_list() which checks if there would be too many
-            // subprotocols *or* individual version numbers, i.e. more than
-            // MAX_PROTOCOLS_TO_EXPAND, and does this *per vote*, we need to
-            // match it's behaviour and ensure we're not allowing more than it
-            // would.
-            if vote.len() > MAX_PROTOCOLS_TO_EXPAND {
-                continue;
-            }
+        entry.downgrade_to_max_version(&Protocol::Desc, 5);
+        assert_eq!("Cons=1-2 Desc=1-3,5 Link=3-5", entry.to_string());
 
-            for (protocol, versions) in vote.iter() {
-                let supported_vers: &mut HashMap<Version, usize> =
-                    all_count.entry(protocol.clone()).or_insert(HashMap::new());
+        entry.downgrade_to_max_version(&Protocol::Link, 2);
+        assert_eq!("Cons=1-2 Desc=1-3,5", entry.to_string());
+        assert!(entry.get(&Protocol::Link).is_none());
 
-                for version in versions.clone().expand() {
-                    let counter: &mut usize = supported_vers.entry(version).or_insert(0);
-                    *counter += 1;
-                }
-            }
-        }
+        entry.downgrade_to_max_version(&Protocol::Relay, 1);
+        entry.downgrade_to_max_version(&Protocol::Cons, u32::MAX);
+        assert_eq!("Cons=1-2 Desc=1-3,5", entry.to_string());
+    }
 
-        for (protocol, mut versions) in all_count {
-            // Go through and remove versions that are less than the threshold
-            versions.retain(|_, count| *count as usize >= *threshold);
+    #[test]
+    fn test_protoentry_cap_all_protocols_at() {
+        let mut entry: ProtoEntry = "Cons=1-2 Desc=1-3,5-7 Link=3-5 Relay=".parse().unwrap();
 
-            if versions.len() > 0 {
-                let voted_versions: Vec<Version> = versions.keys().cloned().collect();
-                let voted_protoset: ProtoSet = ProtoSet::from(voted_versions);
+        entry.cap_all_protocols_at(u32::MAX);
+        assert_eq!("Cons=1-2 Desc=1-3,5-7 Link=3-5", entry.to_string());
 
-                final_output.insert(protocol, voted_protoset);
-            }
-        }
-        final_output
+        entry.cap_all_protocols_at(3);
+        assert_eq!("Cons=1-2 Desc=1-3 Link=3", entry.to_string());
+
+        entry.cap_all_protocols_at(0);
+        assert!(entry.is_empty());
     }
-}
 
-/// Returns a boolean indicating whether the given protocol and version is
-/// supported in any of the existing Tor protocols
-///
-/// # Examples
-/// ```
-/// use protover::is_supported_here;
-/// use protover::Protocol;
-///
-/// let is_supported = is_supported_here(&Protocol::Link, &10);
-/// assert_eq!(false, is_supported);
-///
-/// let is_supported = is_supported_here(&Protocol::Link, &1);
-/// assert_eq!(true, is_supported);
-/// ```
-pub fn is_supported_here(proto: &Protocol, vers: &Version) -> bool {
-    let currently_supported: ProtoEntry = match ProtoEntry::supported() {
-        Ok(result) => result,
-        Err(_) => return false,
-    };
-    let supported_versions = match currently_supported.get(proto) {
-        Some(n) => n,
-        None => return false,
-    };
-    supported_versions.contains(vers)
-}
+    #[test]
+    fn test_protoentry_clone_with_only() {
+        let entry: ProtoEntry = "Cons=1-2 Desc=1-2 Link=1-5 Relay=1-3".parse().unwrap();
+        let link = entry.clone_with_only(&[Protocol::Link, Protocol::Relay, Protocol::Padding]);
 
-/// Since older versions of Tor cannot infer their own subprotocols,
-/// determine which subprotocols are supported by older Tor versions.
-///
-/// # Inputs
-///
-/// * `version`, a string comprised of "[0-9a-z.-]"
-///
-/// # Returns
-///
-/// A `&'static CStr` encoding a list of protocol names and supported
-/// versions. The string takes the following format:
-///
-/// "HSDir=1-1 LinkAuth=1"
-///
-/// This function returns the protocols that are supported by the version input,
-/// only for tor versions older than `FIRST_TOR_VERSION_TO_ADVERTISE_PROTOCOLS`
-/// (but not older than 0.2.4.19).  For newer tors (or older than 0.2.4.19), it
-/// returns an empty string.
-///
-/// # Note
-///
-/// This function is meant to be called for/within FFI code.  If you'd
-/// like to use this code in Rust, please see `compute_for_old_tor()`.
-//
-// C_RUST_COUPLED: src/rust/protover.c `compute_for_old_tor`
-pub(crate) fn compute_for_old_tor_cstr(version: &str) -> &'static CStr {
-    let empty: &'static CStr = cstr!("");
+        assert_eq!("Link=1-5 Relay=1-3", link.to_string());
+        assert!(link.get(&Protocol::Cons).is_none());
+        assert!(link.get(&Protocol::Desc).is_none());
+        assert!(link.get(&Protocol::Padding).is_none());
+
+        assert!(entry.clone_with_only(&[]).is_empty());
+        assert_eq!(entry, entry.clone_with_only(Protocol::variants()));
+    }
 
-    if c_tor_version_as_new_as(version, FIRST_TOR_VERSION_TO_ADVERTISE_PROTOCOLS) {
-        return empty;
+    #[test]
+    fn test_unvalidatedprotoentry_clone_with_only_names() {
+        let entry: UnvalidatedProtoEntry = "Fribble=1-2 Link=1-5 Wombat=9".parse().unwrap();
+        let filtered = entry.clone_with_only_names(&["Fribble", "link", "Cons"]);
+
+        assert_eq!("Fribble=1-2", filtered.to_string());
+        assert!(filtered.get(&"Link".parse().unwrap()).is_none());
+        assert!(filtered.get(&"Wombat".parse().unwrap()).is_none());
+        assert!(entry.clone_with_only_names(&[]).is_empty());
     }
-    if c_tor_version_as_new_as(version, "0.2.9.1-alpha") {
-        return cstr!(
-            "Cons=1-2 Desc=1-2 DirCache=1 HSDir=1 HSIntro=3 HSRend=1-2 \
-             Link=1-4 LinkAuth=1 Microdesc=1-2 Relay=1-2"
+
+    #[test]
+    fn test_protoentry_protocols_supported_above() {
+        let relay: ProtoEntry = "Cons=1-2 Desc=1-3,5 Link=1-5 Relay=1-2".parse().unwrap();
+        let minimum: ProtoEntry = "Cons=1-2 Desc=2 Link=1-3,5 HSDir=2".parse().unwrap();
+        let above = relay.protocols_supported_above(&minimum);
+
+        assert_eq!("Desc=1,3,5 Link=4 Relay=1-2", above.to_string());
+        assert!(above.get(&Protocol::Cons).is_none());
+        assert!(above.get(&Protocol::HSDir).is_none());
+
+        assert!(relay.protocols_supported_above(&relay).is_empty());
+        assert_eq!(
+            relay,
+            relay.protocols_supported_above(&ProtoEntry::default())
         );
     }
-    if c_tor_version_as_new_as(version, "0.2.7.5") {
-        return cstr!(
-            "Cons=1-2 Desc=1-2 DirCache=1 HSDir=1 HSIntro=3 HSRend=1 \
-             Link=1-4 LinkAuth=1 Microdesc=1-2 Relay=1-2"
+
+    #[test]
+    fn test_protoentry_count_protocols_missing_from() {
+        let relay: ProtoEntry = "Cons=1-2 Desc=1-2 Link=1-5".parse().unwrap();
+        // Five required pairs, of which Cons=2, Desc=2, and Link=4 are supported.
+        let required: ProtoEntry = "Cons=2 Desc=2-3 Link=4 Relay=2".parse().unwrap();
+
+        assert_eq!(5, required.total_version_count());
+        assert_eq!(2, relay.count_protocols_missing_from(&required));
+
+        let missing: usize = relay
+            .check_required_protocols(&required)
+            .values()
+            .map(|versions| versions.len())
+            .sum();
+        assert_eq!(missing, relay.count_protocols_missing_from(&required));
+
+        assert_eq!(
+            0,
+            relay.count_protocols_missing_from(&ProtoEntry::default())
+        );
+        assert_eq!(
+            5,
+            ProtoEntry::default().count_protocols_missing_from(&required)
         );
     }
-    if c_tor_version_as_new_as(version, "0.2.4.19") {
-        return cstr!(
-            "Cons=1 Desc=1 DirCache=1 HSDir=1 HSIntro=3 HSRend=1 \
-             Link=1-4 LinkAuth=1 Microdesc=1 Relay=1-2"
+
+    #[test]
+    fn test_protoentry_get_or_empty() {
+        let entry: ProtoEntry = "Cons=1-2 Link=1-5 Relay=".parse().unwrap();
+
+        assert_eq!(
+            Some(&*entry.get_or_empty(&Protocol::Link)),
+            entry.get(&Protocol::Link)
         );
+        assert_eq!(ProtoSet::default(), *entry.get_or_empty(&Protocol::Desc));
+        assert!(entry.get_or_empty(&Protocol::Relay).is_empty());
+
+        // Present protocols are borrowed, rather than cloned.
+        match entry.get_or_empty(&Protocol::Link) {
+            Cow::Borrowed(versions) => {
+                assert!(::std::ptr::eq(
+                    versions,
+                    entry.get(&Protocol::Link).unwrap()
+                ))
+            }
+            Cow::Owned(_) => panic!("Link should be borrowed"),
+        }
+
+        let unvalidated: UnvalidatedProtoEntry = entry.to_unvalidated();
+        let wombat: UnknownProtocol = "Wombat".parse().unwrap();
+        assert!(unvalidated.get_or_empty(&wombat).is_empty());
     }
-    empty
-}
 
-/// Since older versions of Tor cannot infer their own subprotocols,
-/// determine which subprotocols are supported by older Tor versions.
-///
-/// # Inputs
-///
-/// * `version`, a string comprised of "[0-9a-z.-]"
-///
-/// # Returns
-///
-/// A `Result` whose `Ok` value is an `&'static str` encoding a list of protocol
-/// names and supported versions. The string takes the following format:
-///
-/// "HSDir=1-1 LinkAuth=1"
-///
-/// This function returns the protocols that are supported by the version input,
-/// only for tor versions older than `FIRST_TOR_VERSION_TO_ADVERTISE_PROTOCOLS`.
-/// (but not older than 0.2.4.19).  For newer tors (or older than 0.2.4.19), its
-/// `Ok` `Result` contains an empty string.
-///
-/// Otherwise, its `Err` contains a `ProtoverError::Unparseable` if the
-/// `version` string was invalid utf-8.
-///
-/// # Note
-///
-/// This function is meant to be called for/within non-FFI Rust code.
-//
-// C_RUST_COUPLED: src/rust/protover.c `compute_for_old_tor`
-pub fn compute_for_old_tor(version: &str) -> Result<&'static str, ProtoverError> {
-    // .to_str() fails with a Utf8Error if it couldn't validate the
-    // utf-8, so convert that here into an Unparseable ProtoverError.
-    compute_for_old_tor_cstr(version)
-        .to_str()
-        .or(Err(ProtoverError::Unparseable))
-}
+    #[test]
+    fn test_protoentry_to_required_entry_at_version() {
+        let entry: ProtoEntry = "Cons=1-2 Desc=1-3,5-7 Link=3-5".parse().unwrap();
 
-#[cfg(test)]
-mod test {
-    use std::str::FromStr;
-    use std::string::ToString;
+        assert_eq!(entry, entry.to_required_entry_at_version(0));
+        assert_eq!(entry, entry.to_required_entry_at_version(1));
+        assert!(entry.to_required_entry_at_version(8).is_empty());
+        assert!(entry.to_required_entry_at_version(u32::MAX).is_empty());
 
-    use super::*;
+        let required = entry.to_required_entry_at_version(4);
+        assert_eq!("Desc=5-7 Link=4-5", required.to_string());
+        assert!(required.get(&Protocol::Cons).is_none());
 
-    macro_rules! parse_proto {
-        ($e:expr) => {{
-            let proto: Result<UnknownProtocol, _> = $e.parse();
-            let proto2 = UnknownProtocol::from_str_any_len($e);
-            assert_eq!(proto, proto2);
-            proto
-        }};
+        let required = entry.to_required_entry_at_version(6);
+        assert_eq!("Desc=6-7", required.to_string());
     }
 
     #[test]
-    fn test_protocol_from_str() {
-        assert!(parse_proto!("Cons").is_ok());
-        assert!(parse_proto!("123").is_ok());
-        assert!(parse_proto!("1-2-3").is_ok());
+    fn test_protoentry_is_empty() {
+        assert!(ProtoEntry::default().is_empty());
 
-        let err = Err(ProtoverError::InvalidProtocol);
-        assert_eq!(err, parse_proto!("a_b_c"));
-        assert_eq!(err, parse_proto!("a b"));
-        assert_eq!(err, parse_proto!("a,"));
-        assert_eq!(err, parse_proto!("b."));
-        assert_eq!(err, parse_proto!("é"));
+        let mut entry: ProtoEntry = "Cons=1-2 Link=5".parse().unwrap();
+        assert!(!entry.is_empty());
+
+        entry.remove(&Protocol::Cons);
+        assert!(!entry.is_empty());
+        assert!(entry.remove_protocol_version(&Protocol::Link, 5));
+        assert!(entry.is_empty());
+
+        let listed_without_versions: ProtoEntry = "Relay=".parse().unwrap();
+        assert!(listed_without_versions.is_empty());
     }
 
-    macro_rules! assert_protoentry_is_parseable {
-        ($e:expr) => {
-            let protoentry: Result<ProtoEntry, ProtoverError> = $e.parse();
+    #[test]
+    fn test_protoentry_insert_empty_protoset() {
+        let mut entry: ProtoEntry = "Cons=1-2 Link=1-5".parse().unwrap();
 
-            assert!(protoentry.is_ok(), format!("{:?}", protoentry.err()));
-        };
+        entry.insert(Protocol::Relay, ProtoSet::default());
+        assert_eq!(None, entry.get(&Protocol::Relay));
+
+        entry.insert(Protocol::Link, ProtoSet::default());
+        assert_eq!(None, entry.get(&Protocol::Link));
+        assert_eq!("Cons=1-2", entry.to_string());
     }
 
-    macro_rules! assert_protoentry_is_unparseable {
-        ($e:expr) => {
-            let protoentry: Result<ProtoEntry, ProtoverError> = $e.parse();
+    #[test]
+    fn test_unvalidatedprotoentry_is_empty() {
+        assert!(UnvalidatedProtoEntry::default().is_empty());
 
-            assert!(protoentry.is_err());
-        };
+        let wombat: UnknownProtocol = "Wombat".parse().unwrap();
+        let mut entry: UnvalidatedProtoEntry = "Link=1-5 Wombat=9".parse().unwrap();
+        assert!(!entry.is_empty());
+
+        entry.remove(&Protocol::Link.into());
+        assert!(!entry.is_empty());
+        assert!(entry.remove_protocol_version(&wombat, 9));
+        assert!(entry.is_empty());
+
+        entry.insert(wombat.clone(), ProtoSet::default());
+        assert_eq!(None, entry.get(&wombat));
+
+        let listed_without_versions: UnvalidatedProtoEntry = "Fribble=".parse().unwrap();
+        assert!(listed_without_versions.is_empty());
     }
 
     #[test]
-    fn test_protoentry_from_str_multiple_protocols_multiple_versions() {
-        assert_protoentry_is_parseable!("Cons=3-4 Link=1,3-5");
+    fn test_compute_for_old_tor_version_boundaries() {
+        let oldest = "Cons=1 Desc=1 DirCache=1 HSDir=1 HSIntro=3 HSRend=1 \
+                      Link=1-4 LinkAuth=1 Microdesc=1 Relay=1-2";
+        let v0_2_7_5 = "Cons=1-2 Desc=1-2 DirCache=1 HSDir=1 HSIntro=3 HSRend=1 \
+                        Link=1-4 LinkAuth=1 Microdesc=1-2 Relay=1-2";
+        let v0_2_9_1 = "Cons=1-2 Desc=1-2 DirCache=1 HSDir=1 HSIntro=3 HSRend=1-2 \
+                        Link=1-4 LinkAuth=1 Microdesc=1-2 Relay=1-2";
+        let cases = [
+            ("Tor 0.2.4.18-rc", ""),
+            ("Tor 0.2.4.19", oldest),
+            ("Tor 0.2.4.20", oldest),
+            ("Tor 0.2.7.4-rc", oldest),
+            ("Tor 0.2.7.5", v0_2_7_5),
+            ("Tor 0.2.7.6", v0_2_7_5),
+            ("Tor 0.2.8.12", v0_2_7_5),
+            ("Tor 0.2.9.1-alpha", v0_2_9_1),
+            ("Tor 0.2.9.1-alpha-dev", v0_2_9_1),
+            ("Tor 0.2.9.1-alpha (git-0123456789abcdef)", v0_2_9_1),
+            ("Tor 0.2.9.2-alpha", v0_2_9_1),
+            ("Tor 0.2.9.3-alpha", ""),
+            ("Tor 0.2.9.4-alpha", ""),
+            ("Tor 0.4.1.5", ""),
+            // Nonstandard and unparseable platforms are assumed to be new.
+            ("0.2.4.19", ""),
+            ("Wombat 0.2.7.5", ""),
+            ("Tor wombat", ""),
+        ];
+
+        for &(version, expected) in cases.iter() {
+            assert_eq!(Ok(expected), compute_for_old_tor(version), "{}", version);
+        }
     }
 
     #[test]
-    fn test_protoentry_from_str_empty() {
-        assert_protoentry_is_unparseable!("");
+    fn test_protoentry_remove_deprecated_by() {
+        let mut entry: ProtoEntry = "Cons=1-2 Link=1-5 Relay=1-2".parse().unwrap();
+
+        entry.remove_deprecated_by(&"Link=2-3".parse().unwrap());
+        assert_eq!("Cons=1-2 Link=1,4-5 Relay=1-2", entry.to_string());
+
+        entry.remove_deprecated_by(&"Cons=1-5 Link=1,4-5 Desc=1".parse().unwrap());
+        assert_eq!(None, entry.get(&Protocol::Cons));
+        assert_eq!(None, entry.get(&Protocol::Link));
+        assert_eq!("Relay=1-2", entry.to_string());
+
+        entry.remove_deprecated_by(&ProtoEntry::default());
+        assert_eq!("Relay=1-2", entry.to_string());
     }
 
     #[test]
-    fn test_protoentry_from_str_single_protocol_single_version() {
-        assert_protoentry_is_parseable!("HSDir=1");
+    fn test_protoentry_iter_mut_caps_versions() {
+        let mut entry: ProtoEntry = "Cons=1-2 Desc=1-5,8 Link=4-6".parse().unwrap();
+        let above_max: ProtoSet = "4-4294967294".parse().unwrap();
+
+        for (_, versions) in entry.iter_mut() {
+            *versions = versions.and_not_in(&above_max);
+        }
+        assert_protoset_contains_exactly!(entry.get(&Protocol::Cons).unwrap(), [1, 2]);
+        assert_protoset_contains_exactly!(entry.get(&Protocol::Desc).unwrap(), [1, 2, 3]);
+        assert_protoset_eq!(ProtoSet::default(), entry.get(&Protocol::Link).unwrap());
     }
 
     #[test]
-    fn test_protoentry_from_str_unknown_protocol() {
-        assert_protoentry_is_unparseable!("Ducks=5-7,8");
+    fn test_unvalidatedprotoentry_iter_mut_caps_versions() {
+        let mut entry: UnvalidatedProtoEntry = "Fribble=3-9 Link=1,5".parse().unwrap();
+        let above_max: ProtoSet = "5-4294967294".parse().unwrap();
+
+        for (_, versions) in entry.iter_mut() {
+            *versions = versions.and_not_in(&above_max);
+        }
+        assert_eq!("Fribble=3-4 Link=1", entry.to_string());
     }
 
     #[test]
-    fn test_protoentry_from_str_allowed_number_of_versions() {
-        assert_protoentry_is_parseable!("Desc=1-4294967294");
+    fn test_protoentry_format_for_descriptor_supported() {
+        // The test_linking_hack feature pretends that we have LinkAuth=1.
+        assert_eq!(
+            "Cons=1-2 Desc=1-2 DirCache=1-2 FlowCtrl=1 HSDir=1-2 HSIntro=3-4 HSRend=1-2 \
+             Link=1-5 LinkAuth=1,3 Microdesc=1-2 Padding=2 Relay=1-2",
+            ProtoEntry::supported().unwrap().format_for_descriptor()
+        );
     }
 
     #[test]
-    fn test_protoentry_from_str_too_many_versions() {
-        assert_protoentry_is_unparseable!("Desc=1-4294967295");
+    fn test_protoentry_format_for_descriptor_is_canonical() {
+        let entry: ProtoEntry = "Relay=2 Link=5-6,1-2,3-4 Cons=1,2,3 Desc=".parse().unwrap();
+
+        assert_eq!(
+            "Cons=1-3 Desc= Link=1-6 Relay=2",
+            entry.format_for_descriptor()
+        );
+        assert_eq!("", ProtoEntry::default().format_for_descriptor());
+    }
+
+    fn hash_of<T: Hash>(value: &T) -> u64 {
+        use std::collections::hash_map::DefaultHasher;
+
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
     }
 
     #[test]
-    fn test_protoentry_from_str_() {
-        assert_protoentry_is_unparseable!("");
+    fn test_protoentry_hash() {
+        let a: ProtoEntry = "Cons=1-2 Desc=1 Link=3-5 Relay=2".parse().unwrap();
+        let b: ProtoEntry = "Relay=2 Link=3-5 Desc=1 Cons=1-2".parse().unwrap();
+        let c: ProtoEntry = "Cons=1-2 Desc=1 Link=3-6 Relay=2".parse().unwrap();
+
+        assert_eq!(a, b);
+        assert_eq!(hash_of(&a), hash_of(&b));
+        assert_ne!(hash_of(&a), hash_of(&c));
+        assert_ne!(hash_of(&a), hash_of(&ProtoEntry::default()));
+
+        let set: ::std::collections::HashSet<ProtoEntry> = vec![a, b, c].into_iter().collect();
+        assert_eq!(2, set.len());
     }
 
     #[test]
-    fn test_protoentry_all_supported_single_protocol_single_version() {
-        let protocol: UnvalidatedProtoEntry = "Cons=1".parse().unwrap();
-        let unsupported: Option<UnvalidatedProtoEntry> = protocol.all_supported();
-        assert_eq!(true, unsupported.is_none());
+    fn test_unvalidatedprotoentry_hash() {
+        let a: UnvalidatedProtoEntry = "Fribble=1-2 Link=3-5 Wombat=9".parse().unwrap();
+        let b: UnvalidatedProtoEntry = "Wombat=9 Link=3-5 Fribble=1-2".parse().unwrap();
+        let c: UnvalidatedProtoEntry = "Fribble=1-2 Link=3-5 Wombat=8".parse().unwrap();
+
+        assert_eq!(a, b);
+        assert_eq!(hash_of(&a), hash_of(&b));
+        assert_ne!(hash_of(&a), hash_of(&c));
+
+        let mut counts: HashMap<UnvalidatedProtoEntry, usize> = HashMap::new();
+        for entry in vec![a.clone(), b, c] {
+            *counts.entry(entry).or_insert(0) += 1;
+        }
+        assert_eq!(2, counts.len());
+        assert_eq!(Some(&2), counts.get(&a));
     }
 
     #[test]
-    fn test_protoentry_all_supported_multiple_protocol_multiple_versions() {
-        let protocols: UnvalidatedProtoEntry = "Link=3-4 Desc=2".parse().unwrap();
-        let unsupported: Option<UnvalidatedProtoEntry> = protocols.all_supported();
-        assert_eq!(true, unsupported.is_none());
+    fn test_protoentry_count_supported_by() {
+        let ours: ProtoEntry = "Cons=1-2 Link=1-5".parse().unwrap();
+        let all: ProtoEntry = "Cons=1-9 Link=1-9 Relay=1".parse().unwrap();
+        let none: ProtoEntry = "Cons=3 Relay=1-2".parse().unwrap();
+        let some: ProtoEntry = "Cons=2 Link=4-9".parse().unwrap();
+
+        assert_eq!(7, ours.count_supported_by(&all));
+        assert_eq!(0, ours.count_supported_by(&none));
+        assert_eq!(3, ours.count_supported_by(&some));
+        assert_eq!(0, ours.count_supported_by(&ProtoEntry::default()));
+        assert_eq!(0, ProtoEntry::default().count_supported_by(&ours));
     }
 
     #[test]
-    fn test_protoentry_all_supported_three_values() {
-        let protocols: UnvalidatedProtoEntry = "LinkAuth=1 Microdesc=1-2 Relay=2".parse().unwrap();
-        let unsupported: Option<UnvalidatedProtoEntry> = protocols.all_supported();
-        assert_eq!(true, unsupported.is_none());
+    fn test_protoentry_count_supported_by_is_symmetric() {
+        let entries: Vec<ProtoEntry> = vec![
+            ProtoEntry::default(),
+            ProtoEntry::supported().unwrap(),
+            "Cons=1-2 Link=1-5".parse().unwrap(),
+            "Cons=2-3 Link=4-9 Relay=1".parse().unwrap(),
+            "Link=1,3,5,7 LinkAuth=1-3 HSDir=2".parse().unwrap(),
+        ];
+
+        for a in &entries {
+            for b in &entries {
+                let expected: usize = a
+                    .flat_iter()
+                    .filter(|&(protocol, version)| {
+                        b.get(protocol).map_or(false, |v| v.contains(&version))
+                    })
+                    .count();
+
+                assert_eq!(expected, a.count_supported_by(b), "{:?} & {:?}", a, b);
+                assert_eq!(expected, b.count_supported_by(a), "{:?} & {:?}", b, a);
+            }
+            assert_eq!(a.total_version_count(), a.count_supported_by(a));
+        }
     }
 
     #[test]
-    fn test_protoentry_all_supported_unknown_protocol() {
-        let protocols: UnvalidatedProtoEntry = "Wombat=9".parse().unwrap();
-        let unsupported: Option<UnvalidatedProtoEntry> = protocols.all_supported();
-        assert_eq!(true, unsupported.is_some());
-        assert_eq!("Wombat=9", &unsupported.unwrap().to_string());
+    #[allow(deprecated)]
+    fn test_protoentry_intersection_count_is_count_supported_by() {
+        let a: ProtoEntry = "Cons=1-2 Link=1-5".parse().unwrap();
+        let b: ProtoEntry = "Cons=2-3 Link=4-9 Relay=1".parse().unwrap();
+
+        assert_eq!(a.count_supported_by(&b), a.intersection_count(&b));
     }
 
     #[test]
-    fn test_protoentry_all_supported_unsupported_high_version() {
-        let protocols: UnvalidatedProtoEntry = "HSDir=12-100".parse().unwrap();
-        let unsupported: Option<UnvalidatedProtoEntry> = protocols.all_supported();
-        assert_eq!(true, unsupported.is_some());
-        assert_eq!("HSDir=12-100", &unsupported.unwrap().to_string());
+    fn test_protoentry_count_supported_by_huge_ranges() {
+        let ours: ProtoEntry = "Link=1-4294967294".parse().unwrap();
+        let theirs: ProtoEntry = "Link=100-4294967293".parse().unwrap();
+
+        assert_eq!(4294967194, ours.count_supported_by(&theirs));
     }
 
     #[test]
-    fn test_protoentry_all_supported_unsupported_low_version() {
-        let protocols: UnvalidatedProtoEntry = "HSIntro=2-3".parse().unwrap();
-        let unsupported: Option<UnvalidatedProtoEntry> = protocols.all_supported();
-        assert_eq!(true, unsupported.is_some());
-        assert_eq!("HSIntro=2", &unsupported.unwrap().to_string());
+    fn test_unvalidatedprotoentry_count_supported_by() {
+        let ours: UnvalidatedProtoEntry = "Fribble=1-2 Link=1-5".parse().unwrap();
+        let all: UnvalidatedProtoEntry = "Fribble=1-3 Link=1-5 Wombat=1".parse().unwrap();
+        let none: UnvalidatedProtoEntry = "Fribble=3 Wombat=1-2".parse().unwrap();
+        let some: UnvalidatedProtoEntry = "Fribble=2 Link=4-9".parse().unwrap();
+
+        assert_eq!(7, ours.count_supported_by(&all));
+        assert_eq!(0, ours.count_supported_by(&none));
+        assert_eq!(3, ours.count_supported_by(&some));
+    }
+
+    #[test]
+    fn test_protocol_is_hidden_service_related() {
+        let hs: Vec<&Protocol> = Protocol::variants()
+            .iter()
+            .filter(|p| p.is_hidden_service_related())
+            .collect();
+
+        assert_eq!(
+            vec![&Protocol::HSDir, &Protocol::HSIntro, &Protocol::HSRend],
+            hs
+        );
+    }
+
+    #[test]
+    fn test_protoentry_hidden_service_protocols() {
+        let supported = ProtoEntry::supported().unwrap();
+        let hs = supported.hidden_service_protocols();
+
+        assert_eq!(
+            vec![&Protocol::HSDir, &Protocol::HSIntro, &Protocol::HSRend],
+            hs.protocol_names()
+        );
+        assert_eq!(
+            supported.get(&Protocol::HSIntro),
+            hs.get(&Protocol::HSIntro)
+        );
+        assert!(ProtoEntry::default().hidden_service_protocols().is_empty());
+    }
+
+    #[test]
+    fn test_protoentry_debug() {
+        let entry: ProtoEntry = "Link=1-30 Cons=1-2,5".parse().unwrap();
+
+        assert_eq!(
+            "ProtoEntry {Cons: [1, 2, 5], Link: 1-30}",
+            format!("{:?}", entry)
+        );
+        assert_eq!("ProtoEntry {}", format!("{:?}", ProtoEntry::default()));
+    }
+
+    #[test]
+    fn test_protoentry_debug_supported() {
+        let debug = format!("{:?}", ProtoEntry::supported().unwrap());
+
+        assert!(debug.starts_with("ProtoEntry {Cons: [1, 2], Desc: [1, 2], "));
+        assert!(debug.contains("HSDir: "));
+        assert!(debug.contains("Relay: [1, 2]"));
     }
 
     #[test]
@@ -3,10 +3,22 @@
 
 extern crate protover;
 
+mod rng;
+
+use std::collections::BTreeMap;
+
 use protover::errors::ProtoverError;
+use protover::protoset::ProtoSet;
+use protover::protoset::Version;
 use protover::ProtoEntry;
+use protover::ProtoEntryBuilder;
+use protover::Protocol;
 use protover::ProtoverVote;
+use protover::UnknownProtocol;
 use protover::UnvalidatedProtoEntry;
+use protover::UnvalidatedProtoEntryBuilder;
+
+use rng::TestRng;
 
 #[test]
 fn parse_protocol_with_single_proto_and_single_version() {
@@ -243,6 +255,263 @@ fn protover_compute_vote_handles_invalid_proto_entries() {
     assert_eq!("Cons=1", ProtoverVote::compute(protocols, &2).to_string());
 }
 
+#[test]
+fn protover_compute_votes_per_version_counts_every_version() {
+    let protocols: &[UnvalidatedProtoEntry] = &[
+        "Cons=1-2 Link=3".parse().unwrap(),
+        "Cons=2 Link=3-4".parse().unwrap(),
+        "Link=4 Wombat=7".parse().unwrap(),
+    ];
+    let tally: BTreeMap<UnknownProtocol, BTreeMap<Version, usize>> =
+        ProtoverVote::compute_votes_per_version(protocols).tally();
+
+    let cons: BTreeMap<Version, usize> = vec![(1, 1), (2, 2)].into_iter().collect();
+    let link: BTreeMap<Version, usize> = vec![(3, 2), (4, 2)].into_iter().collect();
+    let wombat: BTreeMap<Version, usize> = vec![(7, 1)].into_iter().collect();
+
+    assert_eq!(3, tally.len());
+    assert_eq!(cons, tally[&Protocol::Cons.into()]);
+    assert_eq!(link, tally[&Protocol::Link.into()]);
+    assert_eq!(wombat, tally[&"Wombat".parse().unwrap()]);
+}
+
+#[test]
+fn protover_compute_is_deterministic() {
+    let protocols: &[UnvalidatedProtoEntry] = &[
+        "Wombat=1-3 Cons=1-2 Link=3 Fribble=9".parse().unwrap(),
+        "Cons=2 Link=3-4 Wombat=2".parse().unwrap(),
+        "Link=4 Wombat=7 Fribble=9,11".parse().unwrap(),
+    ];
+    let first: UnvalidatedProtoEntry = ProtoverVote::compute(protocols, &2);
+    let first_tally = ProtoverVote::compute_votes_per_version(protocols).tally();
+
+    for _ in 0..100 {
+        assert_eq!(first, ProtoverVote::compute(protocols, &2));
+        assert_eq!(
+            first_tally,
+            ProtoverVote::compute_votes_per_version(protocols).tally()
+        );
+    }
+
+    let names: Vec<String> = first_tally.keys().map(|p| p.to_string()).collect();
+    assert_eq!(vec!["Cons", "Fribble", "Link", "Wombat"], names);
+}
+
+#[test]
+fn protover_compute_votes_per_version_with_no_votes_is_empty() {
+    let tally = ProtoverVote::compute_votes_per_version(&[]).tally();
+
+    assert!(tally.is_empty());
+}
+
+#[test]
+fn protover_vote_to_string_sorts_protocols_and_versions() {
+    let protocols: &[UnvalidatedProtoEntry] = &[
+        "Link=3-4 Cons=1".parse().unwrap(),
+        "Link=3 Cons=1".parse().unwrap(),
+        "Cons=1".parse().unwrap(),
+    ];
+    let vote: ProtoverVote = ProtoverVote::compute_votes_per_version(protocols);
+
+    assert_eq!("Cons: 1->3; Link: 3->2, 4->1", vote.to_string());
+}
+
+#[test]
+fn protover_vote_round_trips_through_a_string() {
+    let protocols: &[UnvalidatedProtoEntry] = &[
+        "Desc=1-10,500 Cons=1,3-7,8".parse().unwrap(),
+        "Link=78,123-126 Cons=2-6,8 Fribble=".parse().unwrap(),
+    ];
+    let vote: ProtoverVote = ProtoverVote::compute_votes_per_version(protocols);
+    let vote_string: String = vote.to_string();
+    let parsed: ProtoverVote = vote_string.parse().unwrap();
+
+    assert_eq!(vote, parsed);
+    assert_eq!(vote_string, parsed.to_string());
+}
+
+#[test]
+fn protover_vote_round_trips_when_empty() {
+    let vote: ProtoverVote = ProtoverVote::default();
+
+    assert_eq!("", vote.to_string());
+    assert_eq!(Ok(vote), "".parse());
+}
+
+#[test]
+fn protover_vote_from_str_rejects_malformed_strings() {
+    let malformed = [
+        "Link",
+        "Link: 3",
+        "Link: 3->",
+        "Link: x->1",
+        "Link: 3->1,4->1",
+        "Link: 3->1; Link: 4->1",
+        "Link: 3->1, 3->2",
+    ];
+
+    for s in malformed.iter() {
+        assert_eq!(Err(ProtoverError::Unparseable), s.parse::<ProtoverVote>());
+    }
+}
+
+#[test]
+fn protover_compute_minimum_returns_everything_for_identical_entries() {
+    let protocols: &[UnvalidatedProtoEntry] = &[
+        "Cons=1-2 Link=3-5".parse().unwrap(),
+        "Cons=1-2 Link=3-5".parse().unwrap(),
+    ];
+    let listed = ProtoverVote::compute_minimum(protocols);
+    assert_eq!("Cons=1-2 Link=3-5", listed.to_string());
+}
+
+#[test]
+fn protover_compute_minimum_returns_intersection_for_differing_entries() {
+    let protocols: &[UnvalidatedProtoEntry] = &[
+        "Cons=1-2 Link=3-5 Wombat=9".parse().unwrap(),
+        "Cons=2-3 Link=1,5".parse().unwrap(),
+    ];
+    let listed = ProtoverVote::compute_minimum(protocols);
+    assert_eq!("Cons=2 Link=5", listed.to_string());
+}
+
+#[test]
+fn protover_compute_minimum_returns_empty_for_no_entries() {
+    let listed = ProtoverVote::compute_minimum(&[]);
+    assert_eq!("", listed.to_string());
+}
+
+#[test]
+fn protover_add_vote_and_finish_matches_compute() {
+    let protocols: &[UnvalidatedProtoEntry] = &[
+        "Desc=1-10,500 Cons=1,3-7,8".parse().unwrap(),
+        "Link=78,123-126 Cons=2-6,8 Fribble=".parse().unwrap(),
+        "Cons=1-8 Desc=5-6".parse().unwrap(),
+        "Sleen=1-4294967294".parse().unwrap(),
+    ];
+    let mut vote: ProtoverVote = ProtoverVote::default();
+
+    for entry in protocols {
+        vote.add_vote(entry);
+    }
+    for threshold in 0..protocols.len() + 2 {
+        assert_eq!(
+            ProtoverVote::compute(protocols, &threshold),
+            vote.finish(threshold)
+        );
+    }
+}
+
+#[test]
+fn protover_finish_can_continue_voting() {
+    let mut vote: ProtoverVote = ProtoverVote::default();
+
+    vote.add_vote(&"Link=1-3".parse().unwrap());
+    assert_eq!("Link=1-3", vote.finish(1).to_string());
+    assert_eq!("", vote.finish(2).to_string());
+
+    vote.add_vote(&"Link=2-4".parse().unwrap());
+    assert_eq!("Link=2-3", vote.finish(2).to_string());
+}
+
+#[test]
+fn protover_vote_reset_discards_earlier_votes() {
+    let mut vote: ProtoverVote = ProtoverVote::default();
+
+    vote.add_vote(&"Link=1-3 Cons=1".parse().unwrap());
+    vote.add_vote(&"Link=1-3".parse().unwrap());
+    vote.reset();
+
+    assert_eq!(ProtoverVote::default(), vote);
+    assert!(vote.finish(1).is_empty());
+
+    vote.add_vote(&"Link=4 Relay=2".parse().unwrap());
+    vote.add_vote(&"Link=4-5".parse().unwrap());
+
+    assert_eq!("Link=4", vote.finish(2).to_string());
+    assert_eq!("Link=4-5 Relay=2", vote.finish(1).to_string());
+}
+
+#[test]
+fn protover_compute_with_exclusion_drops_unanimous_protocols() {
+    let protocols: &[UnvalidatedProtoEntry] = &[
+        "Cons=1-2 Link=3-5 Fribble=1-9".parse().unwrap(),
+        "Cons=1-2 Link=3-5 Fribble=1-9".parse().unwrap(),
+    ];
+    let exclude: &[UnknownProtocol] = &["Fribble".parse().unwrap(), Protocol::Cons.into()];
+    let listed = ProtoverVote::compute_with_exclusion(protocols, 2, exclude);
+
+    assert_eq!("Link=3-5", listed.to_string());
+}
+
+#[test]
+fn protover_compute_with_exclusion_matches_compute_without_exclusions() {
+    let protocols: &[UnvalidatedProtoEntry] = &[
+        "Cons=1-2 Link=3-5".parse().unwrap(),
+        "Cons=2-3 Link=1,5 Wombat=9".parse().unwrap(),
+    ];
+    let not_voted: &[UnknownProtocol] = &["Sleen".parse().unwrap()];
+
+    for threshold in 0..4 {
+        let expected = ProtoverVote::compute(protocols, &threshold);
+
+        assert_eq!(
+            expected,
+            ProtoverVote::compute_with_exclusion(protocols, threshold, &[])
+        );
+        assert_eq!(
+            expected,
+            ProtoverVote::compute_with_exclusion(protocols, threshold, not_voted)
+        );
+    }
+}
+
+#[test]
+fn parse_consensus_proto_line() {
+    let line = "proto Cons=1-2 Desc=1-2 DirCache=1-2 HSDir=1-2 HSIntro=3-4 HSRend=1-2 \
+                Link=1-5 LinkAuth=1,3 Microdesc=1-2 Relay=1-2";
+    let entry: ProtoEntry = ProtoEntry::from_consensus_str(line).unwrap();
+
+    assert_eq!(&line["proto ".len()..], entry.to_string());
+    assert_eq!(10, entry.protocol_count());
+    assert_eq!(Some(5), entry.highest_version(&Protocol::Link));
+}
+
+#[test]
+fn parse_consensus_proto_line_without_keyword() {
+    let unparseable = Err(ProtoverError::Unparseable);
+
+    assert_eq!(
+        unparseable,
+        ProtoEntry::from_consensus_str("Cons=1-2 Link=1-5")
+    );
+    assert_eq!(unparseable, ProtoEntry::from_consensus_str("protoCons=1-2"));
+    assert_eq!(unparseable, ProtoEntry::from_consensus_str("pr Cons=1-2"));
+    assert_eq!(unparseable, ProtoEntry::from_consensus_str(""));
+}
+
+#[test]
+fn parse_consensus_proto_line_with_empty_remainder() {
+    assert_eq!(
+        Err(ProtoverError::Unparseable),
+        ProtoEntry::from_consensus_str("proto")
+    );
+    assert_eq!(
+        Err(ProtoverError::EmptyInput),
+        ProtoEntry::from_consensus_str("proto ")
+    );
+}
+
+#[test]
+fn parse_consensus_proto_line_with_bad_protocols() {
+    let line = "proto Cons=1-2 Fribble=1";
+
+    assert_eq!(
+        Err(ProtoverError::UnknownProtocol),
+        ProtoEntry::from_consensus_str(line)
+    );
+}
+
 #[test]
 fn parse_protocol_with_single_protocol_and_two_nonsequential_versions() {
     let _: ProtoEntry = "Desc=1,2".parse().unwrap();
@@ -402,3 +671,323 @@ fn protover_all_supported_over_maximum_limit() {
 
     assert_eq!(Err(ProtoverError::ExceedsMax), proto);
 }
+
+/// Make a valid `ProtoSet` with a few ranges, which are usually small, but
+/// sometimes very large or very far apart.
+fn arbitrary_protoset(rng: &mut TestRng) -> ProtoSet {
+    let mut pairs: Vec<(Version, Version)> = Vec::new();
+    let mut last_high: u64 = 0;
+
+    for _ in 0..rng.below(8) {
+        let scale: u64 = if rng.below(10) == 0 { 1 << 30 } else { 8 };
+        let low: u64 = last_high + 1 + rng.below(scale);
+        let high: u64 = low + rng.below(scale);
+
+        if high >= u32::max_value() as u64 {
+            break;
+        }
+        pairs.push((low as Version, high as Version));
+        last_high = high;
+    }
+    ProtoSet::from_slice(&pairs).unwrap()
+}
+
+/// Determine if `a` and `b` contain exactly the same versions, even if they
+/// are stored using different ranges.
+fn same_versions(a: &ProtoSet, b: &ProtoSet) -> bool {
+    a.is_subset_of(b) && b.is_subset_of(a)
+}
+
+#[test]
+fn protoset_randomised_string_round_trip() {
+    let mut rng = TestRng(0x2545_f491_4f6c_dd1d);
+
+    for _ in 0..1000 {
+        let protoset = arbitrary_protoset(&mut rng);
+        let string = protoset.to_string();
+        let parsed: ProtoSet = string.parse().unwrap();
+
+        assert_eq!(protoset, parsed);
+        assert_eq!(string, parsed.to_string());
+    }
+}
+
+#[test]
+fn protoentry_randomised_string_round_trip() {
+    let mut rng = TestRng(0x9e37_79b9_7f4a_7c15);
+
+    for _ in 0..1000 {
+        let mut entry = ProtoEntry::default();
+
+        for protocol in Protocol::variants() {
+            if rng.below(2) == 0 {
+                entry.insert(protocol.clone(), arbitrary_protoset(&mut rng));
+            }
+        }
+        // An empty string isn't a valid ProtoEntry.
+        if entry.is_empty() {
+            continue;
+        }
+        let string = entry.to_string();
+        let parsed: ProtoEntry = string.parse().unwrap();
+
+        assert_eq!(entry, parsed);
+        assert_eq!(string, parsed.to_string());
+    }
+}
+
+#[test]
+fn unvalidatedprotoentry_randomised_string_round_trip() {
+    let mut rng = TestRng(0xd1b5_4a32_d192_ed03);
+
+    for _ in 0..1000 {
+        let mut entry = UnvalidatedProtoEntry::default();
+
+        for _ in 0..1 + rng.below(6) {
+            let name: UnknownProtocol = format!("Proto{}", rng.below(10)).parse().unwrap();
+
+            entry.insert(name, arbitrary_protoset(&mut rng));
+        }
+        // Inserting empty sets can leave no protocols, and an empty string
+        // isn't a valid UnvalidatedProtoEntry.
+        if entry.is_empty() {
+            continue;
+        }
+        let string = entry.to_string();
+        let parsed: UnvalidatedProtoEntry = string.parse().unwrap();
+
+        assert_eq!(entry, parsed);
+        assert_eq!(string, parsed.to_string());
+    }
+}
+
+#[test]
+fn protoset_randomised_set_identities() {
+    let mut rng = TestRng(0x853c_49e6_748f_ea9b);
+
+    for _ in 0..1000 {
+        let a = arbitrary_protoset(&mut rng);
+        let b = arbitrary_protoset(&mut rng);
+
+        let union = a.union(&b);
+        let difference = a.and_not_in(&b);
+        // There is no intersection method, but a - (a - b) is the same thing.
+        let intersection = a.and_not_in(&difference);
+
+        assert!(union.is_superset_of(&a));
+        assert!(union.is_superset_of(&b));
+        assert!(same_versions(&union, &b.union(&a)));
+
+        assert!(difference.is_subset_of(&a));
+        assert!(same_versions(&difference, &difference.and_not_in(&b)));
+
+        assert!(intersection.is_subset_of(&a));
+        assert!(intersection.is_subset_of(&b));
+        assert!(same_versions(&a, &difference.union(&intersection)));
+
+        assert_eq!(a.len() + b.len(), union.len() + intersection.len());
+    }
+}
+
+#[test]
+fn protoentry_from_slice_of_strings() {
+    let entry = ProtoEntry::from_slice_of_strings(&["Link=1-5", "Cons=1-2", "Relay="]).unwrap();
+
+    // Empty ProtoSets are not stored.
+    assert_eq!("Cons=1-2 Link=1-5", entry.to_string());
+    assert_eq!(None, entry.get(&Protocol::Relay));
+    assert_eq!(
+        Ok(ProtoEntry::default()),
+        ProtoEntry::from_slice_of_strings(&[])
+    );
+}
+
+#[test]
+fn protoentry_from_slice_of_strings_duplicate_protocol() {
+    assert_eq!(
+        Err(ProtoverError::Unparseable),
+        ProtoEntry::from_slice_of_strings(&["Link=1-5", "Cons=1", "Link=6"])
+    );
+    assert_eq!(
+        Err(ProtoverError::Unparseable),
+        ProtoEntry::from_slice_of_strings(&["Link=", "Link=1"])
+    );
+}
+
+#[test]
+fn protoentry_from_slice_of_strings_invalid() {
+    let unparseable = Err(ProtoverError::Unparseable);
+
+    assert_eq!(
+        unparseable,
+        ProtoEntry::from_slice_of_strings(&["Link=1 Cons=1"])
+    );
+    assert_eq!(unparseable, ProtoEntry::from_slice_of_strings(&["Link"]));
+    assert_eq!(unparseable, ProtoEntry::from_slice_of_strings(&[""]));
+    assert_eq!(
+        Err(ProtoverError::UnknownProtocol),
+        ProtoEntry::from_slice_of_strings(&["Fribble=1"])
+    );
+}
+
+#[test]
+fn unvalidatedprotoentry_from_slice_of_strings() {
+    let entry =
+        UnvalidatedProtoEntry::from_slice_of_strings(&["Wombat=9", "Link=1-5", "Fribble=1-2"]);
+
+    assert_eq!("Fribble=1-2 Link=1-5 Wombat=9", entry.unwrap().to_string());
+
+    let entry = UnvalidatedProtoEntry::from_slice_of_strings(&["Wombat=", "Link=1-5"]);
+
+    assert_eq!("Link=1-5", entry.unwrap().to_string());
+}
+
+#[test]
+fn unvalidatedprotoentry_from_slice_of_strings_duplicate_protocol() {
+    assert_eq!(
+        Err(ProtoverError::Unparseable),
+        UnvalidatedProtoEntry::from_slice_of_strings(&["Wombat=9", "Wombat=9"])
+    );
+    assert_eq!(
+        Err(ProtoverError::Unparseable),
+        UnvalidatedProtoEntry::from_slice_of_strings(&["Wombat=9", "Wombat="])
+    );
+    assert_eq!(
+        Err(ProtoverError::Unparseable),
+        UnvalidatedProtoEntry::from_slice_of_strings(&["Wombat=9 Link=1"])
+    );
+}
+
+#[test]
+fn unvalidatedprotoentry_difference() {
+    let ours: UnvalidatedProtoEntry = "Cons=1-2 Link=1-5 Relay=1-2 Wombat=9 Fribble="
+        .parse()
+        .unwrap();
+    let theirs: UnvalidatedProtoEntry = "Link=2-3 Relay=1-4 Desc=1".parse().unwrap();
+
+    let difference = ours.difference(&theirs);
+
+    assert_eq!("Cons=1-2 Link=1,4-5 Wombat=9", difference.to_string());
+    assert_eq!(difference, &ours - &theirs);
+    assert_eq!("Desc=1 Relay=3-4", (&theirs - &ours).to_string());
+}
+
+#[test]
+fn unvalidatedprotoentry_difference_with_empty_and_self() {
+    let ours: UnvalidatedProtoEntry = "Link=1-5 Wombat=9".parse().unwrap();
+    let empty = UnvalidatedProtoEntry::default();
+
+    assert_eq!(ours, &ours - &empty);
+    assert!((&ours - &ours).is_empty());
+    assert!((&empty - &ours).is_empty());
+}
+
+#[test]
+fn protoentry_from_multiple() {
+    let complete: ProtoEntry = "Cons=1-2 Desc=1-2 DirCache=1-2 FlowCtrl=1-2 HSDir=1-2 \
+                                HSIntro=3-5 HSRend=1-2 Link=1-5 LinkAuth=1,3 Microdesc=1-2 \
+                                Padding=2 Relay=1-4"
+        .parse()
+        .unwrap();
+    let entry = ProtoEntry::from_multiple(&[
+        "Cons=1 Desc=1-2 DirCache=1 HSDir=1 HSIntro=3 HSRend=1 Link=1-3 LinkAuth=1 Microdesc=1",
+        "Cons=2 DirCache=2 HSDir=2 HSIntro=4-5 HSRend=2 Link=4-5 LinkAuth=3 Microdesc=2 \
+         Relay=1-4 Padding=2 FlowCtrl=1-2",
+    ])
+    .unwrap();
+
+    assert_eq!(complete, entry);
+    assert_eq!(Ok(ProtoEntry::default()), ProtoEntry::from_multiple(&[]));
+
+    // Merging never adds empty ProtoSets.
+    let entry = ProtoEntry::from_multiple(&["Cons=1", "Cons=2 Relay="]).unwrap();
+    assert_eq!("Cons=1-2", entry.to_string());
+}
+
+#[test]
+fn protoentry_from_multiple_invalid() {
+    assert_eq!(
+        Err(ProtoverError::UnknownProtocol),
+        ProtoEntry::from_multiple(&["Link=1", "Fribble=1"])
+    );
+    assert_eq!(
+        Err(ProtoverError::Unparseable),
+        ProtoEntry::from_multiple(&["Link=1", "Cons"])
+    );
+}
+
+#[test]
+fn protoentry_builder() {
+    let mut builder = ProtoEntryBuilder::new();
+
+    assert!(builder.build().is_empty());
+
+    builder
+        .add(Protocol::Cons, "1-2".parse().unwrap())
+        .add(Protocol::Link, "1-3".parse().unwrap())
+        .add(Protocol::Relay, ProtoSet::default());
+
+    // Empty ProtoSets are not stored.
+    let expected: ProtoEntry = "Cons=1-2 Link=1-3".parse().unwrap();
+    assert_eq!(expected, builder.build());
+    assert_eq!(None, builder.build().get(&Protocol::Relay));
+
+    // The builder can keep going after build().
+    builder.add(Protocol::Desc, "2".parse().unwrap());
+    assert_eq!("Cons=1-2 Desc=2 Link=1-3", builder.build().to_string());
+}
+
+#[test]
+fn protoentry_builder_merges_duplicate_protocols() {
+    let entry: ProtoEntry = ProtoEntryBuilder::new()
+        .add(Protocol::Link, "1-3".parse().unwrap())
+        .add(Protocol::Link, "2-5,7".parse().unwrap())
+        .add(Protocol::Cons, "2".parse().unwrap())
+        .add(Protocol::Cons, "1".parse().unwrap())
+        .build();
+
+    assert_eq!("Cons=1-2 Link=1-5,7", entry.to_string());
+    assert_eq!(Some(&"1-5,7".parse().unwrap()), entry.get(&Protocol::Link));
+}
+
+#[test]
+fn unvalidatedprotoentry_builder() {
+    let wombat: UnknownProtocol = "Wombat".parse().unwrap();
+    let entry: UnvalidatedProtoEntry = UnvalidatedProtoEntryBuilder::new()
+        .add(wombat.clone(), "9".parse().unwrap())
+        .add(Protocol::Link.into(), "1-5".parse().unwrap())
+        .add(wombat.clone(), "1-2,9".parse().unwrap())
+        .build();
+    let expected: UnvalidatedProtoEntry = "Link=1-5 Wombat=1-2,9".parse().unwrap();
+
+    assert_eq!(expected, entry);
+    assert_eq!(3, entry.get(&wombat).unwrap().len());
+    assert!(UnvalidatedProtoEntryBuilder::new().build().is_empty());
+}
+
+#[test]
+fn protover_compute_vote_with_floor() {
+    let protocols: &[UnvalidatedProtoEntry] = &[
+        "Cons=2 Link=3-5".parse().unwrap(),
+        "Cons=2 Link=4-5 Wombat=9".parse().unwrap(),
+        "Link=5".parse().unwrap(),
+    ];
+    let floor: UnvalidatedProtoEntry = "Cons=1 Desc=1-2 Link=1-3 Wombat=".parse().unwrap();
+
+    let plain = ProtoverVote::compute(protocols, &2);
+    assert_eq!("Cons=2 Link=4-5", plain.to_string());
+
+    let floored = ProtoverVote::compute_with_floor(protocols, 2, &floor);
+    assert_eq!("Cons=1-2 Desc=1-2 Link=1-5", floored.to_string());
+    assert!(floored.get(&"Wombat".parse().unwrap()).is_none());
+
+    // Floor versions appear even when nobody votes.
+    assert_eq!(
+        "Cons=1 Desc=1-2 Link=1-3",
+        ProtoverVote::compute_with_floor(&[], 1, &floor).to_string()
+    );
+    assert_eq!(
+        plain,
+        ProtoverVote::compute_with_floor(protocols, 2, &UnvalidatedProtoEntry::default())
+    );
+}
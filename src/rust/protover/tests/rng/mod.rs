@@ -0,0 +1,26 @@
+// Copyright (c) 2019, The Tor Project, Inc. */
+// See LICENSE for licensing information */
+//! A small pseudo-random number generator for the protover tests.
+//!
+//! This file is shared by the unit tests in `protoset.rs` and the
+//! integration tests in `tests/protover.rs`, which each use some of it.
+
+#![allow(dead_code)]
+
+/// A small xorshift pseudo-random number generator, so that the randomised
+/// tests are repeatable, and don't need any extra dependencies.
+pub struct TestRng(pub u64);
+
+impl TestRng {
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Get a number less than `n`, which must not be zero.
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+}
@@ -18,23 +18,50 @@ pub trait Smartlist<T> {
 #[repr(C)]
 pub struct Stringlist {
     pub list: *const *const c_char,
+    #[deprecated(note = "use Stringlist::num_used() instead")]
     pub num_used: c_int,
+    #[deprecated(note = "use Stringlist::capacity() instead")]
     pub capacity: c_int,
 }
 
+impl Stringlist {
+    /// Get the number of elements in use in this smartlist.
+    ///
+    /// # Panics
+    ///
+    /// If the C smartlist has a negative `num_used`, which means it is
+    /// corrupt.
+    #[allow(deprecated)]
+    pub fn num_used(&self) -> usize {
+        assert!(self.num_used >= 0, "smartlist num_used is negative");
+        self.num_used as usize
+    }
+
+    /// Get the number of elements this smartlist has allocated space for.
+    ///
+    /// # Panics
+    ///
+    /// If the C smartlist has a negative `capacity`, which means it is
+    /// corrupt.
+    #[allow(deprecated)]
+    pub fn capacity(&self) -> usize {
+        assert!(self.capacity >= 0, "smartlist capacity is negative");
+        self.capacity as usize
+    }
+}
+
 impl Smartlist<String> for Stringlist {
     fn get_list(&self) -> Vec<String> {
         let empty: Vec<String> = Vec::new();
         let mut rust_list: Vec<String> = Vec::new();
 
-        if self.list.is_null() || self.num_used == 0 {
+        if self.list.is_null() || self.num_used() == 0 {
             return empty;
         }
 
         // unsafe, as we need to extract the smartlist list into a vector of
         // pointers, and then transform each element into a Rust string.
-        let elems: &[*const c_char] =
-            unsafe { slice::from_raw_parts(self.list, self.num_used as usize) };
+        let elems: &[*const c_char] = unsafe { slice::from_raw_parts(self.list, self.num_used()) };
 
         for elem in elems.iter() {
             if elem.is_null() {
@@ -60,6 +87,7 @@ impl Smartlist<String> for Stringlist {
 // TODO: CHK: this module maybe should be tested from a test in C with a
 // smartlist as defined in tor.
 #[cfg(test)]
+#[allow(deprecated)] // The tests build Stringlists like C does, using the fields.
 mod test {
     #[test]
     fn test_get_list_of_strings() {
@@ -112,4 +140,36 @@ mod test {
             assert_eq!("b", &data[1]);
         }
     }
+
+    #[test]
+    fn test_num_used_and_capacity() {
+        use std::ptr;
+
+        use super::Stringlist;
+
+        let sl = Stringlist {
+            list: ptr::null(),
+            num_used: 3,
+            capacity: 16,
+        };
+
+        assert_eq!(3, sl.num_used());
+        assert_eq!(16, sl.capacity());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_negative_num_used_panics() {
+        use std::ptr;
+
+        use super::Stringlist;
+
+        let sl = Stringlist {
+            list: ptr::null(),
+            num_used: -1,
+            capacity: 0,
+        };
+
+        sl.num_used();
+    }
 }
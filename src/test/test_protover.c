@@ -347,6 +347,52 @@ test_protover_all_supported(void *arg)
   tor_free(msg);
 }
 
+static void
+test_protover_is_supported_here_with_detail(void *arg)
+{
+  (void)arg;
+  char *msg = NULL;
+
+  /* Nothing is required, or everything required is supported */
+  tt_int_op(protover_is_supported_here_with_detail(NULL, &msg), OP_EQ, 1);
+  tt_ptr_op(msg, OP_EQ, NULL);
+  tt_int_op(protover_is_supported_here_with_detail("", &msg), OP_EQ, 1);
+  tt_ptr_op(msg, OP_EQ, NULL);
+  tt_int_op(protover_is_supported_here_with_detail("Link=3-4 Desc=2", &msg),
+            OP_EQ, 1);
+  tt_ptr_op(msg, OP_EQ, NULL);
+
+  /* Some required versions are missing */
+  tt_int_op(protover_is_supported_here_with_detail("Link=3-999", &msg),
+            OP_EQ, 0);
+  tt_str_op(msg, OP_EQ, "Link=6-999");
+  tor_free(msg);
+  tt_int_op(protover_is_supported_here_with_detail("Link=999", NULL),
+            OP_EQ, 0);
+
+  /* Unknown protocols and unparseable lists are errors, and leave msg
+   * unchanged */
+  tt_int_op(protover_is_supported_here_with_detail("Wombat=9", &msg),
+            OP_EQ, -1);
+  tt_ptr_op(msg, OP_EQ, NULL);
+  tt_int_op(protover_is_supported_here_with_detail("Link=3-4 Wombat=9",
+                                                   &msg),
+            OP_EQ, -1);
+  tt_ptr_op(msg, OP_EQ, NULL);
+  tt_int_op(protover_is_supported_here_with_detail("Fribble=", &msg),
+            OP_EQ, -1);
+  tt_ptr_op(msg, OP_EQ, NULL);
+  tt_int_op(protover_is_supported_here_with_detail("Link=fred", &msg),
+            OP_EQ, -1);
+  tt_ptr_op(msg, OP_EQ, NULL);
+  tt_int_op(protover_is_supported_here_with_detail("Fribble", &msg),
+            OP_EQ, -1);
+  tt_ptr_op(msg, OP_EQ, NULL);
+
+ done:
+  tor_free(msg);
+}
+
 static void
 test_protover_list_supports_protocol_returns_true(void *arg)
 {
@@ -639,6 +685,25 @@ test_protover_vote_roundtrip(void *args)
   tor_free(result);
 }
 
+static void
+test_protover_get_protocol_list(void *arg)
+{
+  (void)arg;
+  int len = -1;
+  const char *protocols;
+
+  protocols = protover_get_protocol_list(&len);
+  tt_assert(protocols);
+  tt_str_op(protocols, OP_EQ, protover_get_supported_protocols());
+  tt_int_op(len, OP_EQ, strlen(protocols));
+
+  /* out_len is optional */
+  tt_str_op(protover_get_protocol_list(NULL), OP_EQ, protocols);
+
+ done:
+  ;
+}
+
 static void
 test_protover_vote_roundtrip_ours(void *args)
 {
@@ -684,10 +749,12 @@ struct testcase_t protover_tests[] = {
   PV_TEST(parse_fail, 0),
   PV_TEST(vote, 0),
   PV_TEST(all_supported, 0),
+  PV_TEST(is_supported_here_with_detail, 0),
   PV_TEST(list_supports_protocol_for_unsupported_returns_false, 0),
   PV_TEST(list_supports_protocol_returns_true, 0),
   PV_TEST(supports_version, 0),
   PV_TEST(supported_protocols, 0),
+  PV_TEST(get_protocol_list, 0),
   PV_TEST(vote_roundtrip, 0),
   PV_TEST(vote_roundtrip_ours, 0),
   END_OF_TESTCASES